@@ -0,0 +1,104 @@
+use std::sync::LazyLock;
+
+pub(crate) const BURST_WINDOW_SECONDS: i64 = 10;
+pub(crate) const BURST_THRESHOLD: i64 = 20;
+
+pub(crate) const REASON_USER_AGENT: &str = "user_agent";
+pub(crate) const REASON_MISSING_USER_AGENT: &str = "missing_user_agent";
+pub(crate) const REASON_HEAD_REQUEST: &str = "head_request";
+pub(crate) const REASON_MISSING_ACCEPT_LANGUAGE: &str = "missing_accept_language";
+pub(crate) const REASON_BURST: &str = "burst";
+
+static BOT_SIGNATURES: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    include_str!("bot_signatures.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+/// Returns why a click looks automated, checked from the most to the least reliable signal.
+/// Burst detection needs shared state and is applied by the caller on top of this.
+pub(crate) fn classify_bot(
+    user_agent: Option<&str>,
+    is_head_request: bool,
+    accept_language: Option<&str>,
+) -> Option<&'static str> {
+    let user_agent = match user_agent.map(str::trim) {
+        Some(user_agent) if !user_agent.is_empty() => user_agent.to_lowercase(),
+        _ => return Some(REASON_MISSING_USER_AGENT),
+    };
+
+    if BOT_SIGNATURES
+        .iter()
+        .any(|signature| user_agent.contains(signature))
+    {
+        return Some(REASON_USER_AGENT);
+    }
+    if is_head_request {
+        return Some(REASON_HEAD_REQUEST);
+    }
+    if accept_language.is_none_or(|value| value.trim().is_empty()) {
+        return Some(REASON_MISSING_ACCEPT_LANGUAGE);
+    }
+
+    None
+}
+
+pub(crate) fn burst_key(fingerprint: &str, timestamp: i64) -> String {
+    format!(
+        "click_burst:{}:{}",
+        fingerprint,
+        timestamp / BURST_WINDOW_SECONDS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::bots::{
+        burst_key, classify_bot, REASON_HEAD_REQUEST, REASON_MISSING_ACCEPT_LANGUAGE,
+        REASON_MISSING_USER_AGENT, REASON_USER_AGENT,
+    };
+
+    const BROWSER_USER_AGENT: &str =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36";
+
+    #[test]
+    fn classify_bot_matches_signatures() {
+        // Act
+        let slack = classify_bot(
+            Some("Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)"),
+            false,
+            Some("en"),
+        );
+        let whatsapp = classify_bot(Some("WhatsApp/2.23.20.0"), false, Some("en"));
+        let curl = classify_bot(Some("curl/8.4.0"), false, None);
+
+        // Assert
+        assert_eq!(slack, Some(REASON_USER_AGENT));
+        assert_eq!(whatsapp, Some(REASON_USER_AGENT));
+        assert_eq!(curl, Some(REASON_USER_AGENT));
+    }
+
+    #[test]
+    fn classify_bot_applies_heuristics() {
+        // Act
+        let missing_user_agent = classify_bot(Some("  "), false, Some("en"));
+        let head = classify_bot(Some(BROWSER_USER_AGENT), true, Some("en"));
+        let missing_language = classify_bot(Some(BROWSER_USER_AGENT), false, None);
+        let human = classify_bot(Some(BROWSER_USER_AGENT), false, Some("en-US,en;q=0.9"));
+
+        // Assert
+        assert_eq!(missing_user_agent, Some(REASON_MISSING_USER_AGENT));
+        assert_eq!(head, Some(REASON_HEAD_REQUEST));
+        assert_eq!(missing_language, Some(REASON_MISSING_ACCEPT_LANGUAGE));
+        assert_eq!(human, None);
+    }
+
+    #[test]
+    fn burst_key_buckets_by_window() {
+        // Act & Assert
+        assert_eq!(burst_key("abc", 1_000), burst_key("abc", 1_009));
+        assert_ne!(burst_key("abc", 1_009), burst_key("abc", 1_010));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::analytics::referrer::{classify_referrer, referrer_domain};
+use crate::analytics::user_agent::parse_user_agent;
+use crate::models::click_event_models::ClickEventModel;
+use url_shortener_database::models::click_models::Click;
+
+/// Every instance publishes its clicks here and relays them to its own live viewers.
+pub(crate) const CLICK_EVENTS_CHANNEL: &str = "click_events";
+
+pub(crate) fn click_event(click: &Click) -> ClickEventModel {
+    let referrer_domain = click.referrer.as_deref().and_then(referrer_domain);
+    let user_agent = parse_user_agent(click.user_agent.as_deref());
+
+    ClickEventModel {
+        code: click.url_id.clone(),
+        clicked_at: click.clicked_at,
+        referrer_category: classify_referrer(referrer_domain.as_deref()),
+        referrer_domain,
+        browser: user_agent.browser,
+        os: user_agent.os,
+        device: user_agent.device,
+        country: click.country.clone(),
+        source: click.source.clone(),
+        is_bot: click.is_bot,
+    }
+}
@@ -0,0 +1,73 @@
+use rand::distr::Alphanumeric;
+use rand::{rng, Rng};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Query parameter carrying the click id to the destination, sent back to us on conversion.
+pub(crate) const CLICK_ID_PARAM: &str = "sclid";
+pub(crate) const TRACKING_ENABLED: &str = "1";
+pub(crate) const TRACKING_DISABLED: &str = "0";
+
+const CLICK_ID_LENGTH: usize = 24;
+/// How long after the click a conversion is still attributed to it.
+pub(crate) const CLICK_ID_TTL_SECS: i64 = 30 * 24 * 60 * 60;
+
+pub(crate) fn conversion_tracking_key(short_url: &str) -> String {
+    format!("conversion_tracking:{}", short_url)
+}
+
+/// Maps the click id to its link from the moment of the redirect, the click itself is only
+/// stored once the queue is flushed and may be dropped or purged.
+pub(crate) fn click_url_key(click_id: &str) -> String {
+    format!("click_url:{}", click_id)
+}
+
+pub(crate) fn new_click_id() -> String {
+    rng()
+        .sample_iter(&Alphanumeric)
+        .take(CLICK_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Appends the click id to the destination, keeping whatever query and fragment it already has.
+pub(crate) fn with_click_id(destination: &str, click_id: &str) -> String {
+    match Url::parse(destination) {
+        Ok(mut url) => {
+            url.query_pairs_mut().append_pair(CLICK_ID_PARAM, click_id);
+            url.to_string()
+        }
+        Err(_) => destination.to_string(),
+    }
+}
+
+/// Compares digests rather than the tokens themselves, so the time taken says nothing about the secret.
+pub(crate) fn postback_token_matches(expected: &str, given: &str) -> bool {
+    Sha256::digest(expected.as_bytes()) == Sha256::digest(given.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::conversions::{postback_token_matches, with_click_id};
+
+    #[test]
+    fn with_click_id_keeps_existing_query_and_fragment() {
+        // Act & Assert
+        assert_eq!(
+            with_click_id("https://www.google.com/a?b=c#d", "abc"),
+            "https://www.google.com/a?b=c&sclid=abc#d"
+        );
+        assert_eq!(
+            with_click_id("https://www.google.com", "abc"),
+            "https://www.google.com/?sclid=abc"
+        );
+    }
+
+    #[test]
+    fn postback_token_matches_only_same_token() {
+        // Act & Assert
+        assert!(postback_token_matches("secret", "secret"));
+        assert!(!postback_token_matches("secret", "secreT"));
+        assert!(!postback_token_matches("secret", ""));
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::distr::Alphanumeric;
+use rand::{rng, Rng};
+
+pub(crate) const COUNTER_PREFIX: &str = "click_count:";
+pub(crate) const PENDING_COUNTERS_KEY: &str = "click_counts:pending";
+pub(crate) const CLAIMED_COUNTERS_KEY: &str = "click_counts:claimed";
+const UNFLUSHED_COUNTER_PREFIX: &str = "click_count_unflushed:";
+
+const FLUSH_ID_SUFFIX_LENGTH: usize = 8;
+
+/// Daily counters are tracked by member, the `{code}:{day}` suffix of their key,
+/// which is also what the pending set and the claim hashes store.
+pub(crate) fn daily_counter_member(short_url: &str, day: NaiveDate) -> String {
+    format!("{}:{}", short_url, day.format("%Y-%m-%d"))
+}
+
+pub(crate) fn daily_counter_key(short_url: &str, day: NaiveDate) -> String {
+    format!("{}{}", COUNTER_PREFIX, daily_counter_member(short_url, day))
+}
+
+/// Every click of the link that has not reached Postgres yet, it is incremented with the daily
+/// counter and only decremented once a flush of that counter is released.
+pub(crate) fn unflushed_counter_key(short_url: &str) -> String {
+    format!("{}{}", UNFLUSHED_COUNTER_PREFIX, short_url)
+}
+
+pub(crate) fn parse_daily_counter_member(member: &str) -> Option<(String, NaiveDate)> {
+    let (short_url, day) = member.rsplit_once(':')?;
+    let day = NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?;
+    Some((short_url.to_string(), day))
+}
+
+pub(crate) fn claim_key(flush_id: &str) -> String {
+    format!("{}:{}", CLAIMED_COUNTERS_KEY, flush_id)
+}
+
+pub(crate) fn new_flush_id(now: DateTime<Utc>) -> String {
+    let suffix: String = rng()
+        .sample_iter(&Alphanumeric)
+        .take(FLUSH_ID_SUFFIX_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}-{}", now.timestamp_millis(), suffix)
+}
+
+/// A claim is only considered abandoned once it is older than `grace`, so a
+/// flush still running on another host is never recovered underneath it.
+pub(crate) fn is_abandoned(flush_id: &str, now: DateTime<Utc>, grace: Duration) -> bool {
+    flush_id
+        .split_once('-')
+        .and_then(|(millis, _)| millis.parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_millis)
+        .is_none_or(|started| now - started > grace)
+}
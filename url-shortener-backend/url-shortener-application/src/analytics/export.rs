@@ -0,0 +1,161 @@
+use crate::analytics::privacy::IpExportMode;
+use crate::models::export_models::{ClickExportModel, ExportFormat};
+use url_shortener_database::models::click_models::ClickRecord;
+
+const CSV_COLUMNS: &[&str] = &[
+    "code",
+    "clicked_at",
+    "referrer",
+    "user_agent",
+    "destination",
+    "is_bot",
+    "bot_reason",
+    "country",
+    "region",
+    "city",
+    "source",
+];
+const CSV_IP_HASH_COLUMN: &str = "ip_hash";
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+pub(crate) fn click_export(click: ClickRecord, ip_export_mode: IpExportMode) -> ClickExportModel {
+    ClickExportModel {
+        code: click.url_id,
+        clicked_at: click.clicked_at,
+        referrer: click.referrer,
+        user_agent: click.user_agent,
+        destination: click.destination,
+        is_bot: click.is_bot,
+        bot_reason: click.bot_reason,
+        country: click.country,
+        region: click.region,
+        city: click.city,
+        source: click.source,
+        ip_hash: match ip_export_mode {
+            IpExportMode::Hash => click.ip_hash,
+            IpExportMode::Omit => None,
+        },
+    }
+}
+
+/// The first chunk of an export, JSON Lines has no header.
+pub(crate) fn export_header(format: ExportFormat, ip_export_mode: IpExportMode) -> String {
+    match format {
+        ExportFormat::Csv => {
+            let mut columns = CSV_COLUMNS.to_vec();
+            if ip_export_mode == IpExportMode::Hash {
+                columns.push(CSV_IP_HASH_COLUMN);
+            }
+            format!("{}\n", columns.join(","))
+        }
+        ExportFormat::Jsonl => String::new(),
+    }
+}
+
+pub(crate) fn export_row(
+    format: ExportFormat,
+    ip_export_mode: IpExportMode,
+    click: &ClickExportModel,
+) -> String {
+    match format {
+        ExportFormat::Csv => {
+            let mut fields = vec![
+                csv_field(&click.code),
+                click.clicked_at.to_rfc3339(),
+                csv_field(click.referrer.as_deref().unwrap_or_default()),
+                csv_field(click.user_agent.as_deref().unwrap_or_default()),
+                csv_field(&click.destination),
+                click.is_bot.to_string(),
+                csv_field(click.bot_reason.as_deref().unwrap_or_default()),
+                csv_field(click.country.as_deref().unwrap_or_default()),
+                csv_field(click.region.as_deref().unwrap_or_default()),
+                csv_field(click.city.as_deref().unwrap_or_default()),
+                csv_field(&click.source),
+            ];
+            if ip_export_mode == IpExportMode::Hash {
+                fields.push(csv_field(click.ip_hash.as_deref().unwrap_or_default()));
+            }
+            format!("{}\n", fields.join(","))
+        }
+        // serializing plain strings and timestamps cannot fail
+        ExportFormat::Jsonl => format!("{}\n", serde_json::to_string(click).unwrap_or_default()),
+    }
+}
+
+/// Quotes a value when needed and defuses values a spreadsheet would evaluate as a formula,
+/// referrers and user agents are sent by the visitor and must be treated as untrusted.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::export::{csv_field, export_header, export_row};
+    use crate::analytics::privacy::IpExportMode;
+    use crate::models::export_models::{ClickExportModel, ExportFormat};
+    use chrono::{TimeZone, Utc};
+
+    fn test_export() -> ClickExportModel {
+        ClickExportModel {
+            code: "1234556".to_string(),
+            clicked_at: Utc.with_ymd_and_hms(2025, 1, 30, 10, 0, 0).unwrap(),
+            referrer: None,
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64)".to_string()),
+            destination: "https://www.google.com".to_string(),
+            is_bot: false,
+            bot_reason: None,
+            country: None,
+            region: None,
+            city: None,
+            source: "qr".to_string(),
+            ip_hash: Some("abc".to_string()),
+        }
+    }
+
+    #[test]
+    fn csv_field_escapes_quotes_and_formulas() {
+        // Act & Assert
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+    }
+
+    #[test]
+    fn export_csv_includes_ip_hash_only_when_allowed() {
+        // Act
+        let omitted_header = export_header(ExportFormat::Csv, IpExportMode::Omit);
+        let hashed_header = export_header(ExportFormat::Csv, IpExportMode::Hash);
+        let omitted_row = export_row(ExportFormat::Csv, IpExportMode::Omit, &test_export());
+        let hashed_row = export_row(ExportFormat::Csv, IpExportMode::Hash, &test_export());
+
+        // Assert
+        assert!(!omitted_header.contains("ip_hash"));
+        assert!(hashed_header.trim_end().ends_with(",ip_hash"));
+        assert_eq!(
+            omitted_row,
+            "1234556,2025-01-30T10:00:00+00:00,,Mozilla/5.0 (X11; Linux x86_64),https://www.google.com,false,,,,,qr\n"
+        );
+        assert!(hashed_row.trim_end().ends_with(",abc"));
+    }
+
+    #[test]
+    fn export_jsonl_writes_one_object_per_line() {
+        // Act
+        let row = export_row(ExportFormat::Jsonl, IpExportMode::Hash, &test_export());
+
+        // Assert
+        assert!(row.ends_with('\n'));
+        assert_eq!(row.matches('\n').count(), 1);
+        assert!(row.contains("\"clickedAt\":\"2025-01-30T10:00:00Z\""));
+    }
+}
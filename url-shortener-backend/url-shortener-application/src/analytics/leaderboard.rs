@@ -0,0 +1,120 @@
+use crate::models::top_models::TopWindow;
+use chrono::{DateTime, Utc};
+
+const LEADERBOARD_PREFIX: &str = "top_links";
+const WINDOWS: &[TopWindow] = &[TopWindow::Hour, TopWindow::Day, TopWindow::Week];
+
+fn bucket_seconds(window: TopWindow) -> i64 {
+    match window {
+        TopWindow::Hour => 5 * 60,
+        TopWindow::Day => 60 * 60,
+        TopWindow::Week => 24 * 60 * 60,
+    }
+}
+
+fn bucket_count(window: TopWindow) -> i64 {
+    match window {
+        TopWindow::Hour => 12,
+        TopWindow::Day => 24,
+        TopWindow::Week => 7,
+    }
+}
+
+fn bucket_key(window: TopWindow, bucket: i64) -> String {
+    format!("{}:{}:{}", LEADERBOARD_PREFIX, window.as_str(), bucket)
+}
+
+/// Every click lands in the current bucket of each window, with a ttl that keeps
+/// the bucket around for as long as any rolling window still covers it.
+pub(crate) fn leaderboard_buckets(at: DateTime<Utc>) -> Vec<(String, i64)> {
+    WINDOWS
+        .iter()
+        .map(|window| {
+            let size = bucket_seconds(*window);
+            (
+                bucket_key(*window, at.timestamp() / size),
+                size * (bucket_count(*window) + 1),
+            )
+        })
+        .collect()
+}
+
+/// The buckets making up a rolling window, the current bucket is still filling up
+/// so a window spans slightly less than its nominal length.
+pub(crate) fn window_keys(window: TopWindow, now: DateTime<Utc>) -> Vec<String> {
+    let current = now.timestamp() / bucket_seconds(window);
+    (current - bucket_count(window) + 1..=current)
+        .map(|bucket| bucket_key(window, bucket))
+        .collect()
+}
+
+/// Trending links are ranked by how far their last hour is above their average hour of the day,
+/// so a link that is steadily popular scores lower than one that just took off.
+pub(crate) fn trending_weights(now: DateTime<Utc>) -> Vec<(String, f64)> {
+    let hours = bucket_count(TopWindow::Day) as f64;
+    window_keys(TopWindow::Hour, now)
+        .into_iter()
+        .map(|key| (key, 1.0))
+        .chain(
+            window_keys(TopWindow::Day, now)
+                .into_iter()
+                .map(|key| (key, -1.0 / hours)),
+        )
+        .collect()
+}
+
+pub(crate) fn ranking_key(name: &str) -> String {
+    format!("{}:ranking:{}", LEADERBOARD_PREFIX, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::leaderboard::{leaderboard_buckets, trending_weights, window_keys};
+    use crate::models::top_models::TopWindow;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn leaderboard_buckets_cover_every_window() {
+        // Arrange
+        let at = Utc.with_ymd_and_hms(2025, 1, 30, 10, 7, 0).unwrap();
+
+        // Act
+        let buckets = leaderboard_buckets(at);
+
+        // Assert
+        assert_eq!(
+            buckets,
+            vec![
+                (format!("top_links:hour:{}", at.timestamp() / 300), 300 * 13),
+                (
+                    format!("top_links:day:{}", at.timestamp() / 3600),
+                    3600 * 25
+                ),
+                (
+                    format!("top_links:week:{}", at.timestamp() / 86400),
+                    86400 * 8
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_keys_end_at_current_bucket() {
+        // Arrange
+        let now = Utc.with_ymd_and_hms(2025, 1, 30, 10, 7, 0).unwrap();
+
+        // Act
+        let day = window_keys(TopWindow::Day, now);
+        let trending = trending_weights(now);
+
+        // Assert
+        assert_eq!(day.len(), 24);
+        assert_eq!(
+            day.last().unwrap(),
+            &format!("top_links:day:{}", now.timestamp() / 3600)
+        );
+        assert_eq!(trending.len(), 12 + 24);
+        assert!(trending[..12].iter().all(|(_, weight)| *weight == 1.0));
+        assert!(trending[12..].iter().all(|(_, weight)| *weight < 0.0));
+    }
+}
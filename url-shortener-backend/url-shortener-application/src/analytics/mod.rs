@@ -0,0 +1,11 @@
+pub(crate) mod bots;
+pub(crate) mod click_events;
+pub(crate) mod conversions;
+pub(crate) mod counters;
+pub(crate) mod export;
+pub(crate) mod leaderboard;
+pub(crate) mod parquet_writer;
+pub(crate) mod privacy;
+pub(crate) mod referrer;
+pub(crate) mod user_agent;
+pub(crate) mod visitors;
@@ -0,0 +1,216 @@
+use crate::analytics::privacy::IpExportMode;
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use std::sync::Arc;
+use url_shortener_database::models::click_models::ClickRecord;
+
+const CLICK_SCHEMA: &str = "
+message click {
+    REQUIRED INT64 id;
+    REQUIRED BYTE_ARRAY url_id (STRING);
+    REQUIRED INT64 clicked_at (TIMESTAMP(MICROS,true));
+    OPTIONAL BYTE_ARRAY referrer (STRING);
+    OPTIONAL BYTE_ARRAY user_agent (STRING);
+    OPTIONAL BYTE_ARRAY ip_hash (STRING);
+    REQUIRED BYTE_ARRAY destination (STRING);
+    REQUIRED BOOLEAN is_bot;
+    OPTIONAL BYTE_ARRAY bot_reason (STRING);
+    OPTIONAL BYTE_ARRAY country (STRING);
+    OPTIONAL BYTE_ARRAY region (STRING);
+    OPTIONAL BYTE_ARRAY city (STRING);
+    REQUIRED BYTE_ARRAY source (STRING);
+}
+";
+
+/// Builds a Parquet file of clicks in memory, each written page becomes one row group
+/// so only the compressed file and the current page are held at a time.
+pub(crate) struct ClickParquetWriter {
+    writer: SerializedFileWriter<Vec<u8>>,
+    ip_export_mode: IpExportMode,
+    rows: usize,
+}
+
+impl ClickParquetWriter {
+    pub fn new(ip_export_mode: IpExportMode) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(CLICK_SCHEMA)?);
+        let properties = Arc::new(
+            WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build(),
+        );
+
+        Ok(Self {
+            writer: SerializedFileWriter::new(Vec::new(), schema, properties)?,
+            ip_export_mode,
+            rows: 0,
+        })
+    }
+
+    pub fn write_page(&mut self, clicks: &[ClickRecord]) -> Result<()> {
+        if clicks.is_empty() {
+            return Ok(());
+        }
+
+        let ip_hashes: Vec<Option<&str>> = clicks
+            .iter()
+            .map(|click| match self.ip_export_mode {
+                IpExportMode::Hash => click.ip_hash.as_deref(),
+                IpExportMode::Omit => None,
+            })
+            .collect();
+
+        let mut row_group = self.writer.next_row_group()?;
+        let mut column = 0;
+        while let Some(mut writer) = row_group.next_column()? {
+            match column {
+                0 => write_int64(&mut writer, clicks.iter().map(|c| c.id))?,
+                1 => write_strings(&mut writer, clicks.iter().map(|c| c.url_id.as_str()))?,
+                2 => write_int64(
+                    &mut writer,
+                    clicks.iter().map(|c| c.clicked_at.timestamp_micros()),
+                )?,
+                3 => write_optional_strings(
+                    &mut writer,
+                    clicks.iter().map(|c| c.referrer.as_deref()),
+                )?,
+                4 => write_optional_strings(
+                    &mut writer,
+                    clicks.iter().map(|c| c.user_agent.as_deref()),
+                )?,
+                5 => write_optional_strings(&mut writer, ip_hashes.iter().copied())?,
+                6 => write_strings(&mut writer, clicks.iter().map(|c| c.destination.as_str()))?,
+                7 => {
+                    let values: Vec<bool> = clicks.iter().map(|c| c.is_bot).collect();
+                    writer
+                        .typed::<BoolType>()
+                        .write_batch(&values, None, None)?;
+                }
+                8 => write_optional_strings(
+                    &mut writer,
+                    clicks.iter().map(|c| c.bot_reason.as_deref()),
+                )?,
+                9 => write_optional_strings(
+                    &mut writer,
+                    clicks.iter().map(|c| c.country.as_deref()),
+                )?,
+                10 => {
+                    write_optional_strings(&mut writer, clicks.iter().map(|c| c.region.as_deref()))?
+                }
+                11 => {
+                    write_optional_strings(&mut writer, clicks.iter().map(|c| c.city.as_deref()))?
+                }
+                _ => write_strings(&mut writer, clicks.iter().map(|c| c.source.as_str()))?,
+            }
+            writer.close()?;
+            column += 1;
+        }
+        row_group.close()?;
+
+        self.rows += clicks.len();
+        Ok(())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>> {
+        self.writer.into_inner()
+    }
+}
+
+fn write_int64(
+    writer: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = i64>,
+) -> Result<()> {
+    let values: Vec<i64> = values.collect();
+    writer
+        .typed::<Int64Type>()
+        .write_batch(&values, None, None)?;
+    Ok(())
+}
+
+fn write_strings<'a>(
+    writer: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = &'a str>,
+) -> Result<()> {
+    let values: Vec<ByteArray> = values.map(ByteArray::from).collect();
+    writer
+        .typed::<ByteArrayType>()
+        .write_batch(&values, None, None)?;
+    Ok(())
+}
+
+fn write_optional_strings<'a>(
+    writer: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = Option<&'a str>>,
+) -> Result<()> {
+    let (definition_levels, values): (Vec<i16>, Vec<Option<ByteArray>>) = values
+        .map(|value| (i16::from(value.is_some()), value.map(ByteArray::from)))
+        .unzip();
+    let values: Vec<ByteArray> = values.into_iter().flatten().collect();
+    writer
+        .typed::<ByteArrayType>()
+        .write_batch(&values, Some(&definition_levels), None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::parquet_writer::ClickParquetWriter;
+    use crate::analytics::privacy::IpExportMode;
+    use chrono::Utc;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use url_shortener_database::models::click_models::ClickRecord;
+
+    fn test_click(id: i64, referrer: Option<&str>) -> ClickRecord {
+        ClickRecord {
+            id,
+            url_id: "1234556".to_string(),
+            clicked_at: Utc::now(),
+            referrer: referrer.map(str::to_string),
+            user_agent: None,
+            ip_hash: Some("abc".to_string()),
+            destination: "https://www.google.com".to_string(),
+            is_bot: false,
+            bot_reason: None,
+            country: None,
+            region: None,
+            city: None,
+            source: "link".to_string(),
+        }
+    }
+
+    #[test]
+    fn click_parquet_writer_round_trips_pages() {
+        // Arrange
+        let mut writer = ClickParquetWriter::new(IpExportMode::Omit).unwrap();
+
+        // Act
+        writer
+            .write_page(&[test_click(1, Some("https://t.co/")), test_click(2, None)])
+            .unwrap();
+        writer.write_page(&[test_click(3, None)]).unwrap();
+        let rows = writer.rows();
+        let file = writer.finish().unwrap();
+
+        // Assert
+        let reader = SerializedFileReader::new(bytes::Bytes::from(file)).unwrap();
+        assert_eq!(rows, 3);
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let records: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].get_string(3).unwrap(), "https://t.co/");
+        assert_eq!(records[2].get_long(0).unwrap(), 3);
+        assert!(records[0].get_string(5).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::analytics::visitors::normalize_ip;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::{rng, Rng};
+use sha2::Sha256;
+use std::env;
+use std::net::IpAddr;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const DEFAULT_SALT_ROTATION_DAYS: i64 = 1;
+const IPV6_KEPT_SEGMENTS: usize = 3;
+
+/// How the stored visitor hash may leave the system, configured with `ANALYTICS_EXPORT_IP`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum IpExportMode {
+    #[default]
+    Omit,
+    Hash,
+}
+
+pub(crate) fn ip_export_mode() -> IpExportMode {
+    match env::var("ANALYTICS_EXPORT_IP").as_deref() {
+        Ok("hash") => IpExportMode::Hash,
+        _ => IpExportMode::Omit,
+    }
+}
+
+/// How a visitor address is reduced before it is stored, configured with `IP_ANONYMIZATION`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum IpAnonymization {
+    #[default]
+    Hash,
+    Truncate,
+}
+
+pub(crate) fn ip_anonymization() -> IpAnonymization {
+    match env::var("IP_ANONYMIZATION").as_deref() {
+        Ok("truncate") => IpAnonymization::Truncate,
+        _ => IpAnonymization::Hash,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RetentionPolicy {
+    pub max_age: Duration,
+    pub aggregate: bool,
+}
+
+/// Raw clicks are kept forever unless `CLICK_RETENTION_DAYS` is set, older rows are then
+/// rolled up into daily aggregates the stats keep counting, or dropped when
+/// `CLICK_RETENTION_MODE=delete`.
+pub(crate) fn retention_policy() -> Option<RetentionPolicy> {
+    let days = env::var("CLICK_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days > 0)?;
+
+    Some(RetentionPolicy {
+        max_age: Duration::days(days),
+        aggregate: env::var("CLICK_RETENTION_MODE").as_deref() != Ok("delete"),
+    })
+}
+
+/// Where the random salt of the period holding `at` is shared, and how long it may live: the
+/// key expires with the period, so once it is gone nothing can link that period's hashes again.
+pub(crate) fn salt_period(at: DateTime<Utc>) -> (String, i64) {
+    let period_seconds = salt_rotation_days() * SECONDS_PER_DAY;
+    let period = at.timestamp().div_euclid(period_seconds);
+    let ttl_seconds = (period + 1) * period_seconds - at.timestamp();
+    (format!("ip_hash_salt:{}", period), ttl_seconds)
+}
+
+pub(crate) fn new_period_salt() -> String {
+    let salt: [u8; 32] = rng().random();
+    salt.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Reduces the address as configured, `None` when it can be neither truncated nor hashed.
+pub(crate) fn anonymize_ip(ip: &str, period_salt: Option<&str>) -> Option<String> {
+    let key = ip_hash_key();
+    anonymize_ip_with(ip_anonymization(), key.as_deref(), period_salt, ip)
+}
+
+pub(crate) fn ip_hash_key() -> Option<String> {
+    env::var("IP_HASH_SALT").ok().filter(|key| !key.is_empty())
+}
+
+fn salt_rotation_days() -> i64 {
+    env::var("IP_HASH_ROTATION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_SALT_ROTATION_DAYS)
+}
+
+fn anonymize_ip_with(
+    anonymization: IpAnonymization,
+    key: Option<&str>,
+    period_salt: Option<&str>,
+    ip: &str,
+) -> Option<String> {
+    let ip = normalize_ip(ip);
+    let hash = key
+        .zip(period_salt)
+        .map(|(key, period_salt)| keyed_hash(&ip, key, period_salt));
+    match anonymization {
+        IpAnonymization::Hash if hash.is_some() => hash,
+        // never store an address that could not be truncated
+        _ => ip.parse::<IpAddr>().ok().map(truncate_ip).or(hash),
+    }
+}
+
+/// Keeps the network part only: the /24 of an IPv4 address or the /48 of an IPv6 one.
+fn truncate_ip(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}.{}.{}.0", a, b, c)
+        }
+        IpAddr::V6(v6) => {
+            let mut segments = v6.segments();
+            segments[IPV6_KEPT_SEGMENTS..].fill(0);
+            IpAddr::from(segments).to_string()
+        }
+    }
+}
+
+/// HMAC of the address keyed by `IP_HASH_SALT` over the random salt of the current period, so
+/// hashes cannot be reversed without the key nor linked across periods once a salt expires.
+pub(crate) fn keyed_hash(ip: &str, key: &str, period_salt: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(period_salt.as_bytes());
+    mac.update(b"|");
+    mac.update(ip.as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::privacy::{anonymize_ip_with, salt_period, IpAnonymization};
+    use chrono::{Duration, TimeZone, Utc};
+
+    const TEST_KEY: &str = "secret";
+
+    #[test]
+    fn anonymize_ip_truncates_network_part() {
+        // Act
+        let v4 = anonymize_ip_with(IpAnonymization::Truncate, None, None, "203.0.113.42:5555");
+        let v6 = anonymize_ip_with(
+            IpAnonymization::Truncate,
+            None,
+            None,
+            "2001:db8:85a3:8d3:1319:8a2e:370:7348",
+        );
+
+        // Assert
+        assert_eq!(v4.as_deref(), Some("203.0.113.0"));
+        assert_eq!(v6.as_deref(), Some("2001:db8:85a3::"));
+    }
+
+    #[test]
+    fn anonymize_ip_hash_rotates_with_period_salt() {
+        // Act
+        let morning = anonymize_ip_with(
+            IpAnonymization::Hash,
+            Some(TEST_KEY),
+            Some("today"),
+            "127.0.0.1:5555",
+        )
+        .unwrap();
+        let evening = anonymize_ip_with(
+            IpAnonymization::Hash,
+            Some(TEST_KEY),
+            Some("today"),
+            "127.0.0.1",
+        )
+        .unwrap();
+        let next_day = anonymize_ip_with(
+            IpAnonymization::Hash,
+            Some(TEST_KEY),
+            Some("tomorrow"),
+            "127.0.0.1",
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(morning, evening);
+        assert_ne!(morning, next_day);
+        assert_eq!(morning.len(), 64);
+        assert!(!morning.contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn anonymize_ip_without_key_or_salt_truncates() {
+        // Act
+        let without_key =
+            anonymize_ip_with(IpAnonymization::Hash, None, Some("today"), "203.0.113.42");
+        let without_salt =
+            anonymize_ip_with(IpAnonymization::Hash, Some(TEST_KEY), None, "203.0.113.42");
+        let invalid = anonymize_ip_with(IpAnonymization::Hash, None, None, "unknown");
+
+        // Assert
+        assert_eq!(without_key.as_deref(), Some("203.0.113.0"));
+        assert_eq!(without_salt.as_deref(), Some("203.0.113.0"));
+        assert_eq!(invalid, None);
+    }
+
+    #[test]
+    fn salt_period_expires_with_the_period() {
+        // Arrange
+        let day = Utc.with_ymd_and_hms(2025, 1, 30, 18, 0, 0).unwrap();
+
+        // Act
+        let (morning_key, _) = salt_period(day - Duration::hours(8));
+        let (evening_key, ttl_seconds) = salt_period(day);
+        let (next_day_key, _) = salt_period(day + Duration::days(1));
+
+        // Assert
+        assert_eq!(morning_key, evening_key);
+        assert_ne!(evening_key, next_day_key);
+        assert_eq!(ttl_seconds, 6 * 60 * 60);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::models::stats_models::ReferrerCategory;
+use url::Url;
+
+const EMAIL_DOMAINS: &[&str] = &[
+    "mail.google.com",
+    "outlook.live.com",
+    "outlook.office.com",
+    "outlook.office365.com",
+    "mail.yahoo.com",
+    "mail.proton.me",
+    "mail.aol.com",
+    "webmail",
+];
+const SEARCH_DOMAINS: &[&str] = &[
+    "google",
+    "bing.com",
+    "duckduckgo.com",
+    "yahoo.com",
+    "yandex",
+    "baidu.com",
+    "ecosia.org",
+    "search.brave.com",
+];
+const SOCIAL_DOMAINS: &[&str] = &[
+    "facebook.com",
+    "fb.com",
+    "instagram.com",
+    "twitter.com",
+    "x.com",
+    "t.co",
+    "linkedin.com",
+    "lnkd.in",
+    "reddit.com",
+    "pinterest.com",
+    "tiktok.com",
+    "youtube.com",
+    "whatsapp.com",
+    "t.me",
+    "telegram.org",
+    "threads.net",
+    "bsky.app",
+    "mastodon.social",
+];
+const STRIPPED_PREFIXES: &[&str] = &["www.", "m.", "l.", "lm."];
+
+/// Reduces a referrer to its host, dropping common mobile and redirect subdomains
+/// so `l.facebook.com` and `www.facebook.com` are reported together.
+pub(crate) fn referrer_domain(referrer: &str) -> Option<String> {
+    let host = Url::parse(referrer).ok()?.host_str()?.to_lowercase();
+
+    let domain = STRIPPED_PREFIXES
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix))
+        .unwrap_or(&host);
+
+    Some(domain.to_string())
+}
+
+pub(crate) fn classify_referrer(domain: Option<&str>) -> ReferrerCategory {
+    let Some(domain) = domain else {
+        return ReferrerCategory::Direct;
+    };
+
+    // email is checked first, webmail hosts usually live under a search engine's domain
+    if EMAIL_DOMAINS.iter().any(|d| matches_domain(domain, d)) {
+        ReferrerCategory::Email
+    } else if SEARCH_DOMAINS.iter().any(|d| matches_domain(domain, d)) {
+        ReferrerCategory::Search
+    } else if SOCIAL_DOMAINS.iter().any(|d| matches_domain(domain, d)) {
+        ReferrerCategory::Social
+    } else {
+        ReferrerCategory::Other
+    }
+}
+
+fn matches_domain(domain: &str, pattern: &str) -> bool {
+    if pattern.contains('.') {
+        domain == pattern || domain.ends_with(&format!(".{}", pattern))
+    } else {
+        // a bare label such as `google` matches every country domain of that provider
+        domain.split('.').any(|label| label == pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::referrer::{classify_referrer, referrer_domain};
+    use crate::models::stats_models::ReferrerCategory;
+
+    #[test]
+    fn referrer_domain_strips_mobile_prefix() {
+        // Act
+        let domain = referrer_domain("https://l.facebook.com/l.php?u=abc");
+
+        // Assert
+        assert_eq!(domain.as_deref(), Some("facebook.com"));
+    }
+
+    #[test]
+    fn classify_referrer_returns_categories() {
+        // Assert
+        assert_eq!(classify_referrer(None), ReferrerCategory::Direct);
+        assert_eq!(
+            classify_referrer(Some("mail.google.com")),
+            ReferrerCategory::Email
+        );
+        assert_eq!(
+            classify_referrer(Some("google.ro")),
+            ReferrerCategory::Search
+        );
+        assert_eq!(classify_referrer(Some("t.co")), ReferrerCategory::Social);
+        assert_eq!(
+            classify_referrer(Some("example.com")),
+            ReferrerCategory::Other
+        );
+    }
+}
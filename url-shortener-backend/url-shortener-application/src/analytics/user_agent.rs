@@ -0,0 +1,46 @@
+use woothee::parser::Parser;
+
+const UNKNOWN: &str = "Unknown";
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct UserAgentInfo {
+    pub browser: String,
+    pub os: String,
+    pub device: String,
+}
+
+pub(crate) fn parse_user_agent(user_agent: Option<&str>) -> UserAgentInfo {
+    let parsed = user_agent.and_then(|ua| Parser::new().parse(ua));
+
+    match (user_agent, parsed) {
+        (Some(ua), Some(result)) => UserAgentInfo {
+            browser: known_or_unknown(result.name),
+            os: known_or_unknown(result.os),
+            device: device_type(ua, result.category, result.os).to_string(),
+        },
+        _ => UserAgentInfo {
+            browser: UNKNOWN.to_string(),
+            os: UNKNOWN.to_string(),
+            device: UNKNOWN.to_string(),
+        },
+    }
+}
+
+fn known_or_unknown(value: &str) -> String {
+    if value.is_empty() || value == "UNKNOWN" {
+        UNKNOWN.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn device_type(user_agent: &str, category: &str, os: &str) -> &'static str {
+    match category {
+        "pc" => "Desktop",
+        "smartphone" if os == "iPad" || user_agent.contains("Tablet") => "Tablet",
+        "smartphone" | "mobilephone" => "Mobile",
+        "crawler" => "Bot",
+        "appliance" => "Appliance",
+        _ => UNKNOWN,
+    }
+}
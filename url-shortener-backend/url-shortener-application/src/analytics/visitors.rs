@@ -0,0 +1,95 @@
+use crate::analytics::privacy::{ip_hash_key, keyed_hash};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::net::{IpAddr, SocketAddr};
+
+pub(crate) const UNIQUE_VISITORS_TTL_SECONDS: i64 = 400 * 24 * 60 * 60;
+
+/// Identifies a visitor without storing anything personal: the client address and user agent
+/// are hashed like a stored address, over the salt of the period, and only ever fed into
+/// HyperLogLog and burst keys.
+pub(crate) fn visitor_fingerprint(ip: &str, user_agent: Option<&str>, period_salt: &str) -> String {
+    let visitor = format!("{}|{}", normalize_ip(ip), user_agent.unwrap_or_default());
+    keyed_hash(&visitor, &ip_hash_key().unwrap_or_default(), period_salt)
+}
+
+pub(crate) fn unique_visitors_key(short_url: &str, day: NaiveDate) -> String {
+    format!("unique_visitors:{}:{}", short_url, day.format("%Y-%m-%d"))
+}
+
+/// Unique visitor keys are bucketed by UTC day, so a range touches every day it overlaps.
+pub(crate) fn days_in_range(from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<NaiveDate> {
+    from.date_naive()
+        .iter_days()
+        .take_while(|day| *day <= to.date_naive())
+        .collect()
+}
+
+/// Strips the port a proxy or the connection info may have appended to the address.
+pub(crate) fn normalize_ip(ip: &str) -> String {
+    ip.parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| ip.parse::<IpAddr>())
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::visitors::{
+        days_in_range, normalize_ip, unique_visitors_key, visitor_fingerprint,
+    };
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn normalize_ip_strips_port() {
+        // Act & Assert
+        assert_eq!(normalize_ip("127.0.0.1:5555"), "127.0.0.1");
+        assert_eq!(normalize_ip("[::1]:5555"), "::1");
+        assert_eq!(normalize_ip("::1"), "::1");
+    }
+
+    #[test]
+    fn visitor_fingerprint_depends_on_user_agent() {
+        // Act
+        let chrome = visitor_fingerprint("127.0.0.1", Some("Chrome"), "today");
+        let firefox = visitor_fingerprint("127.0.0.1", Some("Firefox"), "today");
+
+        // Assert
+        assert_ne!(chrome, firefox);
+    }
+
+    #[test]
+    fn visitor_fingerprint_rotates_with_period_salt() {
+        // Act
+        let today = visitor_fingerprint("127.0.0.1:5555", Some("Chrome"), "today");
+        let same_day = visitor_fingerprint("127.0.0.1", Some("Chrome"), "today");
+        let tomorrow = visitor_fingerprint("127.0.0.1", Some("Chrome"), "tomorrow");
+
+        // Assert
+        assert_eq!(today, same_day);
+        assert_ne!(today, tomorrow);
+    }
+
+    #[test]
+    fn days_in_range_cover_every_day() {
+        // Arrange
+        let from = Utc.with_ymd_and_hms(2025, 1, 30, 12, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2025, 2, 1, 1, 0, 0).unwrap();
+
+        // Act
+        let keys: Vec<String> = days_in_range(from, to)
+            .into_iter()
+            .map(|day| unique_visitors_key("abc", day))
+            .collect();
+
+        // Assert
+        assert_eq!(
+            keys,
+            vec![
+                "unique_visitors:abc:2025-01-30",
+                "unique_visitors:abc:2025-01-31",
+                "unique_visitors:abc:2025-02-01"
+            ]
+        );
+    }
+}
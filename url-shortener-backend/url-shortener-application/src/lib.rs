@@ -1,2 +1,6 @@
+pub(crate) mod analytics;
 pub mod models;
+pub mod qr;
+pub mod queues;
 pub mod services;
+pub mod workers;
@@ -0,0 +1,28 @@
+use crate::models::stats_models::ReferrerCategory;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A click as broadcast to live viewers, it never carries the visitor's address or hash.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClickEventModel {
+    pub code: String,
+    #[serde(rename = "clickedAt")]
+    pub clicked_at: DateTime<Utc>,
+    #[serde(rename = "referrerDomain")]
+    pub referrer_domain: Option<String>,
+    #[serde(rename = "referrerCategory")]
+    pub referrer_category: ReferrerCategory,
+    pub browser: String,
+    pub os: String,
+    pub device: String,
+    pub country: Option<String>,
+    pub source: String,
+    #[serde(rename = "isBot")]
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ClickEventQuery {
+    #[serde(rename = "includeBots")]
+    pub include_bots: Option<bool>,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
+
+/// Query parameter marking where a visit to a short url came from, it is never forwarded to the destination.
+pub const SOURCE_PARAM: &str = "src";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClickSource {
+    #[default]
+    Link,
+    Qr,
+}
+
+impl ClickSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClickSource::Link => "link",
+            ClickSource::Qr => "qr",
+        }
+    }
+
+    /// Anything other than a known marker is treated as a plain link click.
+    pub fn from_query(query: &str) -> Self {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == SOURCE_PARAM)
+            .map(|(_, value)| match value.as_ref() {
+                "qr" => ClickSource::Qr,
+                _ => ClickSource::Link,
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ClickRequest {
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub accept_language: Option<String>,
+    pub is_head_request: bool,
+    pub do_not_track: bool,
+    pub source: ClickSource,
+    pub click_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::click_models::ClickSource;
+
+    #[test]
+    fn click_source_reads_qr_marker() {
+        // Act & Assert
+        assert_eq!(ClickSource::from_query("src=qr"), ClickSource::Qr);
+        assert_eq!(
+            ClickSource::from_query("utm_source=x&src=qr"),
+            ClickSource::Qr
+        );
+        assert_eq!(ClickSource::from_query("src=print"), ClickSource::Link);
+        assert_eq!(ClickSource::from_query(""), ClickSource::Link);
+    }
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversionRequest {
+    #[serde(rename = "clickId")]
+    pub click_id: String,
+    pub event: String,
+    pub value: Option<f64>,
+}
@@ -5,6 +5,7 @@ use thiserror::Error;
 pub enum ApiError {
     NotFound(&'static str),
     BadRequest(&'static str),
+    Unauthorized(&'static str),
     InternalServerError,
 }
 
@@ -13,6 +14,7 @@ impl Display for ApiError {
         match *self {
             ApiError::BadRequest(message) => _f.write_str(message),
             ApiError::NotFound(message) => _f.write_str(message),
+            ApiError::Unauthorized(message) => _f.write_str(message),
             _ => _f.write_str("Something went wrong"),
         }
     }
@@ -1,3 +1,10 @@
+pub mod click_event_models;
+pub mod click_models;
+pub mod conversion_models;
 pub mod errors;
+pub mod export_models;
+pub mod qr_models;
 pub mod response_model;
+pub mod stats_models;
+pub mod top_models;
 pub mod url_models;
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+/// The 2D barcode the link is encoded as, QR unless a scanner needs one of the others.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    #[default]
+    Qr,
+    DataMatrix,
+    Aztec,
+    Pdf417,
+}
+
+impl Symbology {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Symbology::Qr => "qr",
+            Symbology::DataMatrix => "datamatrix",
+            Symbology::Aztec => "aztec",
+            Symbology::Pdf417 => "pdf417",
+        }
+    }
+}
+
+/// Whether the returned QR image is stored yet, a pending one is uploaded in the background
+/// and is served by the on-demand endpoint meanwhile.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QrStatus {
+    Ready,
+    Pending,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrFormat::Png => "png",
+            QrFormat::Svg => "svg",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QrErrorCorrection {
+    #[default]
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+impl QrErrorCorrection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrErrorCorrection::Low => "low",
+            QrErrorCorrection::Medium => "medium",
+            QrErrorCorrection::Quartile => "quartile",
+            QrErrorCorrection::High => "high",
+        }
+    }
+}
+
+/// How the code is framed, both templates put the caption under the code.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFrameTemplate {
+    /// A foreground outline around the code and the caption.
+    Border,
+    /// A foreground band under the code with the caption set in the background color.
+    Banner,
+}
+
+impl QrFrameTemplate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrFrameTemplate::Border => "border",
+            QrFrameTemplate::Banner => "banner",
+        }
+    }
+}
+
+/// Rendering options for a link's QR code, anything left out falls back to the default rendering.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct QrOptions {
+    pub symbology: Option<Symbology>,
+    pub format: Option<QrFormat>,
+    pub size: Option<u32>,
+    #[serde(rename = "errorCorrection")]
+    pub error_correction: Option<QrErrorCorrection>,
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    #[serde(rename = "quietZone")]
+    pub quiet_zone: Option<u32>,
+    pub frame: Option<QrFrameTemplate>,
+    pub caption: Option<String>,
+    /// The logo's width as a percentage of the code, the logo itself is uploaded separately.
+    #[serde(rename = "logoSize")]
+    pub logo_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct QrImageQuery {
+    pub symbology: Option<Symbology>,
+    pub format: Option<QrFormat>,
+    pub size: Option<u32>,
+}
+
+/// A rendered QR code, the ETag changes with any input to the rendering, including the domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrImageModel {
+    pub etag: String,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QrExportFormat {
+    /// One image per link, named by its short URL.
+    #[default]
+    Zip,
+    /// A printable A4 label sheet with the short URL and destination under each code.
+    Pdf,
+}
+
+impl QrExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrExportFormat::Zip => "application/zip",
+            QrExportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            QrExportFormat::Zip => "zip",
+            QrExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// A batch of links whose codes are exported together, `image` overrides every link's stored options.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct QrExportRequest {
+    pub codes: Vec<String>,
+    pub format: Option<QrExportFormat>,
+    pub image: Option<QrImageQuery>,
+}
+
+/// Re-renders stored QR images with the current domain, for all links or those whose destination starts with the prefix.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct QrBackfillRequest {
+    #[serde(rename = "destinationPrefix")]
+    pub destination_prefix: Option<String>,
+}
+
+/// Compares the stored QR images with the links, only reporting the differences unless `dryRun` is `false`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct QrReconcileRequest {
+    #[serde(rename = "dryRun")]
+    pub dry_run: Option<bool>,
+}
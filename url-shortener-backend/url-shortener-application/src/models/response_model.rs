@@ -1,4 +1,10 @@
+use crate::models::qr_models::QrStatus;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use url_shortener_database::models::history_models::UrlHistory;
+use url_shortener_database::models::qr_backfill_models::QrBackfillJob;
+use url_shortener_database::models::qr_reconcile_models::QrReconcileJob;
+use url_shortener_database::models::schedule_models::UrlSchedule;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateResponseModel {
@@ -6,4 +12,129 @@ pub struct CreateResponseModel {
     pub short_url: String,
     #[serde(rename = "qrCodeImage")]
     pub qr_code_image: String,
+    #[serde(rename = "qrStatus")]
+    pub qr_status: QrStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleResponseModel {
+    pub id: i64,
+    pub url: String,
+    #[serde(rename = "scheduledAt")]
+    pub scheduled_at: DateTime<Utc>,
+}
+
+impl From<UrlSchedule> for ScheduleResponseModel {
+    fn from(schedule: UrlSchedule) -> Self {
+        Self {
+            id: schedule.id,
+            url: schedule.destination,
+            scheduled_at: schedule.scheduled_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryResponseModel {
+    pub id: i64,
+    pub field: String,
+    #[serde(rename = "oldValue")]
+    pub old_value: Option<String>,
+    #[serde(rename = "newValue")]
+    pub new_value: String,
+    #[serde(rename = "changedBy")]
+    pub changed_by: String,
+    #[serde(rename = "changedAt")]
+    pub changed_at: DateTime<Utc>,
+}
+
+impl From<UrlHistory> for HistoryResponseModel {
+    fn from(history: UrlHistory) -> Self {
+        Self {
+            id: history.id,
+            field: history.field,
+            old_value: history.old_value,
+            new_value: history.new_value,
+            changed_by: history.changed_by,
+            changed_at: history.changed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QrBackfillResponseModel {
+    pub id: i64,
+    pub status: String,
+    pub domain: String,
+    #[serde(rename = "destinationPrefix")]
+    pub destination_prefix: Option<String>,
+    pub total: i64,
+    pub processed: i64,
+    pub failed: i64,
+    pub error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<QrBackfillJob> for QrBackfillResponseModel {
+    fn from(job: QrBackfillJob) -> Self {
+        Self {
+            id: job.id,
+            status: job.status,
+            domain: job.domain,
+            destination_prefix: job.destination_prefix,
+            total: job.total,
+            processed: job.processed,
+            failed: job.failed,
+            error: job.error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+            finished_at: job.finished_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct QrReconcileResponseModel {
+    pub id: i64,
+    pub status: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// Stored images no link uses, such as those of deleted links.
+    pub orphaned: Vec<String>,
+    /// Images links should have but that were never stored.
+    pub missing: Vec<String>,
+    pub deleted: i64,
+    pub regenerated: i64,
+    pub failed: i64,
+    pub error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<QrReconcileJob> for QrReconcileResponseModel {
+    fn from(job: QrReconcileJob) -> Self {
+        Self {
+            id: job.id,
+            status: job.status,
+            dry_run: job.dry_run,
+            orphaned: job.orphaned,
+            missing: job.missing,
+            deleted: job.deleted,
+            regenerated: job.regenerated,
+            failed: job.failed,
+            error: job.error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+            finished_at: job.finished_at,
+        }
+    }
 }
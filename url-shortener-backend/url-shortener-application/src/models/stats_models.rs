@@ -0,0 +1,122 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsInterval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsInterval {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StatsQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub interval: Option<StatsInterval>,
+    pub timezone: Option<String>,
+    #[serde(rename = "includeBots")]
+    pub include_bots: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferrerCategory {
+    Social,
+    Search,
+    Email,
+    Direct,
+    Other,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeriodStatsModel {
+    #[serde(rename = "periodStart")]
+    pub period_start: DateTime<Tz>,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferrerStatsModel {
+    pub domain: Option<String>,
+    pub category: ReferrerCategory,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct BreakdownStatsModel {
+    pub name: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CityStatsModel {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ConversionStatsModel {
+    pub event: String,
+    pub conversions: i64,
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DailyVisitorsModel {
+    pub day: NaiveDate,
+    pub visitors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsResponseModel {
+    #[serde(rename = "totalClicks")]
+    pub total_clicks: i64,
+    #[serde(rename = "directClicks")]
+    pub direct_clicks: i64,
+    #[serde(rename = "qrScans")]
+    pub qr_scans: i64,
+    /// Every human click since the link was created. Bot clicks never reach the live counters,
+    /// so this always excludes them whatever `includeBots` says.
+    #[serde(rename = "lifetimeClicks")]
+    pub lifetime_clicks: Option<i64>,
+    /// Estimated from human clicks only, like `lifetimeClicks` it ignores `includeBots`.
+    #[serde(rename = "uniqueVisitors")]
+    pub unique_visitors: Option<u64>,
+    #[serde(rename = "dailyUniqueVisitors")]
+    pub daily_unique_visitors: Vec<DailyVisitorsModel>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub interval: StatsInterval,
+    pub timezone: String,
+    /// Whether the click totals, series and breakdowns count bot clicks.
+    #[serde(rename = "includeBots")]
+    pub include_bots: bool,
+    pub series: Vec<PeriodStatsModel>,
+    pub referrers: Vec<ReferrerStatsModel>,
+    pub browsers: Vec<BreakdownStatsModel>,
+    #[serde(rename = "operatingSystems")]
+    pub operating_systems: Vec<BreakdownStatsModel>,
+    pub devices: Vec<BreakdownStatsModel>,
+    pub countries: Vec<BreakdownStatsModel>,
+    pub cities: Vec<CityStatsModel>,
+    pub conversions: i64,
+    #[serde(rename = "conversionRate")]
+    pub conversion_rate: Option<f64>,
+    #[serde(rename = "conversionEvents")]
+    pub conversion_events: Vec<ConversionStatsModel>,
+}
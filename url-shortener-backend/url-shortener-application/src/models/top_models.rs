@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TopWindow {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl TopWindow {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TopWindow::Hour => "hour",
+            TopWindow::Day => "day",
+            TopWindow::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct TopLinksQuery {
+    pub window: Option<TopWindow>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct TrendingLinksQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TopLinkModel {
+    pub code: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopLinksResponseModel {
+    pub window: TopWindow,
+    pub links: Vec<TopLinkModel>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TrendingLinkModel {
+    pub code: String,
+    pub score: f64,
+}
@@ -1,6 +1,38 @@
+use crate::analytics::conversions::with_click_id;
+use crate::models::qr_models::QrOptions;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateUrlRequest {
     pub url: String,
+    #[serde(rename = "trackConversions", default)]
+    pub track_conversions: bool,
+    /// Groups the link with others so their clicks can be exported together.
+    pub campaign: Option<String>,
+    pub qr: Option<QrOptions>,
+}
+
+/// Where a short url sends its visitor, the click id is only set for links tracking conversions.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RedirectModel {
+    pub url: String,
+    #[serde(rename = "clickId")]
+    pub click_id: Option<String>,
+}
+
+impl RedirectModel {
+    pub fn location(&self) -> String {
+        match &self.click_id {
+            Some(click_id) => with_click_id(&self.url, click_id),
+            None => self.url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub url: String,
+    #[serde(rename = "scheduledAt")]
+    pub scheduled_at: DateTime<Utc>,
 }
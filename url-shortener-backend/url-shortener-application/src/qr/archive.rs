@@ -0,0 +1,150 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+/// File names are UTF-8.
+const UTF8_FLAG: u16 = 0x0800;
+/// Entries are stored as is, PNGs are already compressed and SVGs are small.
+const STORED: u16 = 0;
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a ZIP archive one entry at a time, so every entry can be sent as soon as it is
+/// rendered. Only the central directory, a few bytes per entry, is held until the end.
+pub(crate) struct ZipWriter {
+    offset: u32,
+    time: u16,
+    date: u16,
+    entries: Vec<CentralEntry>,
+}
+
+impl ZipWriter {
+    pub fn new(modified: DateTime<Utc>) -> Self {
+        Self {
+            offset: 0,
+            time: ((modified.hour() << 11) | (modified.minute() << 5) | (modified.second() / 2))
+                as u16,
+            date: (((modified.year() - 1980).max(0) as u32) << 9
+                | (modified.month() << 5)
+                | modified.day()) as u16,
+            entries: Vec::new(),
+        }
+    }
+
+    /// The local header followed by the file's contents.
+    pub fn entry(&mut self, name: &str, contents: &[u8]) -> Vec<u8> {
+        let entry = CentralEntry {
+            name: name.to_string(),
+            crc: crc32fast::hash(contents),
+            size: contents.len() as u32,
+            offset: self.offset,
+        };
+
+        let mut bytes = Vec::with_capacity(30 + name.len() + contents.len());
+        put_u32(&mut bytes, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut bytes, VERSION);
+        self.put_common(&mut bytes, &entry);
+        put_u16(&mut bytes, 0);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(contents);
+
+        self.offset += bytes.len() as u32;
+        self.entries.push(entry);
+        bytes
+    }
+
+    /// The central directory, which closes the archive.
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for entry in &self.entries {
+            put_u32(&mut bytes, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut bytes, VERSION);
+            put_u16(&mut bytes, VERSION);
+            self.put_common(&mut bytes, entry);
+            // extra field, comment, disk, internal and external attributes
+            put_u16(&mut bytes, 0);
+            put_u16(&mut bytes, 0);
+            put_u16(&mut bytes, 0);
+            put_u16(&mut bytes, 0);
+            put_u32(&mut bytes, 0);
+            put_u32(&mut bytes, entry.offset);
+            bytes.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let directory_size = bytes.len() as u32;
+        put_u32(&mut bytes, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut bytes, 0);
+        put_u16(&mut bytes, 0);
+        put_u16(&mut bytes, self.entries.len() as u16);
+        put_u16(&mut bytes, self.entries.len() as u16);
+        put_u32(&mut bytes, directory_size);
+        put_u32(&mut bytes, self.offset);
+        put_u16(&mut bytes, 0);
+        bytes
+    }
+
+    /// The fields shared by the local and the central header, up to the name's length.
+    fn put_common(&self, bytes: &mut Vec<u8>, entry: &CentralEntry) {
+        put_u16(bytes, UTF8_FLAG);
+        put_u16(bytes, STORED);
+        put_u16(bytes, self.time);
+        put_u16(bytes, self.date);
+        put_u32(bytes, entry.crc);
+        put_u32(bytes, entry.size);
+        put_u32(bytes, entry.size);
+        put_u16(bytes, entry.name.len() as u16);
+    }
+}
+
+fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qr::archive::ZipWriter;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn zip_writer_builds_a_readable_archive() {
+        // Arrange
+        let mut writer = ZipWriter::new(Utc.with_ymd_and_hms(2025, 3, 1, 12, 30, 10).unwrap());
+
+        // Act
+        let mut archive = writer.entry("1234556.png", b"first");
+        archive.extend(writer.entry("abc.svg", b"<svg/>"));
+        archive.extend(writer.finish());
+
+        // Assert
+        let end = archive.len() - 22;
+        assert_eq!(archive[..4], [0x50, 0x4b, 0x03, 0x04]);
+        assert_eq!(archive[end..end + 4], [0x50, 0x4b, 0x05, 0x06]);
+        // two entries, and the central directory starts right after the second file
+        assert_eq!(
+            u16::from_le_bytes([archive[end + 10], archive[end + 11]]),
+            2
+        );
+        let directory = u32::from_le_bytes(archive[end + 16..end + 20].try_into().unwrap());
+        assert_eq!(directory as usize, 30 + 11 + 5 + 30 + 7 + 6);
+        assert_eq!(
+            archive[directory as usize..directory as usize + 4],
+            [0x50, 0x4b, 0x01, 0x02]
+        );
+        // the CRC of the first entry
+        assert_eq!(
+            u32::from_le_bytes(archive[14..18].try_into().unwrap()),
+            crc32fast::hash(b"first")
+        );
+    }
+}
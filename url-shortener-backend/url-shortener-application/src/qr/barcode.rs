@@ -0,0 +1,105 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::Symbology;
+use log::error;
+use rxing::datamatrix::encoder::SymbolShapeHint;
+use rxing::{BarcodeFormat, EncodeHintValue, EncodeHints, MultiFormatWriter, Writer};
+
+/// The dark modules of the payload in the given symbology, one entry per row. PDF417 is the
+/// only one that is not square, its rows are several modules high so line scanners can read them.
+pub(crate) fn encode_barcode(
+    payload: &str,
+    symbology: Symbology,
+) -> Result<Vec<Vec<bool>>, ApiError> {
+    // the renderer draws the quiet zone itself
+    let hints = EncodeHints::default().with(EncodeHintValue::Margin("0".to_string()));
+    let (format, hints) = match symbology {
+        // rectangular symbols are read by fewer scanners
+        Symbology::DataMatrix => (
+            BarcodeFormat::DATA_MATRIX,
+            hints.with(EncodeHintValue::DataMatrixShape(
+                SymbolShapeHint::FORCE_SQUARE,
+            )),
+        ),
+        Symbology::Aztec => (BarcodeFormat::AZTEC, hints),
+        Symbology::Pdf417 => (BarcodeFormat::PDF_417, hints),
+        Symbology::Qr => (BarcodeFormat::QR_CODE, hints),
+    };
+
+    let matrix = MultiFormatWriter
+        .encode_with_hints(payload, &format, 0, 0, &hints)
+        .map_err(|e| {
+            error!("Failed to generate {} code: {:?}", symbology.as_str(), e);
+            ApiError::InternalServerError
+        })?;
+
+    Ok((0..matrix.getHeight())
+        .map(|y| (0..matrix.getWidth()).map(|x| matrix.get(x, y)).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::qr_models::Symbology;
+    use crate::qr::barcode::encode_barcode;
+    use crate::qr::renderer::qr_payload;
+    use rxing::helpers::detect_in_luma;
+    use rxing::BarcodeFormat;
+
+    /// Draws the modules four pixels wide on a four module quiet zone, as a scanner would see them.
+    fn scan(matrix: &[Vec<bool>], format: BarcodeFormat) -> String {
+        let (scale, quiet_zone) = (4, 4);
+        let width = (matrix[0].len() + 2 * quiet_zone) * scale;
+        let height = (matrix.len() + 2 * quiet_zone) * scale;
+        let mut luma = vec![255u8; width * height];
+        for (y, row) in matrix.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, dark)| **dark) {
+                for dy in 0..scale {
+                    let top = (y + quiet_zone) * scale + dy;
+                    let left = (x + quiet_zone) * scale;
+                    luma[top * width + left..top * width + left + scale].fill(0);
+                }
+            }
+        }
+        detect_in_luma(luma, width as u32, height as u32, Some(format))
+            .unwrap()
+            .getText()
+            .to_string()
+    }
+
+    #[test]
+    fn encode_barcode_round_trips_every_symbology() {
+        // Arrange
+        let payload = qr_payload("https://sho.rt", "1234556");
+
+        for (symbology, format) in [
+            (Symbology::DataMatrix, BarcodeFormat::DATA_MATRIX),
+            (Symbology::Aztec, BarcodeFormat::AZTEC),
+            (Symbology::Pdf417, BarcodeFormat::PDF_417),
+        ] {
+            // Act
+            let matrix = encode_barcode(&payload, symbology).unwrap();
+
+            // Assert
+            assert_eq!(scan(&matrix, format), payload, "{}", symbology.as_str());
+        }
+    }
+
+    #[test]
+    fn encode_barcode_keeps_data_matrix_and_aztec_square_and_pdf417_wide() {
+        // Arrange
+        let payload = qr_payload("https://sho.rt", "1234556");
+
+        // Act
+        let data_matrix = encode_barcode(&payload, Symbology::DataMatrix).unwrap();
+        let aztec = encode_barcode(&payload, Symbology::Aztec).unwrap();
+        let pdf417 = encode_barcode(&payload, Symbology::Pdf417).unwrap();
+
+        // Assert
+        assert_eq!(data_matrix.len(), data_matrix[0].len());
+        assert_eq!(aztec.len(), aztec[0].len());
+        assert!(pdf417[0].len() > pdf417.len());
+        // long links need larger symbols, not a failure
+        let long = format!("https://sho.rt/{}", "a".repeat(400));
+        assert!(encode_barcode(&long, Symbology::Aztec).unwrap().len() > aztec.len());
+    }
+}
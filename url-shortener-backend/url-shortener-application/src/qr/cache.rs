@@ -0,0 +1,62 @@
+use coi::{Inject, Provide};
+use lru::LruCache;
+use mockall::automock;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+/// Rendered QR images keyed by their ETag, the key already covers every rendering input.
+#[automock]
+pub trait QrImageCacheTrait: Inject {
+    fn get(&self, etag: &str) -> Option<Vec<u8>>;
+    fn put(&self, etag: String, image: Vec<u8>);
+}
+
+#[derive(Inject)]
+pub struct QrImageCache(Arc<Mutex<LruCache<String, Vec<u8>>>>);
+
+impl QrImageCacheTrait for QrImageCache {
+    fn get(&self, etag: &str) -> Option<Vec<u8>> {
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        cache.get(etag).cloned()
+    }
+
+    fn put(&self, etag: String, image: Vec<u8>) {
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        cache.put(etag, image);
+    }
+}
+
+#[derive(Provide)]
+#[coi(provides dyn QrImageCacheTrait with QrImageCache(self.0.clone()))]
+pub struct QrImageCacheProvider(Arc<Mutex<LruCache<String, Vec<u8>>>>);
+
+pub fn create_qr_image_cache(capacity: usize) -> QrImageCacheProvider {
+    let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+    QrImageCacheProvider(Arc::new(Mutex::new(LruCache::new(capacity))))
+}
+
+// for mocking purposes
+impl Inject for MockQrImageCacheTrait {}
+
+#[cfg(test)]
+mod tests {
+    use crate::qr::cache::{create_qr_image_cache, QrImageCache, QrImageCacheTrait};
+
+    #[test]
+    fn qr_image_cache_evicts_least_recently_used() {
+        // Arrange
+        let provider = create_qr_image_cache(2);
+        let cache = QrImageCache(provider.0.clone());
+        cache.put("a".to_string(), vec![1]);
+        cache.put("b".to_string(), vec![2]);
+
+        // Act
+        let _ = cache.get("a");
+        cache.put("c".to_string(), vec![3]);
+
+        // Assert
+        assert_eq!(cache.get("a"), Some(vec![1]));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(vec![3]));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::QrExportFormat;
+use crate::qr::archive::ZipWriter;
+use crate::qr::label_sheet::{Label, LabelSheetWriter};
+use crate::qr::renderer::{qr_file_name, qr_payload, render_qr, symbol_matrix};
+use crate::qr::settings::QrSettings;
+use chrono::Utc;
+
+/// A link to export with the settings its code is rendered with.
+pub(crate) struct ExportLink {
+    pub short_url: String,
+    pub destination: String,
+    pub settings: QrSettings,
+}
+
+/// Turns batches of links into consecutive parts of one ZIP or PDF file.
+pub(crate) enum QrExportWriter {
+    Zip(ZipWriter),
+    Pdf(LabelSheetWriter),
+}
+
+impl QrExportWriter {
+    /// The writer and the bytes that open the file.
+    pub fn new(format: QrExportFormat) -> (Self, Vec<u8>) {
+        match format {
+            QrExportFormat::Zip => (QrExportWriter::Zip(ZipWriter::new(Utc::now())), Vec::new()),
+            QrExportFormat::Pdf => {
+                let (writer, header) = LabelSheetWriter::new();
+                (QrExportWriter::Pdf(writer), header)
+            }
+        }
+    }
+
+    /// A ZIP gets one image per link named by its short URL, a PDF gets one page of labels.
+    pub fn write(&mut self, domain: &str, links: &[ExportLink]) -> Result<Vec<u8>, ApiError> {
+        match self {
+            QrExportWriter::Zip(writer) => {
+                let mut bytes = Vec::new();
+                for link in links {
+                    let image = render_qr(&qr_payload(domain, &link.short_url), &link.settings)?;
+                    bytes.extend(
+                        writer.entry(&qr_file_name(&link.short_url, link.settings.format), &image),
+                    );
+                }
+                Ok(bytes)
+            }
+            QrExportWriter::Pdf(writer) => {
+                // the label shows the short link without its scheme, it is read by people
+                let host = domain.split_once("://").map_or(domain, |(_, host)| host);
+                let labels = links
+                    .iter()
+                    .map(|link| {
+                        Ok(Label {
+                            matrix: symbol_matrix(
+                                &qr_payload(domain, &link.short_url),
+                                &link.settings,
+                            )?,
+                            quiet_zone: link.settings.quiet_zone,
+                            foreground: link.settings.foreground,
+                            background: link.settings.background,
+                            short_url: format!("{}/{}", host, link.short_url),
+                            title: link.destination.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, ApiError>>()?;
+                Ok(writer.page(&labels))
+            }
+        }
+    }
+
+    /// The bytes that close the file.
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            QrExportWriter::Zip(writer) => writer.finish(),
+            QrExportWriter::Pdf(writer) => writer.finish(),
+        }
+    }
+}
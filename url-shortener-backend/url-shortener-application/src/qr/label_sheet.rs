@@ -0,0 +1,324 @@
+use crate::qr::settings::Rgb;
+use std::fmt::Write;
+
+/// A4 in points.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 36.0;
+const COLUMNS: usize = 3;
+const ROWS: usize = 4;
+pub(crate) const LABELS_PER_PAGE: usize = COLUMNS * ROWS;
+/// The code including its quiet zone, a little under two inches so phones read it from arm's length.
+const CODE_SIDE: f64 = 130.0;
+const SHORT_URL_FONT_SIZE: f64 = 10.0;
+const TITLE_FONT_SIZE: f64 = 7.0;
+const TEXT_PADDING: f64 = 6.0;
+
+const CATALOG_ID: usize = 1;
+const PAGES_ID: usize = 2;
+const FONT_ID: usize = 3;
+
+/// Glyph widths of the standard Helvetica font for ASCII 32 to 126, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// One label: the code's modules, and the two lines of text printed under it.
+pub(crate) struct Label {
+    pub matrix: Vec<Vec<bool>>,
+    pub quiet_zone: u32,
+    pub foreground: Rgb,
+    pub background: Rgb,
+    pub short_url: String,
+    pub title: String,
+}
+
+/// The standard fonts only cover Latin-1 reliably, anything outside printable ASCII becomes `?`.
+fn printable(text: &str) -> String {
+    text.chars()
+        .map(|c| if (' '..='~').contains(&c) { c } else { '?' })
+        .collect()
+}
+
+fn text_width(text: &str, font_size: f64) -> f64 {
+    let units: u32 = text
+        .bytes()
+        .map(|b| HELVETICA_WIDTHS[(b - b' ') as usize] as u32)
+        .sum();
+    units as f64 * font_size / 1000.0
+}
+
+/// Cuts the text to fit the width, marking the cut with an ellipsis.
+fn fit_text(text: &str, font_size: f64, width: f64) -> String {
+    let text = printable(text);
+    if text_width(&text, font_size) <= width {
+        return text;
+    }
+    let mut fitted = text;
+    while !fitted.is_empty() && text_width(&format!("{}...", fitted), font_size) > width {
+        fitted.pop();
+    }
+    format!("{}...", fitted)
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+fn set_color(content: &mut String, Rgb(r, g, b): Rgb) {
+    let _ = writeln!(
+        content,
+        "{:.3} {:.3} {:.3} rg",
+        r as f64 / 255.0,
+        g as f64 / 255.0,
+        b as f64 / 255.0
+    );
+}
+
+/// Draws the code as vector rectangles, one per run of dark modules, so it prints sharp at any size.
+/// A wide symbol keeps the code's width at a lower height, the text follows right below it.
+fn draw_label(content: &mut String, label: &Label, (left, top): (f64, f64), width: f64) {
+    let margins = 2.0 * label.quiet_zone as f64;
+    let module_size = CODE_SIDE / (label.matrix[0].len() as f64 + margins);
+    let code_height = (label.matrix.len() as f64 + margins) * module_size;
+    let code_left = left + (width - CODE_SIDE) / 2.0;
+    let code_bottom = top - code_height;
+
+    set_color(content, label.background);
+    let _ = writeln!(
+        content,
+        "{:.2} {:.2} {:.2} {:.2} re f",
+        code_left, code_bottom, CODE_SIDE, code_height
+    );
+    set_color(content, label.foreground);
+    for (y, row) in label.matrix.iter().enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            if !row[x] {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < row.len() && row[x] {
+                x += 1;
+            }
+            let _ = writeln!(
+                content,
+                "{:.2} {:.2} {:.2} {:.2} re",
+                code_left + (start as f64 + label.quiet_zone as f64) * module_size,
+                top - (y as f64 + 1.0 + label.quiet_zone as f64) * module_size,
+                (x - start) as f64 * module_size,
+                module_size
+            );
+        }
+    }
+    let _ = writeln!(content, "f");
+
+    set_color(content, Rgb(0, 0, 0));
+    let mut baseline = code_bottom - TEXT_PADDING;
+    for (text, font_size) in [
+        (&label.short_url, SHORT_URL_FONT_SIZE),
+        (&label.title, TITLE_FONT_SIZE),
+    ] {
+        baseline -= font_size;
+        let text = fit_text(text, font_size, width - 2.0 * TEXT_PADDING);
+        let _ = writeln!(
+            content,
+            "BT /F1 {} Tf {:.2} {:.2} Td ({}) Tj ET",
+            font_size,
+            left + (width - text_width(&text, font_size)) / 2.0,
+            baseline,
+            escape_text(&text)
+        );
+        baseline -= TEXT_PADDING / 2.0;
+    }
+}
+
+/// Writes a PDF label sheet page by page, so each page can be sent as soon as its codes are
+/// rendered. Only the object offsets are held until the cross-reference table at the end.
+pub(crate) struct LabelSheetWriter {
+    offset: usize,
+    /// The byte offset of each object, by object number minus one.
+    objects: Vec<usize>,
+    pages: Vec<usize>,
+}
+
+impl LabelSheetWriter {
+    /// Starts the document, the catalog and the page tree are written last once all pages are known.
+    pub fn new() -> (Self, Vec<u8>) {
+        let mut writer = Self {
+            offset: 0,
+            objects: vec![0; FONT_ID],
+            pages: Vec::new(),
+        };
+        // the binary comment marks the file as binary for transfer tools
+        let mut bytes = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        writer.offset = bytes.len();
+        bytes.extend(writer.object(
+            FONT_ID,
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>",
+        ));
+        (writer, bytes)
+    }
+
+    /// A page with up to `LABELS_PER_PAGE` labels, filled row by row.
+    pub fn page(&mut self, labels: &[Label]) -> Vec<u8> {
+        let width = (PAGE_WIDTH - 2.0 * MARGIN) / COLUMNS as f64;
+        let height = (PAGE_HEIGHT - 2.0 * MARGIN) / ROWS as f64;
+        let mut content = String::new();
+        for (i, label) in labels.iter().take(LABELS_PER_PAGE).enumerate() {
+            let left = MARGIN + (i % COLUMNS) as f64 * width;
+            let top = PAGE_HEIGHT - MARGIN - (i / COLUMNS) as f64 * height;
+            draw_label(&mut content, label, (left, top - TEXT_PADDING), width);
+        }
+
+        let content_id = self.allocate();
+        let mut bytes = self.object(
+            content_id,
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            )
+            .as_bytes(),
+        );
+        let page_id = self.allocate();
+        bytes.extend(
+            self.object(
+                page_id,
+                format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 {} 0 R >> >> /Contents {} 0 R >>",
+                    PAGES_ID, PAGE_WIDTH, PAGE_HEIGHT, FONT_ID, content_id
+                )
+                .as_bytes(),
+            ),
+        );
+        self.pages.push(page_id);
+        bytes
+    }
+
+    /// The page tree, the catalog and the cross-reference table, which close the document.
+    pub fn finish(mut self) -> Vec<u8> {
+        let kids = self
+            .pages
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut bytes = self.object(
+            PAGES_ID,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids,
+                self.pages.len()
+            )
+            .as_bytes(),
+        );
+        bytes.extend(self.object(
+            CATALOG_ID,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID).as_bytes(),
+        ));
+
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in &self.objects {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            xref,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            CATALOG_ID,
+            self.offset
+        );
+        bytes.extend(xref.into_bytes());
+        bytes
+    }
+
+    fn allocate(&mut self) -> usize {
+        self.objects.push(0);
+        self.objects.len()
+    }
+
+    fn object(&mut self, id: usize, body: &[u8]) -> Vec<u8> {
+        let mut bytes = format!("{} 0 obj\n", id).into_bytes();
+        bytes.extend_from_slice(body);
+        bytes.extend_from_slice(b"\nendobj\n");
+        self.objects[id - 1] = self.offset;
+        self.offset += bytes.len();
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qr::label_sheet::{fit_text, Label, LabelSheetWriter};
+    use crate::qr::settings::Rgb;
+
+    fn label(short_url: &str) -> Label {
+        Label {
+            matrix: vec![vec![true, false], vec![false, true]],
+            quiet_zone: 1,
+            foreground: Rgb(0, 0, 0),
+            background: Rgb(255, 255, 255),
+            short_url: short_url.to_string(),
+            title: "https://www.google.com/(search)".to_string(),
+        }
+    }
+
+    #[test]
+    fn label_sheet_writer_builds_a_consistent_document() {
+        // Arrange
+        let (mut writer, mut document) = LabelSheetWriter::new();
+
+        // Act
+        document.extend(writer.page(&[label("sho.rt/1234556"), label("sho.rt/abc")]));
+        document.extend(writer.page(&[label("sho.rt/xyz")]));
+        document.extend(writer.finish());
+
+        // Assert
+        let find = |pattern: &str| {
+            document
+                .windows(pattern.len())
+                .position(|window| window == pattern.as_bytes())
+        };
+        assert!(document.starts_with(b"%PDF-1.4"));
+        assert!(find("/Type /Pages /Kids [5 0 R 7 0 R] /Count 2").is_some());
+        assert!(find("(https://www.google.com/\\(search\\)) Tj").is_some());
+        // every cross-reference entry points at the start of its object
+        let xref = find("xref\n").unwrap();
+        let table = String::from_utf8(document[xref..].to_vec()).unwrap();
+        for (i, line) in table.lines().skip(3).take(7).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(document[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+        let startxref = table.rsplit("startxref\n").next().unwrap();
+        assert_eq!(
+            startxref
+                .trim_end_matches("\n%%EOF\n")
+                .parse::<usize>()
+                .unwrap(),
+            xref
+        );
+    }
+
+    #[test]
+    fn fit_text_truncates_long_titles() {
+        // Act
+        let fitted = fit_text(
+            &format!("https://example.com/{}", "a".repeat(200)),
+            7.0,
+            150.0,
+        );
+
+        // Assert
+        assert!(fitted.ends_with("..."));
+        assert!(fitted.len() < 60);
+        assert_eq!(fit_text("Café", 7.0, 150.0), "Caf?");
+    }
+}
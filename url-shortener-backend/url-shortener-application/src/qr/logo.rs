@@ -0,0 +1,197 @@
+use crate::models::errors::ApiError;
+use image::codecs::png::PngEncoder;
+use image::imageops::grayscale;
+use image::{ExtendedColorType, ImageEncoder, RgbImage};
+use log::{error, warn};
+use rxing::helpers::detect_in_luma;
+use rxing::BarcodeFormat;
+
+const MAX_LOGO_BYTES: usize = 256 * 1024;
+const MIN_LOGO_PIXELS: u32 = 16;
+/// Logos are stored downscaled, which is plenty for screens and ordinary print sizes.
+const MAX_LOGO_PIXELS: u32 = 512;
+/// The finder pattern with its separator, scanners need all three to locate the code.
+const FINDER_EXTENT: usize = 8;
+/// High error correction restores up to 30% of the codewords, a hidden module can belong to
+/// a codeword that is only partly covered, so only half of that budget is spent on the logo.
+const MAX_LOGO_COVERAGE: f64 = 0.15;
+
+/// Decodes an uploaded PNG or JPEG logo and stores it as a PNG, so rendering only ever reads one format.
+pub(crate) fn normalize_logo(bytes: &[u8]) -> Result<Vec<u8>, ApiError> {
+    if bytes.is_empty() || bytes.len() > MAX_LOGO_BYTES {
+        warn!("Invalid logo upload of {} bytes", bytes.len());
+        return Err(ApiError::BadRequest(
+            "The logo must be a PNG or JPEG image of at most 256 KB",
+        ));
+    }
+    let logo = image::load_from_memory(bytes).map_err(|e| {
+        warn!("Failed to decode logo: {:?}", e);
+        ApiError::BadRequest("The logo must be a PNG or JPEG image of at most 256 KB")
+    })?;
+    if logo.width() < MIN_LOGO_PIXELS || logo.height() < MIN_LOGO_PIXELS {
+        warn!("Logo too small: {}x{}", logo.width(), logo.height());
+        return Err(ApiError::BadRequest(
+            "The logo must be at least 16 pixels wide and high",
+        ));
+    }
+
+    let logo = if logo.width() > MAX_LOGO_PIXELS || logo.height() > MAX_LOGO_PIXELS {
+        logo.thumbnail(MAX_LOGO_PIXELS, MAX_LOGO_PIXELS)
+    } else {
+        logo
+    }
+    .to_rgba8();
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(
+            logo.as_raw(),
+            logo.width(),
+            logo.height(),
+            ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| {
+            error!("Failed to encode logo: {:?}", e);
+            ApiError::InternalServerError
+        })?;
+
+    Ok(png)
+}
+
+/// The centered square of modules cleared for the logo, including a one module margin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LogoArea {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl LogoArea {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.start..self.start + self.len).contains(&x)
+            && (self.start..self.start + self.len).contains(&y)
+    }
+}
+
+/// Finds where the logo goes and rejects sizes that cannot work: the finder patterns must stay
+/// intact and no more modules may be hidden than the error correction can restore. Whether the
+/// code really scans is only known once it is drawn, see `ensure_scannable`.
+pub(crate) fn logo_area(modules: usize, logo_size: u32) -> Result<LogoArea, ApiError> {
+    let logo = (modules * logo_size as usize).div_ceil(100);
+    let mut len = logo + 2;
+    // an odd matrix keeps an odd area, so the logo sits exactly in the middle
+    if (modules - len.min(modules)) % 2 == 1 {
+        len += 1;
+    }
+    let len = len.min(modules);
+    let area = LogoArea {
+        start: (modules - len) / 2,
+        len,
+    };
+
+    let coverage = (len * len) as f64 / (modules * modules) as f64;
+    // the area is centered, so clearing the top left finder means it clears all three
+    if area.start < FINDER_EXTENT || coverage > MAX_LOGO_COVERAGE {
+        warn!(
+            "Logo of {}% covers {:.1}% of a {} module code",
+            logo_size,
+            coverage * 100.0,
+            modules
+        );
+        return Err(ApiError::BadRequest(
+            "The logo covers too much of the QR code, choose a smaller logo size",
+        ));
+    }
+
+    Ok(area)
+}
+
+/// Decodes the drawn code and rejects the logo unless the scan returns the payload, a logo that
+/// fits the error correction budget on paper can still blend into the modules around it.
+pub(crate) fn ensure_scannable(payload: &str, image: &RgbImage) -> Result<(), ApiError> {
+    let luma = grayscale(image);
+    let (width, height) = luma.dimensions();
+    match detect_in_luma(luma.into_raw(), width, height, Some(BarcodeFormat::QR_CODE)) {
+        Ok(scan) if scan.getText() == payload => Ok(()),
+        scan => {
+            warn!("QR code with logo does not scan as {}: {:?}", payload, scan);
+            Err(ApiError::BadRequest(
+                "The QR code does not scan with this logo, choose a smaller logo size",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::qr::logo::{ensure_scannable, logo_area, normalize_logo};
+    use crate::qr::renderer::{qr_payload, render_qr};
+    use crate::qr::settings::QrSettings;
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn logo_area_is_centered_and_bounded() {
+        // Arrange
+        let modules = 33;
+
+        // Act
+        let area = logo_area(modules, 20).unwrap();
+
+        // Assert
+        assert_eq!(area.start * 2 + area.len, modules);
+        assert!(area.contains(16, 16));
+        assert!(!area.contains(0, 0));
+        assert_eq!(
+            logo_area(21, 20).unwrap_err(),
+            ApiError::BadRequest(
+                "The logo covers too much of the QR code, choose a smaller logo size"
+            )
+        );
+        assert!(logo_area(modules, 30).is_err());
+    }
+
+    #[test]
+    fn normalize_logo_downscales_and_rejects_garbage() {
+        // Arrange
+        let mut upload = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(2048, 1024))
+            .write_to(&mut Cursor::new(&mut upload), ImageFormat::Png)
+            .unwrap();
+
+        // Act
+        let logo = normalize_logo(&upload).unwrap();
+
+        // Assert
+        let logo = image::load_from_memory(&logo).unwrap();
+        assert_eq!((logo.width(), logo.height()), (512, 256));
+        assert!(normalize_logo(b"not an image").is_err());
+    }
+
+    #[test]
+    fn ensure_scannable_rejects_codes_that_do_not_scan_as_the_payload() {
+        // Arrange
+        let payload = qr_payload("https://sho.rt", "1234556");
+        let png = render_qr(&payload, &QrSettings::default()).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        let mut covered = image.clone();
+        for y in 60..200 {
+            for x in 60..200 {
+                covered.put_pixel(x, y, image::Rgb([255, 255, 255]));
+            }
+        }
+
+        // Act
+        let scanned = ensure_scannable(&payload, &image);
+
+        // Assert
+        assert_eq!(scanned, Ok(()));
+        assert!(ensure_scannable(&qr_payload("https://sho.rt", "other"), &image).is_err());
+        assert_eq!(
+            ensure_scannable(&payload, &covered).unwrap_err(),
+            ApiError::BadRequest(
+                "The QR code does not scan with this logo, choose a smaller logo size"
+            )
+        );
+    }
+}
@@ -0,0 +1,10 @@
+pub(crate) mod archive;
+pub(crate) mod barcode;
+pub mod cache;
+pub(crate) mod export;
+pub(crate) mod font;
+pub(crate) mod label_sheet;
+pub(crate) mod logo;
+pub(crate) mod publish;
+pub(crate) mod renderer;
+pub(crate) mod settings;
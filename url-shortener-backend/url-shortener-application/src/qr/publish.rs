@@ -0,0 +1,98 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::{QrFormat, QrStatus};
+use crate::qr::renderer::{qr_endpoint_url, qr_file_name, qr_file_url};
+use crate::services::qr_upload_service::retry_delay_secs;
+use log::{error, warn};
+use std::env;
+use url_shortener_database::repositories::qr_upload_repository::QrUploadRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+/// Pre-rendering to S3 stays on unless `QR_PRERENDER` is `false`, the on-demand endpoint serves the code either way.
+pub(crate) fn prerender_enabled() -> bool {
+    env::var("QR_PRERENDER").as_deref() != Ok("false")
+}
+
+/// Makes the rendered QR code available and returns where it can be fetched. Callers render
+/// the code even when it is not uploaded, so settings that cannot be drawn are never stored.
+pub(crate) async fn publish_qr(
+    s3_client_wrapper: &dyn S3ClientWrapperTrait,
+    domain: &str,
+    short_url: &str,
+    format: QrFormat,
+    qr_code: Vec<u8>,
+) -> Result<String, ApiError> {
+    if !prerender_enabled() {
+        return Ok(qr_endpoint_url(domain, short_url));
+    }
+
+    let file_name = upload_qr(s3_client_wrapper, short_url, format, qr_code).await?;
+
+    // without a CDN or presigned links the backend serves the uploaded image itself
+    match s3_client_wrapper.image_url(&file_name).await {
+        Ok(Some(url)) => Ok(url),
+        Ok(None) => Ok(qr_file_url(domain, &file_name)),
+        Err(e) => {
+            error!("Failed to get the qr code url: {:?}", e);
+            Err(ApiError::InternalServerError)
+        }
+    }
+}
+
+/// Publishes the code of a link whose upload was queued along with its settings. The queue entry
+/// is removed once the image is stored, a failed upload stays queued for the background retry and
+/// the code is served from the on-demand endpoint meanwhile.
+pub(crate) async fn publish_queued_qr(
+    qr_upload_repository: &dyn QrUploadRepositoryTrait,
+    s3_client_wrapper: &dyn S3ClientWrapperTrait,
+    domain: &str,
+    short_url: &str,
+    format: QrFormat,
+    qr_code: Vec<u8>,
+) -> (String, QrStatus) {
+    match publish_qr(s3_client_wrapper, domain, short_url, format, qr_code).await {
+        Ok(qr_code_image) => {
+            if prerender_enabled() {
+                if let Err(e) = qr_upload_repository.complete(short_url).await {
+                    // the retry only uploads the same image again
+                    warn!("Failed to complete qr upload for {}: {:?}", short_url, e);
+                }
+            }
+            (qr_code_image, QrStatus::Ready)
+        }
+        Err(upload_error) => {
+            if let Err(e) = qr_upload_repository
+                .record_failure(
+                    short_url,
+                    format!("{:?}", upload_error),
+                    retry_delay_secs(0),
+                )
+                .await
+            {
+                warn!(
+                    "Failed to record qr upload failure for {}: {:?}",
+                    short_url, e
+                );
+            }
+            (qr_endpoint_url(domain, short_url), QrStatus::Pending)
+        }
+    }
+}
+
+/// Uploads the rendered QR code under the link's file name, which is returned.
+pub(crate) async fn upload_qr(
+    s3_client_wrapper: &dyn S3ClientWrapperTrait,
+    short_url: &str,
+    format: QrFormat,
+    qr_code: Vec<u8>,
+) -> Result<String, ApiError> {
+    let file_name = qr_file_name(short_url, format);
+    s3_client_wrapper
+        .upload_image(qr_code, &file_name, format.content_type())
+        .await
+        .map_err(|e| {
+            error!("Failed to upload qr code: {:?}", e);
+            ApiError::InternalServerError
+        })?;
+
+    Ok(file_name)
+}
@@ -0,0 +1,648 @@
+use crate::models::click_models::{ClickSource, SOURCE_PARAM};
+use crate::models::errors::ApiError;
+use crate::models::qr_models::{QrErrorCorrection, QrFormat, QrFrameTemplate, Symbology};
+use crate::qr::barcode::encode_barcode;
+use crate::qr::font;
+use crate::qr::logo::{ensure_scannable, logo_area, LogoArea};
+use crate::qr::settings::{QrSettings, Rgb};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{ExtendedColorType, ImageEncoder, RgbImage};
+use log::{error, warn};
+use qrcode_generator::QrCodeEcc;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// The link encoded in a QR code, the marker lets scans of the printed code be told apart from shared links.
+pub(crate) fn qr_payload(domain: &str, short_url: &str) -> String {
+    format!(
+        "{}/{}?{}={}",
+        domain,
+        short_url,
+        SOURCE_PARAM,
+        ClickSource::Qr.as_str()
+    )
+}
+
+pub(crate) fn qr_file_name(short_url: &str, format: QrFormat) -> String {
+    format!("{}.{}", short_url, format.as_str())
+}
+
+/// The format of an uploaded QR image, `None` for names `qr_file_name` never produces.
+pub(crate) fn qr_file_format(file_name: &str) -> Option<QrFormat> {
+    let (short_url, extension) = file_name.rsplit_once('.')?;
+    if short_url.is_empty() || short_url.contains('/') {
+        return None;
+    }
+    [QrFormat::Png, QrFormat::Svg]
+        .into_iter()
+        .find(|format| format.as_str() == extension)
+}
+
+/// Bumped whenever the drawing code changes, so clients drop images rendered by an older version.
+const RENDERER_VERSION: &str = "3";
+
+/// The link to the on-demand rendering, which keeps working when the pre-rendered upload is unavailable.
+pub(crate) fn qr_endpoint_url(domain: &str, short_url: &str) -> String {
+    format!("{}/api/url/{}/qr", domain, short_url)
+}
+
+/// The backend's own link to an uploaded image, for storage that is not behind a CDN.
+pub(crate) fn qr_file_url(domain: &str, file_name: &str) -> String {
+    format!("{}/api/url/qr/files/{}", domain, file_name)
+}
+
+/// A strong ETag derived from every rendering input, rendering is deterministic so equal inputs
+/// always produce byte-identical images and the tag can be computed without rendering.
+pub(crate) fn qr_etag(payload: &str, settings: &QrSettings) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        RENDERER_VERSION,
+        payload,
+        settings.symbology.as_str(),
+        settings.format.as_str(),
+        &settings.size.to_string(),
+        settings.error_correction.as_str(),
+        &settings.foreground.hex(),
+        &settings.background.hex(),
+        &settings.quiet_zone.to_string(),
+        settings
+            .frame
+            .as_ref()
+            .map_or("", |frame| frame.template.as_str()),
+        settings.frame.as_ref().map_or("", |frame| &frame.caption),
+        &settings.logo_size.to_string(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update(b"|");
+    }
+    if let Some(logo) = &settings.logo {
+        hasher.update(logo);
+    }
+    format!("\"{:x}\"", hasher.finalize())
+}
+
+/// The dark modules of the payload encoded in the settings' symbology, without any logo.
+pub(crate) fn symbol_matrix(
+    payload: &str,
+    settings: &QrSettings,
+) -> Result<Vec<Vec<bool>>, ApiError> {
+    match settings.symbology {
+        Symbology::Qr => {
+            let ecc = match settings.effective_error_correction() {
+                QrErrorCorrection::Low => QrCodeEcc::Low,
+                QrErrorCorrection::Medium => QrCodeEcc::Medium,
+                QrErrorCorrection::Quartile => QrCodeEcc::Quartile,
+                QrErrorCorrection::High => QrCodeEcc::High,
+            };
+            qrcode_generator::to_matrix(payload, ecc).map_err(|e| {
+                error!("Failed to generate qr code: {:?}", e);
+                ApiError::InternalServerError
+            })
+        }
+        Symbology::DataMatrix | Symbology::Aztec | Symbology::Pdf417 => {
+            encode_barcode(payload, settings.symbology)
+        }
+    }
+}
+
+/// A logo and the modules cleared for it.
+type PlacedLogo<'a> = (&'a [u8], LogoArea);
+
+const CHECK_QUIET_ZONE: u32 = 4;
+const CHECK_MODULE_PIXELS: u32 = 4;
+
+pub(crate) fn render_qr(payload: &str, settings: &QrSettings) -> Result<Vec<u8>, ApiError> {
+    let mut matrix = symbol_matrix(payload, settings)?;
+
+    // only QR codes carry a logo, the other symbologies have too little error correction to
+    // spare for one. The modules under the logo are cleared so it sits on plain background.
+    let logo = match (&settings.logo, settings.symbology) {
+        (Some(logo), Symbology::Qr) => {
+            let area = logo_area(matrix.len(), settings.logo_size)?;
+            for (y, row) in matrix.iter_mut().enumerate() {
+                for (x, dark) in row.iter_mut().enumerate() {
+                    *dark &= !area.contains(x, y);
+                }
+            }
+            Some((logo.as_slice(), area))
+        }
+        _ => None,
+    };
+
+    if logo.is_some() {
+        // the logo is checked on the bare code with a standard quiet zone, a frame or the served
+        // format do not change which modules it hides
+        let check = QrSettings {
+            format: QrFormat::Png,
+            size: (matrix.len() as u32 + 2 * CHECK_QUIET_ZONE) * CHECK_MODULE_PIXELS,
+            quiet_zone: CHECK_QUIET_ZONE,
+            frame: None,
+            ..settings.clone()
+        };
+        ensure_scannable(payload, &draw_png(&matrix, logo, &check)?)?;
+    }
+
+    match settings.format {
+        QrFormat::Png => render_png(&matrix, logo, settings),
+        QrFormat::Svg => Ok(render_svg(&matrix, logo, settings).into_bytes()),
+    }
+}
+
+/// Modules are drawn a whole number of pixels wide so they stay sharp, the leftover pixels
+/// are split around the code on top of the quiet zone. A wide symbol keeps the requested width
+/// and gets a proportionally lower image. A frame adds the caption band below the code.
+fn render_png(
+    matrix: &[Vec<bool>],
+    logo: Option<PlacedLogo>,
+    settings: &QrSettings,
+) -> Result<Vec<u8>, ApiError> {
+    let image = draw_png(matrix, logo, settings)?;
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| {
+            error!("Failed to encode qr code: {:?}", e);
+            ApiError::InternalServerError
+        })?;
+
+    Ok(png)
+}
+
+fn draw_png(
+    matrix: &[Vec<bool>],
+    logo: Option<PlacedLogo>,
+    settings: &QrSettings,
+) -> Result<RgbImage, ApiError> {
+    let band = settings.frame.as_ref().map_or(0, |_| settings.size / 5);
+    let border = match settings.frame.as_ref().map(|frame| frame.template) {
+        Some(QrFrameTemplate::Border) => (settings.size / 64).max(2),
+        _ => 0,
+    };
+    let side = settings.size - 2 * border;
+
+    let (columns, rows) = (matrix[0].len() as u32, matrix.len() as u32);
+    let module_size = side / (columns + 2 * settings.quiet_zone);
+    if module_size == 0 {
+        warn!(
+            "QR size {} too small for {} modules",
+            settings.size, columns
+        );
+        return Err(ApiError::BadRequest(
+            "The QR size is too small for this link",
+        ));
+    }
+    let side_height = side * (rows + 2 * settings.quiet_zone) / (columns + 2 * settings.quiet_zone);
+    let code_height = side_height + 2 * border;
+    let height = code_height + band;
+    let left_origin = border + (side - module_size * columns) / 2;
+    let top_origin = border + (side_height - module_size * rows) / 2;
+
+    let Rgb(r, g, b) = settings.background;
+    let mut image = RgbImage::from_pixel(settings.size, height, image::Rgb([r, g, b]));
+    for (y, row) in matrix.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, dark)| **dark) {
+            let left = left_origin + x as u32 * module_size;
+            let top = top_origin + y as u32 * module_size;
+            fill(
+                &mut image,
+                (left, top),
+                (module_size, module_size),
+                settings.foreground,
+            );
+        }
+    }
+
+    if let Some((logo, area)) = logo {
+        // the logo keeps a one module margin inside its area
+        let start = (area.start as u32 + 1) * module_size;
+        let extent = (area.len as u32 - 2) * module_size;
+        draw_logo(
+            &mut image,
+            logo,
+            (left_origin + start, top_origin + start),
+            extent,
+        )?;
+    }
+
+    if let Some(frame) = &settings.frame {
+        let caption_color = match frame.template {
+            QrFrameTemplate::Border => {
+                let (width, thickness) = (settings.size, border);
+                fill(&mut image, (0, 0), (width, thickness), settings.foreground);
+                fill(
+                    &mut image,
+                    (0, height - thickness),
+                    (width, thickness),
+                    settings.foreground,
+                );
+                fill(&mut image, (0, 0), (thickness, height), settings.foreground);
+                fill(
+                    &mut image,
+                    (width - thickness, 0),
+                    (thickness, height),
+                    settings.foreground,
+                );
+                settings.foreground
+            }
+            QrFrameTemplate::Banner => {
+                fill(
+                    &mut image,
+                    (0, code_height),
+                    (settings.size, band),
+                    settings.foreground,
+                );
+                settings.background
+            }
+        };
+
+        let text_band = band - border;
+        let scale = (text_band / 2 / font::GLYPH_HEIGHT)
+            .min(settings.size * 9 / 10 / font::text_width(&frame.caption).max(1));
+        if scale == 0 {
+            warn!(
+                "QR size {} too small for caption {:?}",
+                settings.size, frame.caption
+            );
+            return Err(ApiError::BadRequest(
+                "The QR size is too small for this caption",
+            ));
+        }
+        let center = (settings.size / 2, code_height + text_band / 2);
+        font::draw_text(&mut image, &frame.caption, center, scale, caption_color);
+    }
+
+    Ok(image)
+}
+
+fn fill(image: &mut RgbImage, (left, top): (u32, u32), (width, height): (u32, u32), color: Rgb) {
+    let Rgb(r, g, b) = color;
+    for y in top..top + height {
+        for x in left..left + width {
+            image.put_pixel(x, y, image::Rgb([r, g, b]));
+        }
+    }
+}
+
+/// Scales the logo to fit the square, keeping its aspect ratio, and blends it by its alpha.
+fn draw_logo(
+    image: &mut RgbImage,
+    logo: &[u8],
+    (left, top): (u32, u32),
+    extent: u32,
+) -> Result<(), ApiError> {
+    let logo = image::load_from_memory(logo).map_err(|e| {
+        error!("Failed to decode stored logo: {:?}", e);
+        ApiError::InternalServerError
+    })?;
+    let logo = logo.resize(extent, extent, FilterType::Triangle).to_rgba8();
+    let left = left + (extent - logo.width()) / 2;
+    let top = top + (extent - logo.height()) / 2;
+
+    for (x, y, pixel) in logo.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let target = image.get_pixel_mut(left + x, top + y);
+        let alpha = a as u32;
+        for (channel, value) in target.0.iter_mut().zip([r, g, b]) {
+            *channel = ((value as u32 * alpha + *channel as u32 * (255 - alpha)) / 255) as u8;
+        }
+    }
+
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('\'', "&apos;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The view box is measured in modules, so the code scales to any print size without blurring.
+/// A frame adds a one module border or a band under the code, the width stays at the requested size.
+fn render_svg(matrix: &[Vec<bool>], logo: Option<PlacedLogo>, settings: &QrSettings) -> String {
+    let extent = matrix[0].len() as u32 + 2 * settings.quiet_zone;
+    let extent_height = matrix.len() as u32 + 2 * settings.quiet_zone;
+    let template = settings.frame.as_ref().map(|frame| frame.template);
+    let band = settings.frame.as_ref().map_or(0, |_| extent.div_ceil(5));
+    let border = match template {
+        Some(QrFrameTemplate::Border) => 1,
+        _ => 0,
+    };
+    let width = extent + 2 * border;
+    let height = extent_height + 2 * border + band;
+
+    let mut path = String::new();
+    for (y, row) in matrix.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, dark)| **dark) {
+            let _ = write!(
+                path,
+                "M{} {}h1v1h-1z",
+                x as u32 + settings.quiet_zone + border,
+                y as u32 + settings.quiet_zone + border
+            );
+        }
+    }
+
+    let mut body = String::new();
+    let _ = write!(
+        body,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width,
+        height,
+        settings.background.hex()
+    );
+    if let Some(QrFrameTemplate::Border) = template {
+        let _ = write!(
+            body,
+            r#"<path d="M0 0h{w}v{h}h-{w}z M1 1v{ih}h{iw}v-{ih}z" fill="{fg}" fill-rule="evenodd"/>"#,
+            w = width,
+            h = height,
+            iw = width - 2,
+            ih = height - 2,
+            fg = settings.foreground.hex()
+        );
+    }
+    let _ = write!(
+        body,
+        r#"<path d="{}" fill="{}"/>"#,
+        path,
+        settings.foreground.hex()
+    );
+
+    if let Some((logo, area)) = logo {
+        let start = area.start as u32 + 1 + settings.quiet_zone + border;
+        let _ = write!(
+            body,
+            r#"<image x="{start}" y="{start}" width="{size}" height="{size}" preserveAspectRatio="xMidYMid meet" href="data:image/png;base64,{data}"/>"#,
+            start = start,
+            size = area.len - 2,
+            data = BASE64_STANDARD.encode(logo)
+        );
+    }
+
+    if let Some(frame) = &settings.frame {
+        let caption_color = match frame.template {
+            QrFrameTemplate::Border => settings.foreground,
+            QrFrameTemplate::Banner => {
+                let _ = write!(
+                    body,
+                    r#"<rect y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    extent_height,
+                    width,
+                    band,
+                    settings.foreground.hex()
+                );
+                settings.background
+            }
+        };
+        let _ = write!(
+            body,
+            r#"<text x="{x}" y="{y}" font-family="Helvetica, Arial, sans-serif" font-weight="bold" font-size="{font_size}" text-anchor="middle" dominant-baseline="central" fill="{color}">{caption}</text>"#,
+            x = width as f64 / 2.0,
+            y = (extent_height + border) as f64 + band as f64 / 2.0,
+            font_size = band as f64 / 2.0,
+            color = caption_color.hex(),
+            caption = escape_xml(&frame.caption)
+        );
+    }
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width_px}" height="{height_px}" viewBox="0 0 {width} {height}" shape-rendering="crispEdges">{body}</svg>"#,
+        width_px = settings.size,
+        height_px = (settings.size as u64 * height as u64).div_ceil(width as u64),
+        width = width,
+        height = height,
+        body = body,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::qr_models::{QrFormat, QrFrameTemplate, Symbology};
+    use crate::qr::renderer::{qr_etag, qr_payload, render_qr, symbol_matrix};
+    use crate::qr::settings::{QrFrame, QrSettings, Rgb};
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use rxing::helpers::detect_in_luma;
+    use rxing::BarcodeFormat;
+    use std::io::Cursor;
+
+    #[test]
+    fn render_qr_png_uses_requested_size_and_colors() {
+        // Arrange
+        let settings = QrSettings {
+            size: 300,
+            quiet_zone: 4,
+            background: Rgb(255, 250, 240),
+            ..Default::default()
+        };
+
+        // Act
+        let png = render_qr(&qr_payload("https://sho.rt", "1234556"), &settings).unwrap();
+
+        // Assert
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (300, 300));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 250, 240]);
+    }
+
+    #[test]
+    fn render_qr_svg_is_scalable() {
+        // Arrange
+        let settings = QrSettings {
+            format: QrFormat::Svg,
+            size: 512,
+            ..Default::default()
+        };
+
+        // Act
+        let svg = render_qr(&qr_payload("https://sho.rt", "1234556"), &settings).unwrap();
+
+        // Assert
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"width="512""#));
+        assert!(svg.contains(r##"fill="#000000""##));
+    }
+
+    #[test]
+    fn qr_etag_changes_with_domain_and_settings() {
+        // Arrange
+        let settings = QrSettings::default();
+        let payload = qr_payload("https://sho.rt", "1234556");
+
+        // Act
+        let etag = qr_etag(&payload, &settings);
+
+        // Assert
+        assert_eq!(etag, qr_etag(&payload, &settings));
+        assert_ne!(
+            etag,
+            qr_etag(&qr_payload("https://new.sho.rt", "1234556"), &settings)
+        );
+        let larger = QrSettings {
+            size: 512,
+            ..Default::default()
+        };
+        assert_ne!(etag, qr_etag(&payload, &larger));
+    }
+
+    #[test]
+    fn render_qr_png_adds_frame_and_logo() {
+        // Arrange
+        let mut logo = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255])))
+            .write_to(&mut Cursor::new(&mut logo), ImageFormat::Png)
+            .unwrap();
+        let settings = QrSettings {
+            size: 400,
+            frame: Some(QrFrame {
+                template: QrFrameTemplate::Banner,
+                caption: "SCAN ME".to_string(),
+            }),
+            logo: Some(logo),
+            ..Default::default()
+        };
+
+        // Act
+        let png = render_qr(&qr_payload("https://sho.rt", "1234556"), &settings).unwrap();
+
+        // Assert
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (400, 480));
+        assert_eq!(image.get_pixel(200, 200).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(2, 478).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn render_qr_svg_adds_frame_and_logo() {
+        // Arrange
+        let mut logo = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255])))
+            .write_to(&mut Cursor::new(&mut logo), ImageFormat::Png)
+            .unwrap();
+        let settings = QrSettings {
+            format: QrFormat::Svg,
+            frame: Some(QrFrame {
+                template: QrFrameTemplate::Border,
+                caption: "SCAN & GO".to_string(),
+            }),
+            logo: Some(logo.clone()),
+            ..Default::default()
+        };
+
+        // Act
+        let svg = render_qr(&qr_payload("https://sho.rt", "1234556"), &settings).unwrap();
+
+        // Assert
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains(&format!(
+            r#"href="data:image/png;base64,{}""#,
+            BASE64_STANDARD.encode(&logo)
+        )));
+        assert!(svg.contains(">SCAN &amp; GO</text>"));
+    }
+
+    #[test]
+    fn render_qr_supports_data_matrix_and_aztec() {
+        // Arrange
+        let payload = qr_payload("https://sho.rt", "1234556");
+        let data_matrix = QrSettings {
+            symbology: Symbology::DataMatrix,
+            quiet_zone: 0,
+            size: 220,
+            ..Default::default()
+        };
+        let aztec = QrSettings {
+            symbology: Symbology::Aztec,
+            format: QrFormat::Svg,
+            logo: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+
+        // Act
+        let png = render_qr(&payload, &data_matrix).unwrap();
+        let svg = render_qr(&payload, &aztec).unwrap();
+
+        // Assert
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        // a 22 module symbol drawn 10 pixels per module, with the solid finder edge on the left
+        assert_eq!(image.dimensions(), (220, 220));
+        assert!((0..220).all(|y| image.get_pixel(0, y).0 == [0, 0, 0]));
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(!svg.contains("<image"));
+        assert_ne!(
+            qr_etag(&payload, &data_matrix),
+            qr_etag(
+                &payload,
+                &QrSettings {
+                    symbology: Symbology::Qr,
+                    ..data_matrix.clone()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn render_qr_png_scans_in_every_symbology() {
+        // Arrange
+        let payload = qr_payload("https://sho.rt", "1234556");
+
+        for (symbology, format) in [
+            (Symbology::Qr, BarcodeFormat::QR_CODE),
+            (Symbology::DataMatrix, BarcodeFormat::DATA_MATRIX),
+            (Symbology::Aztec, BarcodeFormat::AZTEC),
+            (Symbology::Pdf417, BarcodeFormat::PDF_417),
+        ] {
+            let settings = QrSettings {
+                symbology,
+                size: 512,
+                ..Default::default()
+            };
+
+            // Act
+            let png = render_qr(&payload, &settings).unwrap();
+
+            // Assert
+            let image = image::load_from_memory(&png).unwrap().to_luma8();
+            let (width, height) = image.dimensions();
+            assert_eq!(width, 512);
+            assert_eq!(symbology == Symbology::Pdf417, height < width);
+            let scan = detect_in_luma(image.into_raw(), width, height, Some(format)).unwrap();
+            assert_eq!(scan.getText(), payload, "{}", symbology.as_str());
+        }
+    }
+
+    #[test]
+    fn render_qr_svg_keeps_the_shape_of_wide_symbols() {
+        // Arrange
+        let settings = QrSettings {
+            symbology: Symbology::Pdf417,
+            format: QrFormat::Svg,
+            size: 400,
+            quiet_zone: 2,
+            ..Default::default()
+        };
+        let payload = qr_payload("https://sho.rt", "1234556");
+        let matrix = symbol_matrix(&payload, &settings).unwrap();
+
+        // Act
+        let svg = render_qr(&payload, &settings).unwrap();
+
+        // Assert
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains(&format!(
+            r#"viewBox="0 0 {} {}""#,
+            matrix[0].len() + 4,
+            matrix.len() + 4
+        )));
+    }
+}
@@ -0,0 +1,385 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::{
+    QrErrorCorrection, QrFormat, QrFrameTemplate, QrOptions, Symbology,
+};
+use crate::qr::font;
+use log::warn;
+use url_shortener_database::models::qr_models::UrlQrOptions;
+
+const DEFAULT_SIZE: u32 = 256;
+const MIN_SIZE: u32 = 64;
+const MAX_SIZE: u32 = 4096;
+const DEFAULT_QUIET_ZONE: u32 = 1;
+const MAX_QUIET_ZONE: u32 = 16;
+const DEFAULT_FOREGROUND: Rgb = Rgb(0, 0, 0);
+const DEFAULT_BACKGROUND: Rgb = Rgb(255, 255, 255);
+/// The WCAG AA ratio for text, comfortably above what phone cameras need in poor light.
+const MIN_CONTRAST_RATIO: f64 = 4.5;
+const DEFAULT_CAPTION: &str = "SCAN ME";
+const MAX_CAPTION_LENGTH: usize = 24;
+const DEFAULT_LOGO_SIZE: u32 = 20;
+const MIN_LOGO_SIZE: u32 = 10;
+const MAX_LOGO_SIZE: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Accepts `#RRGGBB` only, so stored colors always round-trip to the same string.
+    fn parse(value: &str) -> Option<Self> {
+        let hex = value.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+
+    fn luminance(&self) -> f64 {
+        let linear = |channel: u8| {
+            let c = channel as f64 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+}
+
+fn contrast_ratio(foreground: Rgb, background: Rgb) -> f64 {
+    let (a, b) = (foreground.luminance(), background.luminance());
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn validate_size(size: u32) -> Result<u32, ApiError> {
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        warn!("Invalid QR size: {}", size);
+        return Err(ApiError::BadRequest(
+            "The QR size must be between 64 and 4096 pixels",
+        ));
+    }
+    Ok(size)
+}
+
+fn validate_caption(caption: &str) -> Result<String, ApiError> {
+    let caption = caption.trim();
+    if caption.is_empty() || caption.chars().count() > MAX_CAPTION_LENGTH {
+        warn!("Invalid QR caption length: {:?}", caption);
+        return Err(ApiError::BadRequest(
+            "The QR caption must have between 1 and 24 characters",
+        ));
+    }
+    if !font::supports(caption) {
+        warn!("Unsupported characters in QR caption: {:?}", caption);
+        return Err(ApiError::BadRequest(
+            "The QR caption may only use letters, digits, spaces and . , ! ? - : ' & /",
+        ));
+    }
+    // the bitmap font only has capitals, the SVG caption matches it
+    Ok(caption.to_ascii_uppercase())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QrFrame {
+    pub template: QrFrameTemplate,
+    pub caption: String,
+}
+
+/// Fully resolved QR rendering options, with every default applied.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QrSettings {
+    pub symbology: Symbology,
+    pub format: QrFormat,
+    pub size: u32,
+    pub error_correction: QrErrorCorrection,
+    pub foreground: Rgb,
+    pub background: Rgb,
+    pub quiet_zone: u32,
+    pub frame: Option<QrFrame>,
+    /// A normalized PNG, see `qr::logo::normalize_logo`.
+    pub logo: Option<Vec<u8>>,
+    /// The logo's width as a percentage of the code's width.
+    pub logo_size: u32,
+}
+
+/// The image format stored with a link's options, anything unreadable falls back to PNG.
+pub(crate) fn stored_format(format: &str) -> QrFormat {
+    match format {
+        "svg" => QrFormat::Svg,
+        _ => QrFormat::Png,
+    }
+}
+
+impl Default for QrSettings {
+    fn default() -> Self {
+        Self {
+            symbology: Symbology::default(),
+            format: QrFormat::default(),
+            size: DEFAULT_SIZE,
+            error_correction: QrErrorCorrection::default(),
+            foreground: DEFAULT_FOREGROUND,
+            background: DEFAULT_BACKGROUND,
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            frame: None,
+            logo: None,
+            logo_size: DEFAULT_LOGO_SIZE,
+        }
+    }
+}
+
+impl QrSettings {
+    pub fn from_options(options: &QrOptions) -> Result<Self, ApiError> {
+        let defaults = Self::default();
+        let size = validate_size(options.size.unwrap_or(defaults.size))?;
+
+        let quiet_zone = options.quiet_zone.unwrap_or(defaults.quiet_zone);
+        if quiet_zone > MAX_QUIET_ZONE {
+            warn!("Invalid QR quiet zone: {}", quiet_zone);
+            return Err(ApiError::BadRequest(
+                "The QR quiet zone must be at most 16 modules",
+            ));
+        }
+
+        let frame = match (options.frame, &options.caption) {
+            (Some(template), caption) => Some(QrFrame {
+                template,
+                caption: validate_caption(caption.as_deref().unwrap_or(DEFAULT_CAPTION))?,
+            }),
+            (None, Some(_)) => {
+                return Err(ApiError::BadRequest("A QR caption needs a frame"));
+            }
+            (None, None) => None,
+        };
+
+        let logo_size = options.logo_size.unwrap_or(defaults.logo_size);
+        if !(MIN_LOGO_SIZE..=MAX_LOGO_SIZE).contains(&logo_size) {
+            warn!("Invalid QR logo size: {}", logo_size);
+            return Err(ApiError::BadRequest(
+                "The QR logo size must be between 10 and 30 percent",
+            ));
+        }
+
+        let parse_color = |value: &Option<String>, default: Rgb| match value {
+            Some(value) => Rgb::parse(value).ok_or_else(|| {
+                warn!("Invalid QR color: {:?}", value);
+                ApiError::BadRequest("QR colors must be given as #RRGGBB")
+            }),
+            None => Ok(default),
+        };
+        let foreground = parse_color(&options.foreground, defaults.foreground)?;
+        let background = parse_color(&options.background, defaults.background)?;
+
+        // most scanners only read dark modules on a light background
+        if foreground.luminance() >= background.luminance() {
+            warn!(
+                "Inverted QR colors: {} on {}",
+                foreground.hex(),
+                background.hex()
+            );
+            return Err(ApiError::BadRequest(
+                "The QR foreground must be darker than its background",
+            ));
+        }
+        if contrast_ratio(foreground, background) < MIN_CONTRAST_RATIO {
+            warn!(
+                "Low contrast QR colors: {} on {}",
+                foreground.hex(),
+                background.hex()
+            );
+            return Err(ApiError::BadRequest(
+                "The QR colors do not have enough contrast",
+            ));
+        }
+
+        Ok(Self {
+            symbology: options.symbology.unwrap_or(defaults.symbology),
+            format: options.format.unwrap_or(defaults.format),
+            size,
+            error_correction: options
+                .error_correction
+                .unwrap_or(defaults.error_correction),
+            foreground,
+            background,
+            quiet_zone,
+            frame,
+            logo: None,
+            logo_size,
+        })
+    }
+
+    pub fn with_logo(self, logo: Option<Vec<u8>>) -> Self {
+        Self { logo, ..self }
+    }
+
+    /// A logo hides part of the code, so it is always rendered with the highest error correction.
+    pub fn effective_error_correction(&self) -> QrErrorCorrection {
+        match self.logo {
+            Some(_) => QrErrorCorrection::High,
+            None => self.error_correction,
+        }
+    }
+
+    /// Applies the per-request symbology, format and size on top of the link's own settings.
+    pub fn with_overrides(
+        self,
+        symbology: Option<Symbology>,
+        format: Option<QrFormat>,
+        size: Option<u32>,
+    ) -> Result<Self, ApiError> {
+        Ok(Self {
+            symbology: symbology.unwrap_or(self.symbology),
+            format: format.unwrap_or(self.format),
+            size: validate_size(size.unwrap_or(self.size))?,
+            ..self
+        })
+    }
+
+    /// Stored options were validated when they were saved, anything unreadable falls back to its default.
+    pub fn from_record(record: &UrlQrOptions) -> Self {
+        let defaults = Self::default();
+        Self {
+            symbology: match record.symbology.as_str() {
+                "datamatrix" => Symbology::DataMatrix,
+                "aztec" => Symbology::Aztec,
+                "pdf417" => Symbology::Pdf417,
+                _ => Symbology::Qr,
+            },
+            format: stored_format(&record.format),
+            size: u32::try_from(record.size).unwrap_or(defaults.size),
+            error_correction: match record.error_correction.as_str() {
+                "medium" => QrErrorCorrection::Medium,
+                "quartile" => QrErrorCorrection::Quartile,
+                "high" => QrErrorCorrection::High,
+                _ => QrErrorCorrection::Low,
+            },
+            foreground: Rgb::parse(&record.foreground).unwrap_or(defaults.foreground),
+            background: Rgb::parse(&record.background).unwrap_or(defaults.background),
+            quiet_zone: u32::try_from(record.quiet_zone).unwrap_or(defaults.quiet_zone),
+            frame: record.frame.as_deref().and_then(|frame| {
+                let template = match frame {
+                    "border" => QrFrameTemplate::Border,
+                    "banner" => QrFrameTemplate::Banner,
+                    _ => return None,
+                };
+                Some(QrFrame {
+                    template,
+                    caption: record
+                        .caption
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_CAPTION.to_string()),
+                })
+            }),
+            logo: record.logo.clone(),
+            logo_size: u32::try_from(record.logo_size).unwrap_or(defaults.logo_size),
+        }
+    }
+
+    pub fn to_record(&self, url_id: &str) -> UrlQrOptions {
+        UrlQrOptions {
+            url_id: url_id.to_string(),
+            symbology: self.symbology.as_str().to_string(),
+            format: self.format.as_str().to_string(),
+            size: self.size as i32,
+            error_correction: self.error_correction.as_str().to_string(),
+            foreground: self.foreground.hex(),
+            background: self.background.hex(),
+            quiet_zone: self.quiet_zone as i32,
+            frame: self
+                .frame
+                .as_ref()
+                .map(|frame| frame.template.as_str().to_string()),
+            caption: self.frame.as_ref().map(|frame| frame.caption.clone()),
+            logo: self.logo.clone(),
+            logo_size: self.logo_size as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::qr_models::{QrErrorCorrection, QrFormat, QrFrameTemplate, QrOptions};
+    use crate::qr::settings::{QrSettings, Rgb};
+
+    #[test]
+    fn from_options_applies_defaults_and_round_trips() {
+        // Arrange
+        let options = QrOptions {
+            format: Some(QrFormat::Svg),
+            error_correction: Some(QrErrorCorrection::High),
+            foreground: Some("#1a2b3c".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let settings = QrSettings::from_options(&options).unwrap();
+        let record = settings.to_record("1234556");
+
+        // Assert
+        assert_eq!(settings.size, 256);
+        assert_eq!(settings.foreground, Rgb(0x1a, 0x2b, 0x3c));
+        assert_eq!(record.foreground, "#1A2B3C");
+        assert_eq!(QrSettings::from_record(&record), settings);
+    }
+
+    #[test]
+    fn from_options_rejects_poor_contrast() {
+        // Arrange
+        let low_contrast = QrOptions {
+            foreground: Some("#777777".to_string()),
+            background: Some("#888888".to_string()),
+            ..Default::default()
+        };
+        let inverted = QrOptions {
+            foreground: Some("#FFFFFF".to_string()),
+            background: Some("#000000".to_string()),
+            ..Default::default()
+        };
+
+        // Act & Assert
+        assert_eq!(
+            QrSettings::from_options(&low_contrast).unwrap_err(),
+            ApiError::BadRequest("The QR colors do not have enough contrast")
+        );
+        assert_eq!(
+            QrSettings::from_options(&inverted).unwrap_err(),
+            ApiError::BadRequest("The QR foreground must be darker than its background")
+        );
+    }
+
+    #[test]
+    fn from_options_validates_frame_caption() {
+        // Arrange
+        let framed = QrOptions {
+            frame: Some(QrFrameTemplate::Banner),
+            caption: Some(" Scan me! ".to_string()),
+            ..Default::default()
+        };
+        let unframed = QrOptions {
+            caption: Some("Scan me".to_string()),
+            ..Default::default()
+        };
+        let unsupported = QrOptions {
+            frame: Some(QrFrameTemplate::Border),
+            caption: Some("Scan me 📱".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let settings = QrSettings::from_options(&framed).unwrap();
+
+        // Assert
+        assert_eq!(settings.frame.unwrap().caption, "SCAN ME!");
+        assert_eq!(
+            QrSettings::from_options(&unframed).unwrap_err(),
+            ApiError::BadRequest("A QR caption needs a frame")
+        );
+        assert!(QrSettings::from_options(&unsupported).is_err());
+    }
+}
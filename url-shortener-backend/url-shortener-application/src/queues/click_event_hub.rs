@@ -0,0 +1,36 @@
+use crate::models::click_event_models::ClickEventModel;
+use coi::{Inject, Provide};
+use mockall::automock;
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+
+#[automock]
+pub trait ClickEventHubTrait: Inject {
+    fn dispatch(&self, event: ClickEventModel);
+    fn subscribe(&self) -> Receiver<ClickEventModel>;
+}
+
+#[derive(Inject)]
+pub struct ClickEventHub(Sender<ClickEventModel>);
+
+impl ClickEventHubTrait for ClickEventHub {
+    fn dispatch(&self, event: ClickEventModel) {
+        // sending only fails when nobody is watching, which is the common case
+        let _ = self.0.send(event);
+    }
+
+    fn subscribe(&self) -> Receiver<ClickEventModel> {
+        self.0.subscribe()
+    }
+}
+
+#[derive(Provide)]
+#[coi(provides dyn ClickEventHubTrait with ClickEventHub(self.0.clone()))]
+pub struct ClickEventHubProvider(Sender<ClickEventModel>);
+
+pub fn create_click_event_hub(capacity: usize) -> ClickEventHubProvider {
+    let (sender, _) = channel(capacity);
+    ClickEventHubProvider(sender)
+}
+
+// for mocking purposes
+impl Inject for MockClickEventHubTrait {}
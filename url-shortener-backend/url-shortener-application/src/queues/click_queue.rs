@@ -0,0 +1,44 @@
+use coi::{Inject, Provide};
+use log::{error, warn};
+use mockall::automock;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use url_shortener_database::models::click_models::Click;
+
+#[automock]
+pub trait ClickQueueTrait: Inject {
+    fn enqueue(&self, click: Click);
+}
+
+#[derive(Inject)]
+pub struct ClickQueue(Sender<Click>);
+
+impl ClickQueueTrait for ClickQueue {
+    fn enqueue(&self, click: Click) {
+        // never wait for room in the queue, the redirect must not be slowed down by analytics
+        match self.0.try_send(click) {
+            Ok(_) => {}
+            Err(TrySendError::Full(click)) => {
+                warn!("Click queue is full, dropping click for: {}", click.url_id)
+            }
+            Err(TrySendError::Closed(click)) => {
+                error!(
+                    "Click queue is closed, dropping click for: {}",
+                    click.url_id
+                )
+            }
+        }
+    }
+}
+
+#[derive(Provide)]
+#[coi(provides dyn ClickQueueTrait with ClickQueue(self.0.clone()))]
+pub struct ClickQueueProvider(Sender<Click>);
+
+pub fn create_click_queue(capacity: usize) -> (ClickQueueProvider, Receiver<Click>) {
+    let (sender, receiver) = channel(capacity);
+    (ClickQueueProvider(sender), receiver)
+}
+
+// for mocking purposes
+impl Inject for MockClickQueueTrait {}
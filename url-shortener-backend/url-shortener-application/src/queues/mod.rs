@@ -0,0 +1,2 @@
+pub mod click_event_hub;
+pub mod click_queue;
@@ -0,0 +1,301 @@
+use crate::analytics::counters::{
+    claim_key, is_abandoned, new_flush_id, parse_daily_counter_member, unflushed_counter_key,
+    CLAIMED_COUNTERS_KEY, COUNTER_PREFIX, PENDING_COUNTERS_KEY,
+};
+use crate::models::errors::ApiError;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use coi::Inject;
+use log::{error, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+use url_shortener_database::models::click_models::ClickCount;
+use url_shortener_database::repositories::click_count_repository::ClickCountRepositoryTrait;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+const FLUSH_BATCH_SIZE: usize = 1_000;
+const ABANDONED_CLAIM_MINUTES: i64 = 5;
+const FLUSH_ID_RETENTION_DAYS: i64 = 7;
+
+#[async_trait]
+pub trait ClickCountServiceTrait: Inject {
+    async fn flush_counts(&self) -> Result<usize, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickCountServiceTrait with ClickCountService::new(click_count_repository, redis_client_wrapper))]
+struct ClickCountService {
+    #[coi(inject)]
+    click_count_repository: Arc<dyn ClickCountRepositoryTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl ClickCountService {
+    pub fn new(
+        click_count_repository: Arc<dyn ClickCountRepositoryTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            click_count_repository,
+            redis_client_wrapper,
+        }
+    }
+
+    async fn recover_abandoned_claims(&self) -> Result<usize, ApiError> {
+        let flush_ids = self
+            .redis_client_wrapper
+            .get_set_members(CLAIMED_COUNTERS_KEY)
+            .await
+            .map_err(|e| {
+                error!("Failed to get claimed counters: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        let now = Utc::now();
+        let mut flushed = 0;
+        for flush_id in flush_ids
+            .iter()
+            .filter(|id| is_abandoned(id, now, Duration::minutes(ABANDONED_CLAIM_MINUTES)))
+        {
+            warn!("Recovering abandoned click count flush: {}", flush_id);
+            let claimed = self
+                .redis_client_wrapper
+                .get_counters(&claim_key(flush_id))
+                .await
+                .map_err(|e| {
+                    error!("Failed to get claimed counters: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+            flushed += self.commit_claim(flush_id, claimed).await?;
+        }
+
+        Ok(flushed)
+    }
+
+    async fn commit_claim(
+        &self,
+        flush_id: &str,
+        claimed: HashMap<String, i64>,
+    ) -> Result<usize, ApiError> {
+        let counts: Vec<ClickCount> = claimed
+            .into_iter()
+            .filter_map(
+                |(member, count)| match parse_daily_counter_member(&member) {
+                    Some((url_id, day)) => Some(ClickCount { url_id, day, count }),
+                    None => {
+                        warn!("Skipping malformed click counter: {}", member);
+                        None
+                    }
+                },
+            )
+            .collect();
+        let flushed = counts.len();
+        let mut unflushed: HashMap<String, i64> = HashMap::new();
+        for count in &counts {
+            *unflushed
+                .entry(unflushed_counter_key(&count.url_id))
+                .or_default() += count.count;
+        }
+
+        if !counts.is_empty() {
+            let applied = self
+                .click_count_repository
+                .apply_flush(flush_id, counts)
+                .await
+                .map_err(|e| {
+                    error!("Failed to flush click counts: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+            if !applied {
+                warn!("Click count flush already applied: {}", flush_id);
+            }
+        }
+
+        // the claim is only released once Postgres has the counts, a crash before this point is recovered later
+        if let Err(e) = self
+            .redis_client_wrapper
+            .release_claim(
+                &claim_key(flush_id),
+                CLAIMED_COUNTERS_KEY,
+                flush_id,
+                unflushed.into_iter().collect(),
+            )
+            .await
+        {
+            warn!("Failed to release claimed counters: {}", e);
+        }
+
+        Ok(flushed)
+    }
+}
+
+#[async_trait]
+impl ClickCountServiceTrait for ClickCountService {
+    async fn flush_counts(&self) -> Result<usize, ApiError> {
+        let mut flushed = self.recover_abandoned_claims().await?;
+
+        // abandoned claims were just recovered, a flush id only has to outlive the retries of a
+        // claim whose release failed
+        if let Err(e) = self
+            .click_count_repository
+            .prune_flushes(Utc::now() - Duration::days(FLUSH_ID_RETENTION_DAYS))
+            .await
+        {
+            warn!("Failed to prune click count flushes: {:?}", e);
+        }
+
+        loop {
+            let flush_id = new_flush_id(Utc::now());
+            self.redis_client_wrapper
+                .add_to_set(CLAIMED_COUNTERS_KEY, &flush_id)
+                .await
+                .map_err(|e| {
+                    error!("Failed to register click count flush: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+
+            let claimed = self
+                .redis_client_wrapper
+                .claim_counters(
+                    PENDING_COUNTERS_KEY,
+                    COUNTER_PREFIX,
+                    &claim_key(&flush_id),
+                    FLUSH_BATCH_SIZE,
+                )
+                .await
+                .map_err(|e| {
+                    error!("Failed to claim click counters: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+            let claimed_count = claimed.len();
+
+            flushed += self.commit_claim(&flush_id, claimed).await?;
+
+            if claimed_count < FLUSH_BATCH_SIZE {
+                return Ok(flushed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::services::click_count_service::ClickCountServiceTrait;
+    use chrono::{DateTime, Duration, Utc};
+    use error_stack::Report;
+    use mockall::predicate::{always, eq, function};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use url_shortener_database::models::click_models::ClickCount;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::repositories::click_count_repository::MockClickCountRepositoryTrait;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const ABANDONED_FLUSH_ID: &str = "1000-abcdefgh";
+
+    fn setup_mocks() -> (MockClickCountRepositoryTrait, MockRedisClientWrapperTrait) {
+        let mut click_count_repository = MockClickCountRepositoryTrait::new();
+        click_count_repository
+            .expect_prune_flushes()
+            .with(function(|before: &DateTime<Utc>| {
+                Utc::now() - *before > Duration::days(6)
+            }))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(2) }));
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_add_to_set()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        (click_count_repository, redis_client)
+    }
+
+    #[tokio::test]
+    async fn flush_counts_recovers_abandoned_claim() {
+        // Arrange
+        let (mut click_count_repository, mut redis_client) = setup_mocks();
+        redis_client
+            .expect_get_set_members()
+            .returning(|_| Box::pin(async { Ok(vec![ABANDONED_FLUSH_ID.to_string()]) }));
+        redis_client
+            .expect_get_counters()
+            .with(eq("click_counts:claimed:1000-abcdefgh"))
+            .returning(|_| {
+                Box::pin(async { Ok(HashMap::from([("abc:2025-01-30".to_string(), 3)])) })
+            });
+        redis_client
+            .expect_claim_counters()
+            .returning(|_, _, _, _| Box::pin(async { Ok(HashMap::new()) }));
+        redis_client
+            .expect_release_claim()
+            .with(
+                eq("click_counts:claimed:1000-abcdefgh"),
+                eq("click_counts:claimed"),
+                eq(ABANDONED_FLUSH_ID),
+                eq(vec![("click_count_unflushed:abc".to_string(), 3)]),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_release_claim()
+            .returning(|_, _, _, _| Box::pin(async { Ok(()) }));
+        click_count_repository
+            .expect_apply_flush()
+            .with(
+                eq(ABANDONED_FLUSH_ID),
+                function(|counts: &Vec<ClickCount>| counts.len() == 1 && counts[0].count == 3),
+            )
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(false) }));
+        let click_count_service =
+            super::ClickCountService::new(Arc::new(click_count_repository), Arc::new(redis_client));
+
+        // Act
+        let result = click_count_service.flush_counts().await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn flush_counts_on_database_keeps_claim() {
+        // Arrange
+        let mut click_count_repository = MockClickCountRepositoryTrait::new();
+        click_count_repository
+            .expect_prune_flushes()
+            .returning(|_| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_get_set_members()
+            .returning(|_| Box::pin(async { Ok(vec![]) }));
+        redis_client
+            .expect_add_to_set()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_claim_counters()
+            .with(
+                eq("click_counts:pending"),
+                eq("click_count:"),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| {
+                Box::pin(async { Ok(HashMap::from([("abc:2025-01-30".to_string(), 3)])) })
+            });
+        redis_client.expect_release_claim().never();
+        click_count_repository
+            .expect_apply_flush()
+            .returning(|_, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let click_count_service =
+            super::ClickCountService::new(Arc::new(click_count_repository), Arc::new(redis_client));
+
+        // Act
+        let result = click_count_service.flush_counts().await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+}
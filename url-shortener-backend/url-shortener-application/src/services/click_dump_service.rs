@@ -0,0 +1,263 @@
+use crate::analytics::parquet_writer::ClickParquetWriter;
+use crate::analytics::privacy::ip_export_mode;
+use crate::models::errors::ApiError;
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate, Utc};
+use coi::Inject;
+use log::{error, info};
+use std::env;
+use std::sync::Arc;
+use url_shortener_database::repositories::click_dump_repository::ClickDumpRepositoryTrait;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+const DEFAULT_DUMP_PREFIX: &str = "analytics";
+const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+const DUMP_PAGE_SIZE: i64 = 10_000;
+const MAX_DAYS_PER_RUN: usize = 31;
+
+#[async_trait]
+pub trait ClickDumpServiceTrait: Inject {
+    /// Dumps every finished UTC day that has not been dumped yet, oldest first.
+    async fn dump_pending_days(&self) -> Result<usize, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickDumpServiceTrait with ClickDumpService::new(click_repository, click_dump_repository, s3_client_wrapper))]
+struct ClickDumpService {
+    #[coi(inject)]
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+    #[coi(inject)]
+    click_dump_repository: Arc<dyn ClickDumpRepositoryTrait>,
+    #[coi(inject)]
+    s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+}
+
+impl ClickDumpService {
+    pub fn new(
+        click_repository: Arc<dyn ClickRepositoryTrait>,
+        click_dump_repository: Arc<dyn ClickDumpRepositoryTrait>,
+        s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            click_repository,
+            click_dump_repository,
+            s3_client_wrapper,
+        }
+    }
+
+    /// Partitioned by day so warehouse loaders can pick up each file as its own partition.
+    fn dump_key(day: NaiveDate) -> String {
+        let prefix =
+            env::var("ANALYTICS_DUMP_PREFIX").unwrap_or_else(|_| DEFAULT_DUMP_PREFIX.to_string());
+        format!(
+            "{}/clicks/dt={}/clicks.parquet",
+            prefix.trim_matches('/'),
+            day.format("%Y-%m-%d")
+        )
+    }
+
+    async fn first_pending_day(&self) -> Result<Option<NaiveDate>, ApiError> {
+        let last_dumped = self
+            .click_dump_repository
+            .last_dumped_day()
+            .await
+            .map_err(|e| {
+                error!("Failed to get the last click dump: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        if let Some(day) = last_dumped {
+            return Ok(day.succ_opt());
+        }
+
+        let first_click = self
+            .click_repository
+            .first_clicked_at()
+            .await
+            .map_err(|e| {
+                error!("Failed to get the first click: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        Ok(first_click.map(|clicked_at| clicked_at.date_naive()))
+    }
+
+    async fn dump_day(&self, day: NaiveDate) -> Result<(), ApiError> {
+        let parquet_error = |e| {
+            error!("Failed to write click dump for {}: {:?}", day, e);
+            ApiError::InternalServerError
+        };
+        let from = day.and_time(Default::default()).and_utc();
+        let to = from + Days::new(1);
+
+        let mut writer = ClickParquetWriter::new(ip_export_mode()).map_err(parquet_error)?;
+        let mut after_id = 0;
+        loop {
+            let clicks = self
+                .click_repository
+                .find_all_page(from, to, after_id, DUMP_PAGE_SIZE)
+                .await
+                .map_err(|e| {
+                    error!("Failed to read clicks for {}: {:?}", day, e);
+                    ApiError::InternalServerError
+                })?;
+            writer.write_page(&clicks).map_err(parquet_error)?;
+
+            match clicks.last() {
+                Some(click) if clicks.len() as i64 == DUMP_PAGE_SIZE => after_id = click.id,
+                _ => break,
+            }
+        }
+        let rows = writer.rows();
+        let file = writer.finish().map_err(parquet_error)?;
+
+        let key = Self::dump_key(day);
+        self.s3_client_wrapper
+            .upload_object(file, &key, PARQUET_CONTENT_TYPE)
+            .await
+            .map_err(|e| {
+                error!("Failed to upload click dump {}: {:?}", key, e);
+                ApiError::InternalServerError
+            })?;
+
+        // recorded last, a crash before this point dumps the day again to the same key
+        self.click_dump_repository
+            .record_dump(day, &key, rows as i64)
+            .await
+            .map_err(|e| {
+                error!("Failed to record click dump {}: {:?}", key, e);
+                ApiError::InternalServerError
+            })?;
+
+        info!("Dumped {} clicks for {} to {}", rows, day, key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ClickDumpServiceTrait for ClickDumpService {
+    async fn dump_pending_days(&self) -> Result<usize, ApiError> {
+        let Some(first_day) = self.first_pending_day().await? else {
+            return Ok(0);
+        };
+        // only whole days are dumped, today is still receiving clicks
+        let last_day = Utc::now().date_naive() - Days::new(1);
+
+        let mut dumped = 0;
+        for day in first_day
+            .iter_days()
+            .take_while(|day| *day <= last_day)
+            .take(MAX_DAYS_PER_RUN)
+        {
+            self.dump_day(day).await?;
+            dumped += 1;
+        }
+
+        Ok(dumped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::services::click_dump_service::{ClickDumpServiceTrait, PARQUET_CONTENT_TYPE};
+    use chrono::{Days, NaiveDate, Utc};
+    use error_stack::Report;
+    use mockall::predicate::{always, eq, function};
+    use std::sync::Arc;
+    use url_shortener_database::models::dump_models::ClickDump;
+    use url_shortener_database::repositories::click_dump_repository::MockClickDumpRepositoryTrait;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+    use url_shortener_infrastructure::s3::error::S3Error;
+    use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
+
+    fn days_ago(days: u64) -> NaiveDate {
+        Utc::now().date_naive() - Days::new(days)
+    }
+
+    #[tokio::test]
+    async fn dump_pending_days_resumes_after_last_dump() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut click_dump_repository = MockClickDumpRepositoryTrait::new();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        click_dump_repository
+            .expect_last_dumped_day()
+            .returning(|| Box::pin(async { Ok(Some(days_ago(2))) }));
+        click_repository.expect_first_clicked_at().never();
+        click_repository
+            .expect_find_all_page()
+            .times(1)
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        let expected_key = format!(
+            "analytics/clicks/dt={}/clicks.parquet",
+            days_ago(1).format("%Y-%m-%d")
+        );
+        s3_client
+            .expect_upload_object()
+            .with(
+                function(|file: &Vec<u8>| file.starts_with(b"PAR1")),
+                eq(expected_key.clone()),
+                eq(PARQUET_CONTENT_TYPE),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        click_dump_repository
+            .expect_record_dump()
+            .with(eq(days_ago(1)), eq(expected_key), eq(0))
+            .times(1)
+            .returning(|day, key, row_count| {
+                let dump = ClickDump {
+                    day,
+                    object_key: key.to_string(),
+                    row_count,
+                    dumped_at: Utc::now(),
+                };
+                Box::pin(async move { Ok(dump) })
+            });
+        let click_dump_service = super::ClickDumpService::new(
+            Arc::new(click_repository),
+            Arc::new(click_dump_repository),
+            Arc::new(s3_client),
+        );
+
+        // Act
+        let result = click_dump_service.dump_pending_days().await;
+
+        // Assert
+        assert_eq!(result, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn dump_pending_days_on_upload_failure_does_not_record_dump() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut click_dump_repository = MockClickDumpRepositoryTrait::new();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        click_dump_repository
+            .expect_last_dumped_day()
+            .returning(|| Box::pin(async { Ok(None) }));
+        click_repository
+            .expect_first_clicked_at()
+            .returning(|| Box::pin(async { Ok(Some(Utc::now() - chrono::Duration::days(3))) }));
+        click_repository
+            .expect_find_all_page()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        s3_client
+            .expect_upload_object()
+            .with(always(), always(), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(S3Error {})) }));
+        click_dump_repository.expect_record_dump().never();
+        let click_dump_service = super::ClickDumpService::new(
+            Arc::new(click_repository),
+            Arc::new(click_dump_repository),
+            Arc::new(s3_client),
+        );
+
+        // Act
+        let result = click_dump_service.dump_pending_days().await;
+
+        // Assert
+        assert_eq!(result, Err(ApiError::InternalServerError));
+    }
+}
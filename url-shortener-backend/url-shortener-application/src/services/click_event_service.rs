@@ -0,0 +1,214 @@
+use crate::models::click_event_models::ClickEventModel;
+use crate::models::errors::ApiError;
+use crate::queues::click_event_hub::ClickEventHubTrait;
+use crate::services::validation::{ensure_admin, ensure_url_exists};
+use async_trait::async_trait;
+use coi::Inject;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use log::warn;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+
+#[async_trait]
+pub trait ClickEventServiceTrait: Inject {
+    /// Streams live clicks for one link, or for every link when `short_url` is `None`. Every
+    /// link's traffic is only streamed to an admin.
+    async fn subscribe(
+        &self,
+        token: Option<&str>,
+        short_url: Option<String>,
+        include_bots: bool,
+    ) -> Result<BoxStream<'static, ClickEventModel>, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickEventServiceTrait with ClickEventService::new(click_event_hub, url_repository))]
+struct ClickEventService {
+    #[coi(inject)]
+    click_event_hub: Arc<dyn ClickEventHubTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+}
+
+impl ClickEventService {
+    pub fn new(
+        click_event_hub: Arc<dyn ClickEventHubTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+    ) -> Self {
+        Self {
+            click_event_hub,
+            url_repository,
+        }
+    }
+}
+
+#[async_trait]
+impl ClickEventServiceTrait for ClickEventService {
+    async fn subscribe(
+        &self,
+        token: Option<&str>,
+        short_url: Option<String>,
+        include_bots: bool,
+    ) -> Result<BoxStream<'static, ClickEventModel>, ApiError> {
+        match short_url.as_deref() {
+            Some(short_url) => ensure_url_exists(self.url_repository.as_ref(), short_url).await?,
+            None => ensure_admin(token)?,
+        }
+
+        let receiver = self.click_event_hub.subscribe();
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    // a slow viewer misses some clicks instead of holding everyone back
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Click event viewer lagged, skipped {} events", skipped)
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |event| {
+            let matches = short_url.as_ref().is_none_or(|code| *code == event.code)
+                && (include_bots || !event.is_bot);
+            async move { matches }
+        });
+
+        Ok(events.boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::click_event_models::ClickEventModel;
+    use crate::models::errors::ApiError;
+    use crate::models::stats_models::ReferrerCategory;
+    use crate::queues::click_event_hub::MockClickEventHubTrait;
+    use crate::services::click_event_service::ClickEventServiceTrait;
+    use chrono::Utc;
+    use futures::StreamExt;
+    use std::env;
+    use std::sync::Arc;
+    use tokio::sync::broadcast::channel;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+
+    fn test_event(code: &str, is_bot: bool) -> ClickEventModel {
+        ClickEventModel {
+            code: code.to_string(),
+            clicked_at: Utc::now(),
+            referrer_domain: None,
+            referrer_category: ReferrerCategory::Direct,
+            browser: "Chrome".to_string(),
+            os: "Linux".to_string(),
+            device: "Desktop".to_string(),
+            country: None,
+            source: "link".to_string(),
+            is_bot,
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_filters_other_links_and_bots() {
+        // Arrange
+        let (sender, receiver) = channel(10);
+        let mut click_event_hub = MockClickEventHubTrait::new();
+        click_event_hub
+            .expect_subscribe()
+            .times(1)
+            .return_once(move || receiver);
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository.expect_find().returning(|_| {
+            Box::pin(async {
+                Ok(Some(Url {
+                    id: TEST_SHORT_URL.to_string(),
+                    url: TEST_VALID_URL.to_string(),
+                    track_conversions: false,
+                    campaign: None,
+                }))
+            })
+        });
+        let click_event_service =
+            super::ClickEventService::new(Arc::new(click_event_hub), Arc::new(url_repository));
+
+        // Act
+        let mut events = click_event_service
+            .subscribe(None, Some(TEST_SHORT_URL.to_string()), false)
+            .await
+            .unwrap();
+        sender.send(test_event("other", false)).unwrap();
+        sender.send(test_event(TEST_SHORT_URL, true)).unwrap();
+        sender.send(test_event(TEST_SHORT_URL, false)).unwrap();
+        drop(sender);
+
+        // Assert
+        let event = events.next().await.unwrap();
+        assert_eq!(event.code, TEST_SHORT_URL);
+        assert!(!event.is_bot);
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn subscribe_unknown_link_returns_not_found() {
+        // Arrange
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let click_event_service = super::ClickEventService::new(
+            Arc::new(MockClickEventHubTrait::new()),
+            Arc::new(url_repository),
+        );
+
+        // Act
+        let result = click_event_service
+            .subscribe(None, Some(TEST_SHORT_URL.to_string()), false)
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap(),
+            ApiError::NotFound("The url with this format was not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn subscribe_to_every_link_requires_the_admin_token() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", "admin-secret");
+        let (_sender, receiver) = channel(10);
+        let mut click_event_hub = MockClickEventHubTrait::new();
+        click_event_hub
+            .expect_subscribe()
+            .times(1)
+            .return_once(move || receiver);
+        let click_event_service = super::ClickEventService::new(
+            Arc::new(click_event_hub),
+            Arc::new(MockUrlRepositoryTrait::new()),
+        );
+
+        // Act
+        let missing = click_event_service.subscribe(None, None, false).await;
+        let wrong = click_event_service
+            .subscribe(Some("wrong"), None, false)
+            .await;
+        let admin = click_event_service
+            .subscribe(Some("admin-secret"), None, false)
+            .await;
+
+        // Assert
+        for result in [missing, wrong] {
+            assert_eq!(
+                result.err().unwrap(),
+                ApiError::Unauthorized("Invalid admin token")
+            );
+        }
+        assert!(admin.is_ok());
+    }
+}
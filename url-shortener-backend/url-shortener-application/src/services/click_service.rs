@@ -0,0 +1,592 @@
+use crate::analytics::bots::{
+    burst_key, classify_bot, BURST_THRESHOLD, BURST_WINDOW_SECONDS, REASON_BURST,
+};
+use crate::analytics::click_events::{click_event, CLICK_EVENTS_CHANNEL};
+use crate::analytics::counters::{
+    daily_counter_key, daily_counter_member, unflushed_counter_key, PENDING_COUNTERS_KEY,
+};
+use crate::analytics::leaderboard::leaderboard_buckets;
+use crate::analytics::privacy::{anonymize_ip, new_period_salt, salt_period};
+use crate::analytics::visitors::{
+    normalize_ip, unique_visitors_key, visitor_fingerprint, UNIQUE_VISITORS_TTL_SECONDS,
+};
+use crate::models::click_models::ClickRequest;
+use crate::queues::click_queue::ClickQueueTrait;
+use chrono::{DateTime, Utc};
+use coi::Inject;
+use log::warn;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use url_shortener_database::models::click_models::Click;
+use url_shortener_infrastructure::geoip::geoip_reader::{GeoIpReaderTrait, GeoLocation};
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+pub trait ClickServiceTrait: Inject {
+    /// Builds the click on the caller's side and counts, publishes and queues it in the
+    /// background, the handle finishes once that is done.
+    fn record_click(
+        &self,
+        short_url: &str,
+        destination: &str,
+        click_request: ClickRequest,
+    ) -> JoinHandle<()>;
+}
+
+/// The visitor behind a click that may be tracked, the address only lives until the click is
+/// anonymized in the background.
+struct Visitor {
+    ip: String,
+}
+
+#[derive(Clone, Inject)]
+#[coi(provides pub dyn ClickServiceTrait with ClickService::new(click_queue, redis_client_wrapper, geo_ip_reader))]
+struct ClickService {
+    #[coi(inject)]
+    click_queue: Arc<dyn ClickQueueTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    #[coi(inject)]
+    geo_ip_reader: Arc<dyn GeoIpReaderTrait>,
+}
+
+impl ClickService {
+    pub fn new(
+        click_queue: Arc<dyn ClickQueueTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+        geo_ip_reader: Arc<dyn GeoIpReaderTrait>,
+    ) -> Self {
+        Self {
+            click_queue,
+            redis_client_wrapper,
+            geo_ip_reader,
+        }
+    }
+
+    fn locate(&self, ip: Option<&str>) -> GeoLocation {
+        ip.and_then(|ip| normalize_ip(ip).parse::<IpAddr>().ok())
+            .and_then(|ip| self.geo_ip_reader.lookup(ip))
+            .unwrap_or_default()
+    }
+
+    async fn period_salt(&self, at: DateTime<Utc>) -> Option<String> {
+        let (key, ttl_seconds) = salt_period(at);
+        // the first instance to need a salt for the period picks it, every other one reads it
+        match self
+            .redis_client_wrapper
+            .get_or_set_with_expiry(&key, &new_period_salt(), ttl_seconds)
+            .await
+        {
+            Ok(salt) => Some(salt),
+            Err(e) => {
+                warn!("Failed to get ip hash salt: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn is_burst(&self, fingerprint: &str, timestamp: i64) -> bool {
+        match self
+            .redis_client_wrapper
+            .increment_with_expiry(&burst_key(fingerprint, timestamp), BURST_WINDOW_SECONDS)
+            .await
+        {
+            Ok(count) => count > BURST_THRESHOLD,
+            Err(e) => {
+                warn!("Failed to check click burst: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn count_click(&self, click: &Click, fingerprint: Option<String>) {
+        let day = click.clicked_at.date_naive();
+        if let Some(fingerprint) = fingerprint {
+            let key = unique_visitors_key(&click.url_id, day);
+            if let Err(e) = self
+                .redis_client_wrapper
+                .add_to_hyperloglog(&key, &fingerprint, UNIQUE_VISITORS_TTL_SECONDS)
+                .await
+            {
+                warn!("Failed to count unique visitor: {}", e);
+            }
+        }
+
+        let counter_keys = vec![
+            daily_counter_key(&click.url_id, day),
+            unflushed_counter_key(&click.url_id),
+        ];
+        if let Err(e) = self
+            .redis_client_wrapper
+            .increment_counters(
+                counter_keys,
+                PENDING_COUNTERS_KEY,
+                &daily_counter_member(&click.url_id, day),
+            )
+            .await
+        {
+            warn!("Failed to increment click counters: {}", e);
+        }
+
+        if let Err(e) = self
+            .redis_client_wrapper
+            .increment_sorted_sets(leaderboard_buckets(click.clicked_at), &click.url_id)
+            .await
+        {
+            warn!("Failed to increment top links: {}", e);
+        }
+    }
+
+    async fn publish_click(&self, click: &Click) {
+        let message = match serde_json::to_string(&click_event(click)) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to serialize click event: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self
+            .redis_client_wrapper
+            .publish(CLICK_EVENTS_CHANNEL, &message)
+            .await
+        {
+            warn!("Failed to publish click event: {}", e);
+        }
+    }
+}
+
+impl ClickService {
+    fn build_click(
+        &self,
+        short_url: &str,
+        destination: &str,
+        click_request: ClickRequest,
+    ) -> (Click, Option<Visitor>) {
+        let clicked_at = Utc::now();
+        let location = self.locate(click_request.ip.as_deref());
+        // visitors asking not to be tracked are still counted, but nothing ties the click to them
+        let visitor = match (click_request.do_not_track, click_request.ip) {
+            (false, Some(ip)) => Some(Visitor { ip }),
+            _ => None,
+        };
+
+        let bot_reason = classify_bot(
+            click_request.user_agent.as_deref(),
+            click_request.is_head_request,
+            click_request.accept_language.as_deref(),
+        );
+
+        let click = Click {
+            url_id: short_url.to_string(),
+            clicked_at,
+            referrer: click_request.referrer,
+            user_agent: click_request.user_agent,
+            ip_hash: None,
+            destination: destination.to_string(),
+            is_bot: bot_reason.is_some(),
+            bot_reason: bot_reason.map(str::to_string),
+            country: location.country,
+            region: location.region,
+            city: location.city,
+            source: click_request.source.as_str().to_string(),
+            click_id: click_request.click_id,
+        };
+
+        (click, visitor)
+    }
+
+    async fn track_click(&self, mut click: Click, visitor: Option<Visitor>) {
+        // the raw address is only used here, before it is anonymized
+        let mut fingerprint = None;
+        if let Some(visitor) = visitor {
+            let period_salt = self.period_salt(click.clicked_at).await;
+            click.ip_hash = anonymize_ip(&visitor.ip, period_salt.as_deref());
+            // without the salt of the period the visitor cannot be told apart safely, so the
+            // click is neither counted as unique nor checked for bursts
+            fingerprint = period_salt.map(|period_salt| {
+                visitor_fingerprint(&visitor.ip, click.user_agent.as_deref(), &period_salt)
+            });
+        }
+
+        // bursts are told apart by the full address and user agent, the anonymized hash would
+        // lump a whole truncated network together; the key only lives for its window
+        if !click.is_bot {
+            if let Some(fingerprint) = fingerprint.as_deref() {
+                if self
+                    .is_burst(fingerprint, click.clicked_at.timestamp())
+                    .await
+                {
+                    click.is_bot = true;
+                    click.bot_reason = Some(REASON_BURST.to_string());
+                }
+            }
+        }
+
+        // bot clicks are only stored tagged, they never reach the live counters, so lifetime
+        // clicks and unique visitors always exclude them
+        if !click.is_bot {
+            self.count_click(&click, fingerprint).await;
+        }
+
+        self.publish_click(&click).await;
+        self.click_queue.enqueue(click);
+    }
+}
+
+impl ClickServiceTrait for ClickService {
+    fn record_click(
+        &self,
+        short_url: &str,
+        destination: &str,
+        click_request: ClickRequest,
+    ) -> JoinHandle<()> {
+        let (click, visitor) = self.build_click(short_url, destination, click_request);
+        let service = self.clone();
+        tokio::spawn(async move { service.track_click(click, visitor).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::click_models::{ClickRequest, ClickSource};
+    use crate::queues::click_queue::MockClickQueueTrait;
+    use crate::services::click_service::ClickServiceTrait;
+    use error_stack::Report;
+    use mockall::predicate::{always, eq, function};
+    use std::env;
+    use std::net::IpAddr;
+    use std::sync::{Arc, Mutex};
+    use url_shortener_database::models::click_models::Click;
+    use url_shortener_infrastructure::geoip::geoip_reader::{GeoLocation, MockGeoIpReaderTrait};
+    use url_shortener_infrastructure::redis::error::CacheError;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_HASH_KEY: &str = "hash-key";
+
+    fn expect_period_salt(redis_client: &mut MockRedisClientWrapperTrait) {
+        env::set_var("IP_HASH_SALT", TEST_HASH_KEY);
+        redis_client
+            .expect_get_or_set_with_expiry()
+            .with(
+                function(|key: &str| key.starts_with("ip_hash_salt:")),
+                always(),
+                function(|ttl_seconds: &i64| *ttl_seconds > 0),
+            )
+            .returning(|_, _, _| Box::pin(async { Ok("period-salt".to_string()) }));
+    }
+
+    #[tokio::test]
+    async fn record_click_enqueues_hashed_ip() {
+        // Arrange
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        expect_period_salt(&mut redis_client);
+        redis_client
+            .expect_publish()
+            .with(
+                eq("click_events"),
+                function(|message: &str| {
+                    message.contains("\"code\":\"1234556\"") && !message.contains("127.0.0.1")
+                }),
+            )
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        click_queue
+            .expect_enqueue()
+            .with(function(|click: &Click| {
+                click.url_id == TEST_SHORT_URL
+                    && click.destination == TEST_VALID_URL
+                    && click
+                        .ip_hash
+                        .as_deref()
+                        .is_some_and(|h| h.len() == 64 && !h.contains("127.0.0.1"))
+                    && !click.is_bot
+                    && click.country.as_deref() == Some("RO")
+                    && click.city.as_deref() == Some("Bucharest")
+                    && click.source == "qr"
+            }))
+            .times(1)
+            .return_const(());
+        redis_client
+            .expect_add_to_hyperloglog()
+            .with(
+                function(|key: &str| key.starts_with("unique_visitors:1234556:")),
+                always(),
+                always(),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(CacheError {})) }));
+        redis_client
+            .expect_increment_counters()
+            .with(
+                function(|keys: &Vec<String>| {
+                    keys.len() == 2
+                        && keys[0].starts_with("click_count:1234556:")
+                        && keys[1] == "click_count_unflushed:1234556"
+                }),
+                eq("click_counts:pending"),
+                always(),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_sorted_sets()
+            .with(
+                function(|keys: &Vec<(String, i64)>| keys.len() == 3),
+                eq(TEST_SHORT_URL),
+            )
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_with_expiry()
+            .returning(|_, _| Box::pin(async { Ok(1) }));
+        let location = GeoLocation {
+            country: Some("RO".to_string()),
+            region: Some("B".to_string()),
+            city: Some("Bucharest".to_string()),
+        };
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader(Some(location))),
+        );
+        let request = human_request();
+
+        // Act
+        click_service
+            .record_click(TEST_SHORT_URL, TEST_VALID_URL, request)
+            .await
+            .unwrap();
+    }
+
+    fn geo_ip_reader(location: Option<GeoLocation>) -> MockGeoIpReaderTrait {
+        let mut geo_ip_reader = MockGeoIpReaderTrait::new();
+        geo_ip_reader
+            .expect_lookup()
+            .with(eq("127.0.0.1".parse::<IpAddr>().unwrap()))
+            .returning(move |_| location.clone());
+        geo_ip_reader
+    }
+
+    fn human_request() -> ClickRequest {
+        ClickRequest {
+            referrer: None,
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ip: Some("127.0.0.1:5555".to_string()),
+            accept_language: Some("en-US".to_string()),
+            is_head_request: false,
+            do_not_track: false,
+            source: ClickSource::Qr,
+            click_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn record_click_without_ip_skips_unique_visitors() {
+        // Arrange
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_publish()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_counters()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_sorted_sets()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        click_queue
+            .expect_enqueue()
+            .with(function(|click: &Click| click.ip_hash.is_none()))
+            .times(1)
+            .return_const(());
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader(None)),
+        );
+        let request = ClickRequest {
+            ip: None,
+            ..human_request()
+        };
+
+        // Act
+        click_service
+            .record_click(TEST_SHORT_URL, TEST_VALID_URL, request)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn record_click_with_do_not_track_skips_visitor_tracking() {
+        // Arrange
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_publish()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client.expect_add_to_hyperloglog().never();
+        redis_client.expect_increment_with_expiry().never();
+        redis_client
+            .expect_increment_counters()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_sorted_sets()
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        click_queue
+            .expect_enqueue()
+            .with(function(|click: &Click| {
+                click.ip_hash.is_none() && !click.is_bot
+            }))
+            .times(1)
+            .return_const(());
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader(None)),
+        );
+        let request = ClickRequest {
+            do_not_track: true,
+            ..human_request()
+        };
+
+        // Act
+        click_service
+            .record_click(TEST_SHORT_URL, TEST_VALID_URL, request)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn record_click_from_unfurler_is_tagged_and_not_counted() {
+        // Arrange
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        expect_period_salt(&mut redis_client);
+        redis_client
+            .expect_publish()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client.expect_add_to_hyperloglog().never();
+        redis_client.expect_increment_counters().never();
+        redis_client.expect_increment_sorted_sets().never();
+        click_queue
+            .expect_enqueue()
+            .with(function(|click: &Click| {
+                click.is_bot && click.bot_reason.as_deref() == Some("user_agent")
+            }))
+            .times(1)
+            .return_const(());
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader(None)),
+        );
+        let request = ClickRequest {
+            user_agent: Some("Slackbot-LinkExpanding 1.0".to_string()),
+            ..human_request()
+        };
+
+        // Act
+        click_service
+            .record_click(TEST_SHORT_URL, TEST_VALID_URL, request)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn record_click_over_burst_threshold_is_tagged() {
+        // Arrange
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        expect_period_salt(&mut redis_client);
+        redis_client
+            .expect_publish()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_with_expiry()
+            .with(
+                function(|key: &str| key.starts_with("click_burst:")),
+                eq(10),
+            )
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(21) }));
+        redis_client.expect_increment_counters().never();
+        redis_client.expect_increment_sorted_sets().never();
+        click_queue
+            .expect_enqueue()
+            .with(function(|click: &Click| {
+                click.is_bot && click.bot_reason.as_deref() == Some("burst")
+            }))
+            .times(1)
+            .return_const(());
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader(None)),
+        );
+
+        // Act
+        click_service
+            .record_click(TEST_SHORT_URL, TEST_VALID_URL, human_request())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn record_click_from_neighbouring_addresses_counts_separate_bursts() {
+        // Arrange
+        let burst_keys = Arc::new(Mutex::new(Vec::new()));
+        let mut click_queue = MockClickQueueTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        let mut geo_ip_reader = MockGeoIpReaderTrait::new();
+        expect_period_salt(&mut redis_client);
+        let recorded = burst_keys.clone();
+        redis_client
+            .expect_increment_with_expiry()
+            .times(2)
+            .returning(move |key, _| {
+                recorded.lock().unwrap().push(key.to_string());
+                Box::pin(async { Ok(1) })
+            });
+        redis_client
+            .expect_add_to_hyperloglog()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_counters()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_increment_sorted_sets()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        redis_client
+            .expect_publish()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        geo_ip_reader.expect_lookup().returning(|_| None);
+        click_queue.expect_enqueue().times(2).return_const(());
+        let click_service = super::ClickService::new(
+            Arc::new(click_queue),
+            Arc::new(redis_client),
+            Arc::new(geo_ip_reader),
+        );
+
+        // Act
+        for ip in ["203.0.113.7", "203.0.113.8"] {
+            let request = ClickRequest {
+                ip: Some(ip.to_string()),
+                ..human_request()
+            };
+            click_service
+                .record_click(TEST_SHORT_URL, TEST_VALID_URL, request)
+                .await
+                .unwrap();
+        }
+
+        // Assert
+        let burst_keys = burst_keys.lock().unwrap();
+        assert!(burst_keys.iter().all(|key| key.starts_with("click_burst:")));
+        assert_ne!(burst_keys[0], burst_keys[1]);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::analytics::conversions::{click_url_key, postback_token_matches};
+use crate::models::conversion_models::ConversionRequest;
+use crate::models::errors::ApiError;
+use async_trait::async_trait;
+use coi::Inject;
+use log::{error, info, warn};
+use std::env;
+use std::sync::Arc;
+use url_shortener_database::models::conversion_models::Conversion;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+use url_shortener_database::repositories::conversion_repository::ConversionRepositoryTrait;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+const MAX_CLICK_ID_LENGTH: usize = 64;
+const MAX_EVENT_LENGTH: usize = 64;
+
+#[async_trait]
+pub trait ConversionServiceTrait: Inject {
+    async fn record_conversion(
+        &self,
+        token: Option<&str>,
+        conversion_request: ConversionRequest,
+    ) -> Result<(), ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ConversionServiceTrait with ConversionService::new(click_repository, conversion_repository, redis_client_wrapper))]
+struct ConversionService {
+    #[coi(inject)]
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+    #[coi(inject)]
+    conversion_repository: Arc<dyn ConversionRepositoryTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl ConversionService {
+    pub fn new(
+        click_repository: Arc<dyn ClickRepositoryTrait>,
+        conversion_repository: Arc<dyn ConversionRepositoryTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            click_repository,
+            conversion_repository,
+            redis_client_wrapper,
+        }
+    }
+
+    /// The link the click belongs to. The mapping written at the redirect covers clicks that are
+    /// not stored yet, the stored clicks cover mappings that already expired.
+    async fn find_url_id(&self, click_id: &str) -> Result<Option<String>, ApiError> {
+        match self
+            .redis_client_wrapper
+            .get_cache(&click_url_key(click_id))
+            .await
+        {
+            Ok(url_id) => return Ok(Some(url_id)),
+            Err(e) => warn!("Failed to fetch click id mapping: {}", e),
+        }
+
+        self.click_repository
+            .find_url_id_by_click_id(click_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to find click: {:?}", e);
+                ApiError::InternalServerError
+            })
+    }
+
+    /// Postbacks come from our own backend, without a configured token they are refused outright.
+    fn authorize(token: Option<&str>) -> Result<(), ApiError> {
+        let Ok(expected) = env::var("CONVERSION_POSTBACK_TOKEN") else {
+            warn!("Conversion postback refused, CONVERSION_POSTBACK_TOKEN is not set");
+            return Err(ApiError::Unauthorized("Invalid postback token"));
+        };
+
+        match token {
+            Some(token) if !expected.is_empty() && postback_token_matches(&expected, token) => {
+                Ok(())
+            }
+            _ => {
+                warn!("Conversion postback with an invalid token");
+                Err(ApiError::Unauthorized("Invalid postback token"))
+            }
+        }
+    }
+
+    fn validate(conversion_request: &ConversionRequest) -> Result<(), ApiError> {
+        if conversion_request.click_id.is_empty()
+            || conversion_request.click_id.len() > MAX_CLICK_ID_LENGTH
+        {
+            return Err(ApiError::BadRequest("Invalid click id"));
+        }
+        if conversion_request.event.trim().is_empty()
+            || conversion_request.event.len() > MAX_EVENT_LENGTH
+        {
+            return Err(ApiError::BadRequest(
+                "The event name must have between 1 and 64 characters",
+            ));
+        }
+        if conversion_request
+            .value
+            .is_some_and(|value| !value.is_finite())
+        {
+            return Err(ApiError::BadRequest("Invalid conversion value"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ConversionServiceTrait for ConversionService {
+    async fn record_conversion(
+        &self,
+        token: Option<&str>,
+        conversion_request: ConversionRequest,
+    ) -> Result<(), ApiError> {
+        Self::authorize(token)?;
+        Self::validate(&conversion_request)?;
+
+        let url_id = self
+            .find_url_id(&conversion_request.click_id)
+            .await?
+            .ok_or_else(|| {
+                warn!("Click not found: {:?}", conversion_request.click_id);
+                ApiError::NotFound("The click with this id was not found")
+            })?;
+
+        let created = self
+            .conversion_repository
+            .create(Conversion {
+                click_id: conversion_request.click_id,
+                url_id,
+                event: conversion_request.event.trim().to_string(),
+                value: conversion_request.value,
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to create conversion: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        if !created {
+            info!("Conversion already recorded, ignoring the repeated postback");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::conversion_models::ConversionRequest;
+    use crate::models::errors::ApiError;
+    use crate::services::conversion_service::ConversionServiceTrait;
+    use error_stack::Report;
+    use mockall::predicate::{eq, function};
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::conversion_models::Conversion;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+    use url_shortener_database::repositories::conversion_repository::MockConversionRepositoryTrait;
+    use url_shortener_infrastructure::redis::error::CacheError;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_CLICK_ID: &str = "abcdefghijklmnopqrstuvwx";
+    const TEST_TOKEN: &str = "postback-token";
+
+    /// The redirect's mapping expired, the click is looked up among the stored ones.
+    fn redis_client() -> MockRedisClientWrapperTrait {
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_get_cache()
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError)) }));
+        redis_client
+    }
+
+    fn conversion_request() -> ConversionRequest {
+        ConversionRequest {
+            click_id: TEST_CLICK_ID.to_string(),
+            event: " signup ".to_string(),
+            value: Some(9.99),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_conversion_stores_conversion_for_click() {
+        // Arrange
+        env::set_var("CONVERSION_POSTBACK_TOKEN", TEST_TOKEN);
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut conversion_repository = MockConversionRepositoryTrait::new();
+        click_repository
+            .expect_find_url_id_by_click_id()
+            .with(eq(TEST_CLICK_ID))
+            .returning(|_| Box::pin(async { Ok(Some(TEST_SHORT_URL.to_string())) }));
+        conversion_repository
+            .expect_create()
+            .with(function(|conversion: &Conversion| {
+                conversion.url_id == TEST_SHORT_URL && conversion.event == "signup"
+            }))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(true) }));
+        let conversion_service = super::ConversionService::new(
+            Arc::new(click_repository),
+            Arc::new(conversion_repository),
+            Arc::new(redis_client()),
+        );
+
+        // Act
+        let result = conversion_service
+            .record_conversion(Some(TEST_TOKEN), conversion_request())
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn record_conversion_before_the_click_is_flushed_uses_the_redirect_mapping() {
+        // Arrange
+        env::set_var("CONVERSION_POSTBACK_TOKEN", TEST_TOKEN);
+        let mut click_repository = MockClickRepositoryTrait::new();
+        // the click is still in the queue, it is not stored yet
+        click_repository.expect_find_url_id_by_click_id().never();
+        let mut conversion_repository = MockConversionRepositoryTrait::new();
+        conversion_repository
+            .expect_create()
+            .with(function(|conversion: &Conversion| {
+                conversion.url_id == TEST_SHORT_URL && conversion.click_id == TEST_CLICK_ID
+            }))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(true) }));
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_get_cache()
+            .with(eq("click_url:abcdefghijklmnopqrstuvwx"))
+            .returning(|_| Box::pin(async { Ok(TEST_SHORT_URL.to_string()) }));
+        let conversion_service = super::ConversionService::new(
+            Arc::new(click_repository),
+            Arc::new(conversion_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = conversion_service
+            .record_conversion(Some(TEST_TOKEN), conversion_request())
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn record_conversion_invalid_token_returns_unauthorized() {
+        // Arrange
+        env::set_var("CONVERSION_POSTBACK_TOKEN", TEST_TOKEN);
+        let mut click_repository = MockClickRepositoryTrait::new();
+        click_repository.expect_find_url_id_by_click_id().never();
+        let conversion_service = super::ConversionService::new(
+            Arc::new(click_repository),
+            Arc::new(MockConversionRepositoryTrait::new()),
+            Arc::new(redis_client()),
+        );
+
+        // Act
+        let result = conversion_service
+            .record_conversion(Some("guess"), conversion_request())
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::Unauthorized("Invalid postback token")
+        );
+    }
+
+    #[tokio::test]
+    async fn record_conversion_unknown_click_returns_not_found() {
+        // Arrange
+        env::set_var("CONVERSION_POSTBACK_TOKEN", TEST_TOKEN);
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut conversion_repository = MockConversionRepositoryTrait::new();
+        click_repository
+            .expect_find_url_id_by_click_id()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        conversion_repository.expect_create().never();
+        let conversion_service = super::ConversionService::new(
+            Arc::new(click_repository),
+            Arc::new(conversion_repository),
+            Arc::new(redis_client()),
+        );
+
+        // Act
+        let result = conversion_service
+            .record_conversion(Some(TEST_TOKEN), conversion_request())
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The click with this id was not found")
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use crate::analytics::export::{click_export, export_header, export_row};
+use crate::analytics::privacy::ip_export_mode;
+use crate::models::errors::ApiError;
+use crate::models::export_models::ExportQuery;
+use crate::services::validation::ensure_url_exists;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use coi::Inject;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use log::{error, warn};
+use std::sync::Arc;
+use url_shortener_database::models::click_models::ClickSelection;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const EXPORT_PAGE_SIZE: i64 = 5_000;
+
+#[async_trait]
+pub trait ExportServiceTrait: Inject {
+    /// Streams the clicks of a link as chunks of CSV or JSON Lines, one page of rows per chunk.
+    async fn export_clicks(
+        &self,
+        short_url: &str,
+        export_query: ExportQuery,
+    ) -> Result<BoxStream<'static, Result<String, ApiError>>, ApiError>;
+    /// The same export for every link in the campaign, each row names the link it belongs to.
+    async fn export_campaign_clicks(
+        &self,
+        campaign: &str,
+        export_query: ExportQuery,
+    ) -> Result<BoxStream<'static, Result<String, ApiError>>, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ExportServiceTrait with ExportService::new(click_repository, url_repository))]
+struct ExportService {
+    #[coi(inject)]
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+}
+
+impl ExportService {
+    pub fn new(
+        click_repository: Arc<dyn ClickRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+    ) -> Self {
+        Self {
+            click_repository,
+            url_repository,
+        }
+    }
+}
+
+impl ExportService {
+    fn export(
+        &self,
+        selection: ClickSelection,
+        export_query: ExportQuery,
+    ) -> Result<BoxStream<'static, Result<String, ApiError>>, ApiError> {
+        let to = export_query.to.unwrap_or_else(Utc::now);
+        let from = export_query
+            .from
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_RANGE_DAYS));
+        let format = export_query.format.unwrap_or_default();
+        let include_bots = export_query.include_bots.unwrap_or_default();
+        let ip_export_mode = ip_export_mode();
+
+        if from >= to {
+            warn!("Invalid export range: {} - {}", from, to);
+            return Err(ApiError::BadRequest(
+                "The start of the range must be before its end",
+            ));
+        }
+
+        let header = export_header(format, ip_export_mode);
+        let click_repository = self.click_repository.clone();
+
+        // pages are read by id so each query is short and nothing but the current page is held in memory
+        let pages = stream::unfold(Some(0), move |after_id| {
+            let click_repository = click_repository.clone();
+            let selection = selection.clone();
+            async move {
+                let after_id = after_id?;
+                let clicks = match click_repository
+                    .find_page(
+                        &selection,
+                        from,
+                        to,
+                        include_bots,
+                        after_id,
+                        EXPORT_PAGE_SIZE,
+                    )
+                    .await
+                {
+                    Ok(clicks) => clicks,
+                    Err(e) => {
+                        error!("Failed to export clicks: {:?}", e);
+                        return Some((Err(ApiError::InternalServerError), None));
+                    }
+                };
+                if clicks.is_empty() {
+                    return None;
+                }
+
+                let next = if clicks.len() < EXPORT_PAGE_SIZE as usize {
+                    None
+                } else {
+                    clicks.last().map(|click| click.id)
+                };
+                let chunk = clicks
+                    .into_iter()
+                    .map(|click| {
+                        export_row(format, ip_export_mode, &click_export(click, ip_export_mode))
+                    })
+                    .collect::<String>();
+
+                Some((Ok(chunk), next))
+            }
+        });
+
+        Ok(stream::iter((!header.is_empty()).then_some(Ok(header)))
+            .chain(pages)
+            .boxed())
+    }
+}
+
+#[async_trait]
+impl ExportServiceTrait for ExportService {
+    async fn export_clicks(
+        &self,
+        short_url: &str,
+        export_query: ExportQuery,
+    ) -> Result<BoxStream<'static, Result<String, ApiError>>, ApiError> {
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        self.export(ClickSelection::Link(short_url.to_string()), export_query)
+    }
+
+    async fn export_campaign_clicks(
+        &self,
+        campaign: &str,
+        export_query: ExportQuery,
+    ) -> Result<BoxStream<'static, Result<String, ApiError>>, ApiError> {
+        let exists = self
+            .url_repository
+            .campaign_exists(campaign)
+            .await
+            .map_err(|e| {
+                error!("Failed to find campaign: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        if !exists {
+            warn!("Campaign not found: {}", campaign);
+            return Err(ApiError::NotFound("The campaign was not found"));
+        }
+
+        self.export(ClickSelection::Campaign(campaign.to_string()), export_query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::export_models::{ExportFormat, ExportQuery};
+    use crate::services::export_service::{ExportServiceTrait, EXPORT_PAGE_SIZE};
+    use chrono::{Duration, Utc};
+    use error_stack::Report;
+    use futures::StreamExt;
+    use mockall::predicate::{always, eq};
+    use std::sync::Arc;
+    use url_shortener_database::models::click_models::{ClickRecord, ClickSelection};
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_CAMPAIGN: &str = "spring-sale";
+
+    fn setup_mocks() -> (MockClickRepositoryTrait, MockUrlRepositoryTrait) {
+        let click_repository = MockClickRepositoryTrait::new();
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository.expect_find().returning(|_| {
+            Box::pin(async {
+                Ok(Some(Url {
+                    id: TEST_SHORT_URL.to_string(),
+                    url: TEST_VALID_URL.to_string(),
+                    track_conversions: false,
+                    campaign: None,
+                }))
+            })
+        });
+        (click_repository, url_repository)
+    }
+
+    fn test_clicks(first_id: i64, count: i64) -> Vec<ClickRecord> {
+        (first_id..first_id + count)
+            .map(|id| ClickRecord {
+                id,
+                url_id: TEST_SHORT_URL.to_string(),
+                clicked_at: Utc::now(),
+                referrer: None,
+                user_agent: None,
+                ip_hash: Some("abc".to_string()),
+                destination: TEST_VALID_URL.to_string(),
+                is_bot: false,
+                bot_reason: None,
+                country: None,
+                region: None,
+                city: None,
+                source: "link".to_string(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn export_clicks_pages_through_all_clicks() {
+        // Arrange
+        let (mut click_repository, url_repository) = setup_mocks();
+        click_repository
+            .expect_find_page()
+            .with(
+                eq(ClickSelection::Link(TEST_SHORT_URL.to_string())),
+                always(),
+                always(),
+                eq(false),
+                eq(0),
+                eq(EXPORT_PAGE_SIZE),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _, _| Box::pin(async { Ok(test_clicks(1, EXPORT_PAGE_SIZE)) }));
+        click_repository
+            .expect_find_page()
+            .with(
+                eq(ClickSelection::Link(TEST_SHORT_URL.to_string())),
+                always(),
+                always(),
+                eq(false),
+                eq(EXPORT_PAGE_SIZE),
+                eq(EXPORT_PAGE_SIZE),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _, _| {
+                Box::pin(async { Ok(test_clicks(EXPORT_PAGE_SIZE + 1, 2)) })
+            });
+        let export_service =
+            super::ExportService::new(Arc::new(click_repository), Arc::new(url_repository));
+
+        // Act
+        let chunks: Vec<_> = export_service
+            .export_clicks(TEST_SHORT_URL, ExportQuery::default())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        // Assert
+        assert_eq!(chunks.len(), 3);
+        let body: String = chunks.into_iter().map(Result::unwrap).collect();
+        assert!(body.starts_with("code,clicked_at,"));
+        assert_eq!(body.lines().count(), EXPORT_PAGE_SIZE as usize + 3);
+        assert!(!body.contains("abc"));
+    }
+
+    #[tokio::test]
+    async fn export_clicks_on_database_ends_with_error() {
+        // Arrange
+        let (mut click_repository, url_repository) = setup_mocks();
+        click_repository
+            .expect_find_page()
+            .times(1)
+            .returning(|_, _, _, _, _, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let export_service =
+            super::ExportService::new(Arc::new(click_repository), Arc::new(url_repository));
+        let query = ExportQuery {
+            format: Some(ExportFormat::Jsonl),
+            ..Default::default()
+        };
+
+        // Act
+        let chunks: Vec<_> = export_service
+            .export_clicks(TEST_SHORT_URL, query)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        // Assert
+        assert_eq!(chunks, vec![Err(ApiError::InternalServerError)]);
+    }
+
+    #[tokio::test]
+    async fn export_clicks_invalid_range_returns_bad_request() {
+        // Arrange
+        let (click_repository, url_repository) = setup_mocks();
+        let export_service =
+            super::ExportService::new(Arc::new(click_repository), Arc::new(url_repository));
+        let query = ExportQuery {
+            from: Some(Utc::now()),
+            to: Some(Utc::now() - Duration::days(1)),
+            ..Default::default()
+        };
+
+        // Act
+        let result = export_service.export_clicks(TEST_SHORT_URL, query).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap(),
+            ApiError::BadRequest("The start of the range must be before its end")
+        );
+    }
+
+    #[tokio::test]
+    async fn export_campaign_clicks_reads_every_link_in_the_campaign() {
+        // Arrange
+        let (mut click_repository, mut url_repository) = setup_mocks();
+        url_repository
+            .expect_campaign_exists()
+            .with(eq(TEST_CAMPAIGN))
+            .returning(|_| Box::pin(async { Ok(true) }));
+        click_repository
+            .expect_find_page()
+            .with(
+                eq(ClickSelection::Campaign(TEST_CAMPAIGN.to_string())),
+                always(),
+                always(),
+                eq(false),
+                eq(0),
+                eq(EXPORT_PAGE_SIZE),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _, _| Box::pin(async { Ok(test_clicks(1, 2)) }));
+        let export_service =
+            super::ExportService::new(Arc::new(click_repository), Arc::new(url_repository));
+
+        // Act
+        let chunks: Vec<_> = export_service
+            .export_campaign_clicks(TEST_CAMPAIGN, ExportQuery::default())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        // Assert
+        let body: String = chunks.into_iter().map(Result::unwrap).collect();
+        assert_eq!(body.lines().count(), 3);
+        assert!(body
+            .lines()
+            .skip(1)
+            .all(|line| line.starts_with(TEST_SHORT_URL)));
+    }
+
+    #[tokio::test]
+    async fn export_campaign_clicks_unknown_campaign_returns_not_found() {
+        // Arrange
+        let (click_repository, mut url_repository) = setup_mocks();
+        url_repository
+            .expect_campaign_exists()
+            .with(eq(TEST_CAMPAIGN))
+            .returning(|_| Box::pin(async { Ok(false) }));
+        let export_service =
+            super::ExportService::new(Arc::new(click_repository), Arc::new(url_repository));
+
+        // Act
+        let result = export_service
+            .export_campaign_clicks(TEST_CAMPAIGN, ExportQuery::default())
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap(),
+            ApiError::NotFound("The campaign was not found")
+        );
+    }
+}
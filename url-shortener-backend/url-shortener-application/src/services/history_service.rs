@@ -0,0 +1,271 @@
+use crate::models::errors::ApiError;
+use crate::models::response_model::HistoryResponseModel;
+use crate::qr::publish::prerender_enabled;
+use crate::services::validation::{admin_actor, ensure_url_exists};
+use async_trait::async_trait;
+use coi::Inject;
+use log::{error, warn};
+use std::sync::Arc;
+use url_shortener_database::repositories::history_repository::HistoryRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+#[async_trait]
+pub trait HistoryServiceTrait: Inject {
+    async fn get_history(&self, short_url: &str) -> Result<Vec<HistoryResponseModel>, ApiError>;
+    /// Reverts the link to the entry's value, an admin task recorded as changed by the admin principal.
+    async fn rollback(
+        &self,
+        short_url: &str,
+        id: i64,
+        token: Option<&str>,
+    ) -> Result<HistoryResponseModel, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn HistoryServiceTrait with HistoryService::new(history_repository, url_repository, redis_client_wrapper))]
+struct HistoryService {
+    #[coi(inject)]
+    history_repository: Arc<dyn HistoryRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl HistoryService {
+    pub fn new(
+        history_repository: Arc<dyn HistoryRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            history_repository,
+            url_repository,
+            redis_client_wrapper,
+        }
+    }
+}
+
+#[async_trait]
+impl HistoryServiceTrait for HistoryService {
+    async fn get_history(&self, short_url: &str) -> Result<Vec<HistoryResponseModel>, ApiError> {
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let history = self
+            .history_repository
+            .find_by_url(short_url)
+            .await
+            .map_err(|e| {
+                error!("Failed to get history: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        Ok(history.into_iter().map(Into::into).collect())
+    }
+
+    async fn rollback(
+        &self,
+        short_url: &str,
+        id: i64,
+        token: Option<&str>,
+    ) -> Result<HistoryResponseModel, ApiError> {
+        let changed_by = admin_actor(token)?;
+        let change = self
+            .history_repository
+            .rollback(short_url, id, &changed_by, prerender_enabled())
+            .await
+            .map_err(|e| {
+                error!("Failed to roll back url: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        match change {
+            Some(change) => {
+                if let Err(e) = self.redis_client_wrapper.delete_cache(short_url).await {
+                    warn!("Failed to invalidate url cache: {}", e);
+                }
+                Ok(change.into())
+            }
+            None => {
+                warn!("History entry not found: {} - {}", short_url, id);
+                Err(ApiError::NotFound("The history entry was not found"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::services::history_service::HistoryServiceTrait;
+    use chrono::Utc;
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::models::history_models::{UrlHistory, URL_FIELD};
+    use url_shortener_database::repositories::history_repository::MockHistoryRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_TOKEN: &str = "admin-secret";
+
+    fn setup_mocks() -> (
+        MockHistoryRepositoryTrait,
+        MockUrlRepositoryTrait,
+        MockRedisClientWrapperTrait,
+    ) {
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let history_repository = MockHistoryRepositoryTrait::new();
+        let url_repository = MockUrlRepositoryTrait::new();
+        let redis_client = MockRedisClientWrapperTrait::new();
+        (history_repository, url_repository, redis_client)
+    }
+
+    #[tokio::test]
+    async fn get_history_unknown_url_returns_not_found() {
+        // Arrange
+        let (history_repository, mut url_repository, redis_client) = setup_mocks();
+        url_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let history_service = super::HistoryService::new(
+            Arc::new(history_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = history_service.get_history(TEST_SHORT_URL).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The url with this format was not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_missing_entry_returns_not_found() {
+        // Arrange
+        let (mut history_repository, url_repository, redis_client) = setup_mocks();
+        history_repository
+            .expect_rollback()
+            .with(eq(TEST_SHORT_URL), eq(1), always(), always())
+            .returning(|_, _, _, _| Box::pin(async { Ok(None) }));
+        let history_service = super::HistoryService::new(
+            Arc::new(history_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = history_service
+            .rollback(TEST_SHORT_URL, 1, Some(TEST_TOKEN))
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The history entry was not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_invalidates_cache_returns_ok() {
+        // Arrange
+        let (mut history_repository, url_repository, mut redis_client) = setup_mocks();
+        history_repository
+            .expect_rollback()
+            .with(eq(TEST_SHORT_URL), eq(1), eq("admin"), always())
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(Some(UrlHistory {
+                        id: 2,
+                        url_id: TEST_SHORT_URL.to_string(),
+                        field: URL_FIELD.to_string(),
+                        old_value: Some("https://www.example.com".to_string()),
+                        new_value: TEST_VALID_URL.to_string(),
+                        changed_by: "admin".to_string(),
+                        changed_at: Utc::now(),
+                    }))
+                })
+            });
+        redis_client
+            .expect_delete_cache()
+            .with(eq(TEST_SHORT_URL))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let history_service = super::HistoryService::new(
+            Arc::new(history_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = history_service
+            .rollback(TEST_SHORT_URL, 1, Some(TEST_TOKEN))
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().new_value, TEST_VALID_URL);
+    }
+
+    #[tokio::test]
+    async fn rollback_on_database_returns_internal_server_error() {
+        // Arrange
+        let (mut history_repository, url_repository, redis_client) = setup_mocks();
+        history_repository
+            .expect_rollback()
+            .with(always(), always(), always(), always())
+            .returning(|_, _, _, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let history_service = super::HistoryService::new(
+            Arc::new(history_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = history_service
+            .rollback(TEST_SHORT_URL, 1, Some(TEST_TOKEN))
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+
+    #[tokio::test]
+    async fn rollback_without_admin_token_returns_unauthorized() {
+        // Arrange
+        let (mut history_repository, url_repository, redis_client) = setup_mocks();
+        history_repository.expect_rollback().never();
+        let history_service = super::HistoryService::new(
+            Arc::new(history_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let missing = history_service.rollback(TEST_SHORT_URL, 1, None).await;
+        let wrong = history_service
+            .rollback(TEST_SHORT_URL, 1, Some("someone-else"))
+            .await;
+
+        // Assert
+        for result in [missing, wrong] {
+            assert_eq!(
+                result.unwrap_err(),
+                ApiError::Unauthorized("Invalid admin token")
+            );
+        }
+    }
+}
@@ -1 +1,17 @@
+pub mod click_count_service;
+pub mod click_dump_service;
+pub mod click_event_service;
+pub mod click_service;
+pub mod conversion_service;
+pub mod export_service;
+pub mod history_service;
+pub mod qr_backfill_service;
+pub mod qr_reconcile_service;
+pub mod qr_service;
+pub mod qr_upload_service;
+pub mod retention_service;
+pub mod schedule_service;
+pub mod stats_service;
+pub mod top_links_service;
 pub mod url_service;
+pub(crate) mod validation;
@@ -0,0 +1,565 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::QrBackfillRequest;
+use crate::models::response_model::QrBackfillResponseModel;
+use crate::qr::publish::prerender_enabled;
+use crate::services::qr_service::reupload_stored_qr;
+use crate::services::validation::ensure_admin;
+use async_trait::async_trait;
+use coi::Inject;
+use log::{error, info, warn};
+use rand::distr::Alphanumeric;
+use rand::{rng, Rng};
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url_shortener_database::models::qr_backfill_models::{BACKFILL_COMPLETED, BACKFILL_FAILED};
+use url_shortener_database::repositories::qr_backfill_repository::QrBackfillRepositoryTrait;
+use url_shortener_database::repositories::qr_options_repository::QrOptionsRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+const BACKFILL_PAGE_SIZE: i64 = 100;
+/// Well below the S3 request rate limits, so the backfill never slows down regular uploads.
+const DEFAULT_UPLOADS_PER_SEC: u32 = 10;
+/// A worker that saved no progress for this long is presumed gone, another one resumes its backfill.
+const BACKFILL_LEASE_SECS: i64 = 300;
+/// Progress is saved after every page, and this often while a slow page is still uploading.
+const BACKFILL_HEARTBEAT: Duration = Duration::from_secs(60);
+const LEASE_ID_LENGTH: usize = 16;
+
+/// Identifies the worker holding a job's lease, unique per run.
+pub(crate) fn new_lease_id() -> String {
+    rng()
+        .sample_iter(&Alphanumeric)
+        .take(LEASE_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+#[async_trait]
+pub trait QrBackfillServiceTrait: Inject {
+    /// Queues a backfill for the current `APP_DOMAIN`, the worker picks it up on its next run.
+    async fn start_backfill(
+        &self,
+        token: Option<&str>,
+        request: QrBackfillRequest,
+    ) -> Result<QrBackfillResponseModel, ApiError>;
+    async fn get_backfill(
+        &self,
+        token: Option<&str>,
+        id: i64,
+    ) -> Result<QrBackfillResponseModel, ApiError>;
+    /// Runs the active backfill to the end, resuming after the last link it saved progress for.
+    /// The backfill is leased to this worker meanwhile, so other hosts leave it alone.
+    async fn run_active_backfill(&self) -> Result<usize, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrBackfillServiceTrait with QrBackfillService::new(qr_backfill_repository, url_repository, qr_options_repository, s3_client_wrapper))]
+struct QrBackfillService {
+    #[coi(inject)]
+    qr_backfill_repository: Arc<dyn QrBackfillRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+    #[coi(inject)]
+    s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+}
+
+impl QrBackfillService {
+    pub fn new(
+        qr_backfill_repository: Arc<dyn QrBackfillRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+        s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            qr_backfill_repository,
+            url_repository,
+            qr_options_repository,
+            s3_client_wrapper,
+        }
+    }
+
+    fn upload_interval() -> Duration {
+        let uploads_per_sec = env::var("QR_BACKFILL_UPLOADS_PER_SEC")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_UPLOADS_PER_SEC);
+        Duration::from_secs(1) / uploads_per_sec
+    }
+
+    /// `false` once another worker took the backfill over.
+    async fn update_status(
+        &self,
+        id: i64,
+        lease: &str,
+        status: &str,
+        error: Option<String>,
+    ) -> Result<bool, ApiError> {
+        self.qr_backfill_repository
+            .update_status(id, lease, status, error)
+            .await
+            .map_err(|e| {
+                error!("Failed to update qr backfill {}: {:?}", id, e);
+                ApiError::InternalServerError
+            })
+    }
+
+    /// Saves progress and renews the lease, `false` once another worker took the backfill over.
+    async fn save_progress(
+        &self,
+        id: i64,
+        lease: &str,
+        last_url_id: &str,
+        processed: i64,
+        failed: i64,
+    ) -> Result<bool, ApiError> {
+        let held = self
+            .qr_backfill_repository
+            .update_progress(
+                id,
+                lease,
+                BACKFILL_LEASE_SECS,
+                last_url_id,
+                processed,
+                failed,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to save qr backfill {} progress: {:?}", id, e);
+                ApiError::InternalServerError
+            })?;
+        if !held {
+            warn!("QR backfill {} was taken over by another worker", id);
+        }
+        Ok(held)
+    }
+}
+
+#[async_trait]
+impl QrBackfillServiceTrait for QrBackfillService {
+    async fn start_backfill(
+        &self,
+        token: Option<&str>,
+        request: QrBackfillRequest,
+    ) -> Result<QrBackfillResponseModel, ApiError> {
+        ensure_admin(token)?;
+
+        if !prerender_enabled() {
+            return Err(ApiError::BadRequest(
+                "QR pre-rendering is disabled, codes are rendered on demand with the current domain",
+            ));
+        }
+
+        let destination_prefix = request
+            .destination_prefix
+            .filter(|prefix| !prefix.is_empty());
+        let total = self
+            .url_repository
+            .count(destination_prefix.clone())
+            .await
+            .map_err(|e| {
+                error!("Failed to count urls: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let job = self
+            .qr_backfill_repository
+            .create(&domain, destination_prefix, total)
+            .await
+            .map_err(|e| {
+                error!("Failed to create qr backfill: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        let Some(job) = job else {
+            warn!(
+                "QR backfill for {} requested while another is in progress",
+                domain
+            );
+            return Err(ApiError::BadRequest("A QR backfill is already in progress"));
+        };
+
+        info!(
+            "Queued qr backfill {} of {} links for {}",
+            job.id, total, domain
+        );
+        Ok(job.into())
+    }
+
+    async fn get_backfill(
+        &self,
+        token: Option<&str>,
+        id: i64,
+    ) -> Result<QrBackfillResponseModel, ApiError> {
+        ensure_admin(token)?;
+
+        match self.qr_backfill_repository.find(id).await {
+            Ok(Some(job)) => Ok(job.into()),
+            Ok(None) => Err(ApiError::NotFound("The QR backfill was not found")),
+            Err(e) => {
+                error!("Failed to get qr backfill {}: {:?}", id, e);
+                Err(ApiError::InternalServerError)
+            }
+        }
+    }
+
+    async fn run_active_backfill(&self) -> Result<usize, ApiError> {
+        let lease = new_lease_id();
+        let job = self
+            .qr_backfill_repository
+            .claim_active(&lease, BACKFILL_LEASE_SECS)
+            .await
+            .map_err(|e| {
+                error!("Failed to claim the active qr backfill: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        let Some(job) = job else {
+            return Ok(0);
+        };
+
+        // rendering with another domain would undo the backfill, the new one has to be started again
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        if job.domain != domain {
+            warn!(
+                "QR backfill {} was started for {}, the domain is now {}",
+                job.id, job.domain, domain
+            );
+            self.update_status(
+                job.id,
+                &lease,
+                BACKFILL_FAILED,
+                Some("APP_DOMAIN changed since the backfill was started".to_string()),
+            )
+            .await?;
+            return Ok(0);
+        }
+
+        let mut uploads = tokio::time::interval(Self::upload_interval());
+        let mut heartbeat = Instant::now();
+        let (mut last_url_id, mut processed, mut failed) =
+            (job.last_url_id, job.processed, job.failed);
+        let mut handled = 0;
+        loop {
+            let urls = self
+                .url_repository
+                .find_page(
+                    &last_url_id,
+                    job.destination_prefix.clone(),
+                    BACKFILL_PAGE_SIZE,
+                )
+                .await
+                .map_err(|e| {
+                    error!("Failed to read urls for qr backfill {}: {:?}", job.id, e);
+                    ApiError::InternalServerError
+                })?;
+
+            for url in &urls {
+                uploads.tick().await;
+                // one link failing does not stop the others, the failures are counted on the job
+                if let Err(e) = reupload_stored_qr(
+                    self.qr_options_repository.as_ref(),
+                    self.s3_client_wrapper.as_ref(),
+                    &job.domain,
+                    &url.id,
+                )
+                .await
+                {
+                    warn!("QR backfill {} failed for {}: {:?}", job.id, url.id, e);
+                    failed += 1;
+                }
+                processed += 1;
+                handled += 1;
+                last_url_id = url.id.clone();
+
+                if heartbeat.elapsed() >= BACKFILL_HEARTBEAT {
+                    if !self
+                        .save_progress(job.id, &lease, &last_url_id, processed, failed)
+                        .await?
+                    {
+                        return Ok(handled);
+                    }
+                    heartbeat = Instant::now();
+                }
+            }
+
+            if !urls.is_empty() {
+                if !self
+                    .save_progress(job.id, &lease, &last_url_id, processed, failed)
+                    .await?
+                {
+                    return Ok(handled);
+                }
+                heartbeat = Instant::now();
+            }
+            if urls.len() < BACKFILL_PAGE_SIZE as usize {
+                break;
+            }
+        }
+
+        if !self
+            .update_status(job.id, &lease, BACKFILL_COMPLETED, None)
+            .await?
+        {
+            warn!("QR backfill {} was taken over before it completed", job.id);
+            return Ok(handled);
+        }
+        info!(
+            "QR backfill {} completed, {} links processed and {} failed",
+            job.id, processed, failed
+        );
+        Ok(handled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::qr_models::QrBackfillRequest;
+    use crate::services::qr_backfill_service::{QrBackfillService, QrBackfillServiceTrait};
+    use chrono::Utc;
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use mockall::Sequence;
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::qr_backfill_models::QrBackfillJob;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::qr_backfill_repository::MockQrBackfillRepositoryTrait;
+    use url_shortener_database::repositories::qr_options_repository::MockQrOptionsRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::s3::error::S3Error;
+    use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
+
+    const TEST_TOKEN: &str = "admin-secret";
+    const TEST_DOMAIN: &str = "http://localhost:8080";
+
+    fn job(status: &str, last_url_id: &str) -> QrBackfillJob {
+        QrBackfillJob {
+            id: 1,
+            status: status.to_string(),
+            domain: TEST_DOMAIN.to_string(),
+            destination_prefix: None,
+            last_url_id: last_url_id.to_string(),
+            total: 2,
+            processed: 0,
+            failed: 0,
+            error: None,
+            leased_by: None,
+            leased_until: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+
+    fn url(id: &str) -> Url {
+        Url {
+            id: id.to_string(),
+            url: "https://www.google.com".to_string(),
+            track_conversions: false,
+            campaign: None,
+        }
+    }
+
+    fn service(
+        qr_backfill_repository: MockQrBackfillRepositoryTrait,
+        url_repository: MockUrlRepositoryTrait,
+        s3_client: MockS3ClientWrapperTrait,
+    ) -> QrBackfillService {
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        QrBackfillService::new(
+            Arc::new(qr_backfill_repository),
+            Arc::new(url_repository),
+            Arc::new(qr_options_repository),
+            Arc::new(s3_client),
+        )
+    }
+
+    #[tokio::test]
+    async fn start_backfill_queues_a_job_for_the_current_domain() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        env::set_var("APP_DOMAIN", TEST_DOMAIN);
+        let mut qr_backfill_repository = MockQrBackfillRepositoryTrait::new();
+        qr_backfill_repository
+            .expect_create()
+            .with(
+                eq(TEST_DOMAIN),
+                eq(Some("https://shop.example.com".to_string())),
+                eq(2),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(Some(job("pending", ""))) }));
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_count()
+            .returning(|_| Box::pin(async { Ok(2) }));
+        let service = service(
+            qr_backfill_repository,
+            url_repository,
+            MockS3ClientWrapperTrait::new(),
+        );
+        let request = QrBackfillRequest {
+            destination_prefix: Some("https://shop.example.com".to_string()),
+        };
+
+        // Act
+        let result = service.start_backfill(Some(TEST_TOKEN), request).await;
+
+        // Assert
+        let job = result.unwrap();
+        assert_eq!(job.status, "pending");
+        assert_eq!(job.total, 2);
+    }
+
+    #[tokio::test]
+    async fn start_backfill_rejects_invalid_token_and_running_jobs() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        env::set_var("APP_DOMAIN", TEST_DOMAIN);
+        let mut qr_backfill_repository = MockQrBackfillRepositoryTrait::new();
+        // the unique index rejects a second active job
+        qr_backfill_repository
+            .expect_create()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(None) }));
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_count()
+            .returning(|_| Box::pin(async { Ok(2) }));
+        let service = service(
+            qr_backfill_repository,
+            url_repository,
+            MockS3ClientWrapperTrait::new(),
+        );
+
+        // Act
+        let unauthorized = service
+            .start_backfill(Some("wrong"), QrBackfillRequest::default())
+            .await;
+        let in_progress = service
+            .start_backfill(Some(TEST_TOKEN), QrBackfillRequest::default())
+            .await;
+
+        // Assert
+        assert_eq!(
+            unauthorized.unwrap_err(),
+            ApiError::Unauthorized("Invalid admin token")
+        );
+        assert_eq!(
+            in_progress.unwrap_err(),
+            ApiError::BadRequest("A QR backfill is already in progress")
+        );
+    }
+
+    #[tokio::test]
+    async fn run_active_backfill_resumes_and_counts_failures() {
+        // Arrange
+        env::set_var("APP_DOMAIN", TEST_DOMAIN);
+        env::set_var("QR_BACKFILL_UPLOADS_PER_SEC", "1000");
+        let mut qr_backfill_repository = MockQrBackfillRepositoryTrait::new();
+        qr_backfill_repository
+            .expect_claim_active()
+            .with(always(), eq(300))
+            .returning(|_, _| Box::pin(async { Ok(Some(job("running", "abc"))) }));
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find_page()
+            .with(eq("abc"), eq(None), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(vec![url("abd"), url("abe")]) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("abd.png"), always())
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(S3Error)) }));
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("abe.png"), always())
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        let mut sequence = Sequence::new();
+        qr_backfill_repository
+            .expect_update_progress()
+            .with(eq(1), always(), eq(300), eq("abe"), eq(2), eq(1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _, _, _, _| Box::pin(async { Ok(true) }));
+        qr_backfill_repository
+            .expect_update_status()
+            .with(eq(1), always(), eq("completed"), eq(None))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _, _| Box::pin(async { Ok(true) }));
+        let service = service(qr_backfill_repository, url_repository, s3_client);
+
+        // Act
+        let result = service.run_active_backfill().await;
+
+        // Assert
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_active_backfill_stops_once_the_lease_was_taken_over() {
+        // Arrange
+        env::set_var("APP_DOMAIN", TEST_DOMAIN);
+        env::set_var("QR_BACKFILL_UPLOADS_PER_SEC", "1000");
+        let mut qr_backfill_repository = MockQrBackfillRepositoryTrait::new();
+        qr_backfill_repository
+            .expect_claim_active()
+            .returning(|_, _| Box::pin(async { Ok(Some(job("running", ""))) }));
+        qr_backfill_repository
+            .expect_update_progress()
+            .times(1)
+            .returning(|_, _, _, _, _, _| Box::pin(async { Ok(false) }));
+        qr_backfill_repository.expect_update_status().never();
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find_page()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(vec![url("abd")]) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        let service = service(qr_backfill_repository, url_repository, s3_client);
+
+        // Act
+        let result = service.run_active_backfill().await;
+
+        // Assert
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_backfill_returns_not_found() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let mut qr_backfill_repository = MockQrBackfillRepositoryTrait::new();
+        qr_backfill_repository
+            .expect_find()
+            .with(eq(7))
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let service = service(
+            qr_backfill_repository,
+            MockUrlRepositoryTrait::new(),
+            MockS3ClientWrapperTrait::new(),
+        );
+
+        // Act
+        let result = service.get_backfill(Some(TEST_TOKEN), 7).await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The QR backfill was not found")
+        );
+    }
+}
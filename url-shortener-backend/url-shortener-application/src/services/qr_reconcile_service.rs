@@ -0,0 +1,594 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::QrReconcileRequest;
+use crate::models::response_model::QrReconcileResponseModel;
+use crate::qr::publish::prerender_enabled;
+use crate::qr::renderer::{qr_file_format, qr_file_name};
+use crate::qr::settings::{stored_format, QrSettings};
+use crate::services::qr_backfill_service::new_lease_id;
+use crate::services::qr_service::reupload_stored_qr;
+use crate::services::validation::ensure_admin;
+use async_trait::async_trait;
+use coi::Inject;
+use log::{error, info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url_shortener_database::models::qr_reconcile_models::{QrReconcileJob, QrReconcileReport};
+use url_shortener_database::repositories::qr_options_repository::QrOptionsRepositoryTrait;
+use url_shortener_database::repositories::qr_reconcile_repository::QrReconcileRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+const RECONCILE_PAGE_SIZE: i64 = 500;
+/// A worker that did not renew its lease for this long is presumed gone, another one runs the job again.
+const RECONCILE_LEASE_SECS: i64 = 300;
+const RECONCILE_HEARTBEAT: Duration = Duration::from_secs(60);
+
+#[async_trait]
+pub trait QrReconcileServiceTrait: Inject {
+    /// Queues a reconciliation, the worker picks it up on its next run. It deletes stored images
+    /// no link uses and regenerates those links are missing, a dry run only reports them.
+    async fn start_reconcile(
+        &self,
+        token: Option<&str>,
+        request: QrReconcileRequest,
+    ) -> Result<QrReconcileResponseModel, ApiError>;
+    async fn get_reconcile(
+        &self,
+        token: Option<&str>,
+        id: i64,
+    ) -> Result<QrReconcileResponseModel, ApiError>;
+    /// Runs the active reconciliation to the end, the job is leased to this worker meanwhile.
+    async fn run_active_reconcile(&self) -> Result<usize, ApiError>;
+}
+
+/// This worker's claim on the running job, renewed while the job makes progress.
+struct Lease {
+    id: String,
+    job_id: i64,
+    renewed_at: Instant,
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrReconcileServiceTrait with QrReconcileService::new(qr_reconcile_repository, url_repository, qr_options_repository, s3_client_wrapper))]
+struct QrReconcileService {
+    #[coi(inject)]
+    qr_reconcile_repository: Arc<dyn QrReconcileRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+    #[coi(inject)]
+    s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+}
+
+impl QrReconcileService {
+    pub fn new(
+        qr_reconcile_repository: Arc<dyn QrReconcileRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+        s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            qr_reconcile_repository,
+            url_repository,
+            qr_options_repository,
+            s3_client_wrapper,
+        }
+    }
+
+    /// Renews the lease once a heartbeat is due, `false` once another worker took the job over.
+    async fn keep_lease(&self, lease: &mut Lease) -> Result<bool, ApiError> {
+        if lease.renewed_at.elapsed() < RECONCILE_HEARTBEAT {
+            return Ok(true);
+        }
+        let held = self
+            .qr_reconcile_repository
+            .renew_lease(lease.job_id, &lease.id, RECONCILE_LEASE_SECS)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to renew qr reconciliation {} lease: {:?}",
+                    lease.job_id, e
+                );
+                ApiError::InternalServerError
+            })?;
+        lease.renewed_at = Instant::now();
+        Ok(held)
+    }
+
+    /// The file name every link's image is stored under, by link. `None` once the lease was lost.
+    async fn expected_images(
+        &self,
+        lease: &mut Lease,
+    ) -> Result<Option<BTreeMap<String, String>>, ApiError> {
+        let mut expected = BTreeMap::new();
+        let mut last_url_id = String::new();
+        loop {
+            let urls = self
+                .url_repository
+                .find_page(&last_url_id, None, RECONCILE_PAGE_SIZE)
+                .await
+                .map_err(|e| {
+                    error!("Failed to read urls for qr reconciliation: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+
+            // only the format decides the file name, it is read for the whole page at once
+            let url_ids = urls.iter().map(|url| url.id.clone()).collect::<Vec<_>>();
+            let formats = self
+                .qr_options_repository
+                .find_formats(&url_ids)
+                .await
+                .map_err(|e| {
+                    error!("Failed to read qr formats for reconciliation: {:?}", e);
+                    ApiError::InternalServerError
+                })?;
+            for url in &urls {
+                let format = formats
+                    .get(&url.id)
+                    .map_or(QrSettings::default().format, |format| stored_format(format));
+                expected.insert(qr_file_name(&url.id, format), url.id.clone());
+            }
+
+            if !self.keep_lease(lease).await? {
+                return Ok(None);
+            }
+            match urls.last() {
+                Some(url) if urls.len() == RECONCILE_PAGE_SIZE as usize => {
+                    last_url_id = url.id.clone()
+                }
+                _ => break,
+            }
+        }
+        Ok(Some(expected))
+    }
+
+    /// Compares the stored images with the links and fixes the differences unless it is a dry run.
+    /// `None` once the lease was lost, the worker that took the job over runs it again.
+    async fn reconcile(
+        &self,
+        job: &QrReconcileJob,
+        lease: &mut Lease,
+    ) -> Result<Option<QrReconcileReport>, ApiError> {
+        // listed before the links are read, an image is only uploaded once its link is saved,
+        // so a link created meanwhile shows up as missing and never has its image deleted
+        let stored = self.s3_client_wrapper.list_images().await.map_err(|e| {
+            error!("Failed to list stored qr codes: {:?}", e);
+            ApiError::InternalServerError
+        })?;
+        // other files at the top level are not QR images and are left alone
+        let stored = stored
+            .into_iter()
+            .filter(|file_name| qr_file_format(file_name).is_some())
+            .collect::<BTreeSet<_>>();
+        let Some(expected) = self.expected_images(lease).await? else {
+            return Ok(None);
+        };
+
+        let orphaned = stored
+            .iter()
+            .filter(|file_name| !expected.contains_key(*file_name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let missing = expected
+            .iter()
+            .filter(|(file_name, _)| !stored.contains(*file_name))
+            .map(|(file_name, short_url)| (file_name.clone(), short_url.clone()))
+            .collect::<Vec<_>>();
+
+        let (mut deleted, mut regenerated, mut failed) = (0, 0, 0);
+        if !job.dry_run {
+            for file_name in &orphaned {
+                match self.s3_client_wrapper.delete_image(file_name).await {
+                    Ok(()) => deleted += 1,
+                    Err(e) => {
+                        warn!("Failed to delete orphaned qr code {}: {:?}", file_name, e);
+                        failed += 1;
+                    }
+                }
+                if !self.keep_lease(lease).await? {
+                    return Ok(None);
+                }
+            }
+
+            let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+            for (_, short_url) in &missing {
+                match reupload_stored_qr(
+                    self.qr_options_repository.as_ref(),
+                    self.s3_client_wrapper.as_ref(),
+                    &domain,
+                    short_url,
+                )
+                .await
+                {
+                    Ok(()) => regenerated += 1,
+                    Err(e) => {
+                        warn!("Failed to regenerate qr code for {}: {:?}", short_url, e);
+                        failed += 1;
+                    }
+                }
+                if !self.keep_lease(lease).await? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        Ok(Some(QrReconcileReport {
+            orphaned,
+            missing: missing
+                .into_iter()
+                .map(|(file_name, _)| file_name)
+                .collect(),
+            deleted,
+            regenerated,
+            failed,
+        }))
+    }
+}
+
+#[async_trait]
+impl QrReconcileServiceTrait for QrReconcileService {
+    async fn start_reconcile(
+        &self,
+        token: Option<&str>,
+        request: QrReconcileRequest,
+    ) -> Result<QrReconcileResponseModel, ApiError> {
+        ensure_admin(token)?;
+
+        if !prerender_enabled() {
+            return Err(ApiError::BadRequest(
+                "QR pre-rendering is disabled, there are no stored images to reconcile",
+            ));
+        }
+
+        let dry_run = request.dry_run.unwrap_or(true);
+        let job = self
+            .qr_reconcile_repository
+            .create(dry_run)
+            .await
+            .map_err(|e| {
+                error!("Failed to create qr reconciliation: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        let Some(job) = job else {
+            warn!("QR reconciliation requested while another is in progress");
+            return Err(ApiError::BadRequest(
+                "A QR reconciliation is already in progress",
+            ));
+        };
+
+        info!("Queued qr reconciliation {} (dry run: {})", job.id, dry_run);
+        Ok(job.into())
+    }
+
+    async fn get_reconcile(
+        &self,
+        token: Option<&str>,
+        id: i64,
+    ) -> Result<QrReconcileResponseModel, ApiError> {
+        ensure_admin(token)?;
+
+        match self.qr_reconcile_repository.find(id).await {
+            Ok(Some(job)) => Ok(job.into()),
+            Ok(None) => Err(ApiError::NotFound("The QR reconciliation was not found")),
+            Err(e) => {
+                error!("Failed to get qr reconciliation {}: {:?}", id, e);
+                Err(ApiError::InternalServerError)
+            }
+        }
+    }
+
+    async fn run_active_reconcile(&self) -> Result<usize, ApiError> {
+        let lease_id = new_lease_id();
+        let job = self
+            .qr_reconcile_repository
+            .claim_active(&lease_id, RECONCILE_LEASE_SECS)
+            .await
+            .map_err(|e| {
+                error!("Failed to claim the active qr reconciliation: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        let Some(job) = job else {
+            return Ok(0);
+        };
+        let mut lease = Lease {
+            id: lease_id,
+            job_id: job.id,
+            renewed_at: Instant::now(),
+        };
+
+        let report = match self.reconcile(&job, &mut lease).await {
+            Ok(Some(report)) => report,
+            Ok(None) => {
+                warn!(
+                    "QR reconciliation {} was taken over by another worker",
+                    job.id
+                );
+                return Ok(0);
+            }
+            Err(e) => {
+                // the error itself is logged where it happened, the job only records that it failed
+                if let Err(fail_error) = self
+                    .qr_reconcile_repository
+                    .fail(
+                        job.id,
+                        &lease.id,
+                        "The reconciliation stopped on an error".to_string(),
+                    )
+                    .await
+                {
+                    error!(
+                        "Failed to mark qr reconciliation {} failed: {:?}",
+                        job.id, fail_error
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        let found = report.orphaned.len() + report.missing.len();
+        info!(
+            "QR reconciliation {} found {} orphaned and {} missing images (dry run: {}), {} deleted, {} regenerated and {} failed",
+            job.id,
+            report.orphaned.len(),
+            report.missing.len(),
+            job.dry_run,
+            report.deleted,
+            report.regenerated,
+            report.failed
+        );
+        let held = self
+            .qr_reconcile_repository
+            .complete(job.id, &lease.id, report)
+            .await
+            .map_err(|e| {
+                error!("Failed to complete qr reconciliation {}: {:?}", job.id, e);
+                ApiError::InternalServerError
+            })?;
+        if !held {
+            warn!(
+                "QR reconciliation {} was taken over before it completed",
+                job.id
+            );
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::qr_models::QrReconcileRequest;
+    use crate::services::qr_reconcile_service::{QrReconcileService, QrReconcileServiceTrait};
+    use chrono::Utc;
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use std::collections::HashMap;
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::qr_reconcile_models::{QrReconcileJob, QrReconcileReport};
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::qr_options_repository::MockQrOptionsRepositoryTrait;
+    use url_shortener_database::repositories::qr_reconcile_repository::MockQrReconcileRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::s3::error::S3Error;
+    use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
+
+    const TEST_TOKEN: &str = "admin-secret";
+
+    fn job(status: &str, dry_run: bool) -> QrReconcileJob {
+        QrReconcileJob {
+            id: 1,
+            status: status.to_string(),
+            dry_run,
+            orphaned: vec![],
+            missing: vec![],
+            deleted: 0,
+            regenerated: 0,
+            failed: 0,
+            error: None,
+            leased_by: None,
+            leased_until: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+
+    fn url(id: &str) -> Url {
+        Url {
+            id: id.to_string(),
+            url: "https://www.google.com".to_string(),
+            track_conversions: false,
+            campaign: None,
+        }
+    }
+
+    fn service(
+        qr_reconcile_repository: MockQrReconcileRepositoryTrait,
+        s3_client: MockS3ClientWrapperTrait,
+    ) -> QrReconcileService {
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find_page()
+            .with(eq(""), eq(None), always())
+            .returning(|_, _, _| Box::pin(async { Ok(vec![url("abc"), url("abd")]) }));
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        // the options of a whole page are read in one query
+        qr_options_repository
+            .expect_find_formats()
+            .withf(|url_ids| url_ids == ["abc".to_string(), "abd".to_string()])
+            .returning(|_| Box::pin(async { Ok(HashMap::new()) }));
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        QrReconcileService::new(
+            Arc::new(qr_reconcile_repository),
+            Arc::new(url_repository),
+            Arc::new(qr_options_repository),
+            Arc::new(s3_client),
+        )
+    }
+
+    fn stored_images(s3_client: &mut MockS3ClientWrapperTrait) {
+        s3_client.expect_list_images().times(1).returning(|| {
+            Box::pin(async {
+                Ok(vec![
+                    "abc.png".to_string(),
+                    "deleted.png".to_string(),
+                    "notes.txt".to_string(),
+                ])
+            })
+        });
+    }
+
+    #[tokio::test]
+    async fn start_reconcile_queues_a_dry_run_by_default() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let mut qr_reconcile_repository = MockQrReconcileRepositoryTrait::new();
+        qr_reconcile_repository
+            .expect_create()
+            .with(eq(true))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(Some(job("pending", true))) }));
+        let service = service(qr_reconcile_repository, MockS3ClientWrapperTrait::new());
+
+        // Act
+        let result = service
+            .start_reconcile(Some(TEST_TOKEN), QrReconcileRequest::default())
+            .await;
+
+        // Assert
+        let job = result.unwrap();
+        assert_eq!(job.status, "pending");
+        assert!(job.dry_run);
+    }
+
+    #[tokio::test]
+    async fn start_reconcile_rejects_invalid_token_and_running_jobs() {
+        // Arrange
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let mut qr_reconcile_repository = MockQrReconcileRepositoryTrait::new();
+        // the unique index rejects a second active job
+        qr_reconcile_repository
+            .expect_create()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let service = service(qr_reconcile_repository, MockS3ClientWrapperTrait::new());
+
+        // Act
+        let unauthorized = service
+            .start_reconcile(Some("wrong"), QrReconcileRequest::default())
+            .await;
+        let in_progress = service
+            .start_reconcile(Some(TEST_TOKEN), QrReconcileRequest::default())
+            .await;
+
+        // Assert
+        assert_eq!(
+            unauthorized.unwrap_err(),
+            ApiError::Unauthorized("Invalid admin token")
+        );
+        assert_eq!(
+            in_progress.unwrap_err(),
+            ApiError::BadRequest("A QR reconciliation is already in progress")
+        );
+    }
+
+    #[tokio::test]
+    async fn run_active_reconcile_dry_run_only_reports_differences() {
+        // Arrange
+        let mut qr_reconcile_repository = MockQrReconcileRepositoryTrait::new();
+        qr_reconcile_repository
+            .expect_claim_active()
+            .with(always(), eq(300))
+            .returning(|_, _| Box::pin(async { Ok(Some(job("running", true))) }));
+        qr_reconcile_repository
+            .expect_complete()
+            .with(
+                eq(1),
+                always(),
+                eq(QrReconcileReport {
+                    orphaned: vec!["deleted.png".to_string()],
+                    missing: vec!["abd.png".to_string()],
+                    ..Default::default()
+                }),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(true) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        stored_images(&mut s3_client);
+        s3_client.expect_delete_image().never();
+        s3_client.expect_upload_image().never();
+        let service = service(qr_reconcile_repository, s3_client);
+
+        // Act
+        let result = service.run_active_reconcile().await;
+
+        // Assert
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_active_reconcile_deletes_orphans_and_regenerates_missing_images() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_reconcile_repository = MockQrReconcileRepositoryTrait::new();
+        qr_reconcile_repository
+            .expect_claim_active()
+            .returning(|_, _| Box::pin(async { Ok(Some(job("running", false))) }));
+        qr_reconcile_repository
+            .expect_complete()
+            .withf(|_, _, report| (report.deleted, report.regenerated, report.failed) == (0, 1, 1))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(true) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        stored_images(&mut s3_client);
+        s3_client
+            .expect_delete_image()
+            .with(eq("deleted.png"))
+            .times(1)
+            .returning(|_| Box::pin(async { Err(Report::new(S3Error)) }));
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("abd.png"), eq("image/png"))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        let service = service(qr_reconcile_repository, s3_client);
+
+        // Act
+        let result = service.run_active_reconcile().await;
+
+        // Assert
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_active_reconcile_fails_the_job_when_images_cannot_be_listed() {
+        // Arrange
+        let mut qr_reconcile_repository = MockQrReconcileRepositoryTrait::new();
+        qr_reconcile_repository
+            .expect_claim_active()
+            .returning(|_, _| Box::pin(async { Ok(Some(job("running", true))) }));
+        qr_reconcile_repository
+            .expect_fail()
+            .with(eq(1), always(), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(true) }));
+        qr_reconcile_repository.expect_complete().never();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_list_images()
+            .returning(|| Box::pin(async { Err(Report::new(S3Error)) }));
+        let service = service(qr_reconcile_repository, s3_client);
+
+        // Act
+        let result = service.run_active_reconcile().await;
+
+        // Assert
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+}
@@ -0,0 +1,1074 @@
+use crate::models::errors::ApiError;
+use crate::models::qr_models::{
+    QrExportRequest, QrImageModel, QrImageQuery, QrOptions, QrStatus, Symbology,
+};
+use crate::models::response_model::CreateResponseModel;
+use crate::qr::cache::QrImageCacheTrait;
+use crate::qr::export::{ExportLink, QrExportWriter};
+use crate::qr::label_sheet::LABELS_PER_PAGE;
+use crate::qr::logo::normalize_logo;
+use crate::qr::publish::{prerender_enabled, publish_qr, publish_queued_qr, upload_qr};
+use crate::qr::renderer::{qr_etag, qr_file_format, qr_payload, render_qr};
+use crate::qr::settings::QrSettings;
+use crate::services::validation::{admin_actor, ensure_url_exists};
+use async_trait::async_trait;
+use coi::Inject;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use log::{error, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+use url_shortener_database::repositories::qr_options_repository::QrOptionsRepositoryTrait;
+use url_shortener_database::repositories::qr_upload_repository::QrUploadRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+/// Enough for a large event, each code is rendered while the response streams.
+const MAX_EXPORT_CODES: usize = 1000;
+
+#[async_trait]
+pub trait QrServiceTrait: Inject {
+    /// Renders the link's QR code again, empty options re-render it with the stored ones.
+    async fn regenerate_qr(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        options: QrOptions,
+    ) -> Result<CreateResponseModel, ApiError>;
+    /// Stores a PNG or JPEG logo for the center of the link's QR code.
+    async fn upload_logo(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        logo: Vec<u8>,
+    ) -> Result<CreateResponseModel, ApiError>;
+    async fn remove_logo(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+    ) -> Result<CreateResponseModel, ApiError>;
+    /// Renders the link's QR code on demand, the query overrides the stored format and size.
+    async fn get_qr_image(
+        &self,
+        short_url: &str,
+        query: QrImageQuery,
+    ) -> Result<QrImageModel, ApiError>;
+    /// Serves a pre-rendered image from storage, for setups without a CDN in front of it.
+    async fn get_stored_image(&self, file_name: &str) -> Result<QrImageModel, ApiError>;
+    /// Streams the codes of several links as a ZIP of images or a PDF label sheet.
+    async fn export_qr(
+        &self,
+        request: QrExportRequest,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, ApiError>>, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrServiceTrait with QrService::new(url_repository, qr_options_repository, qr_upload_repository, s3_client_wrapper, qr_image_cache))]
+struct QrService {
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+    #[coi(inject)]
+    qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
+    #[coi(inject)]
+    s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+    #[coi(inject)]
+    qr_image_cache: Arc<dyn QrImageCacheTrait>,
+}
+
+impl QrService {
+    pub fn new(
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+        qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
+        s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+        qr_image_cache: Arc<dyn QrImageCacheTrait>,
+    ) -> Self {
+        Self {
+            url_repository,
+            qr_options_repository,
+            qr_upload_repository,
+            s3_client_wrapper,
+            qr_image_cache,
+        }
+    }
+
+    /// Renders before saving, so options the code cannot be drawn with are never stored. The
+    /// options commit with a queued upload, so saved options always end up in the stored image.
+    async fn save_and_publish(
+        &self,
+        short_url: &str,
+        settings: QrSettings,
+        changed_by: &str,
+    ) -> Result<CreateResponseModel, ApiError> {
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let qr_code = render_qr(&qr_payload(&domain, short_url), &settings)?;
+
+        self.qr_options_repository
+            .save(
+                settings.to_record(short_url),
+                changed_by,
+                prerender_enabled(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to save qr options: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        let (qr_code_image, qr_status) = publish_queued_qr(
+            self.qr_upload_repository.as_ref(),
+            self.s3_client_wrapper.as_ref(),
+            &domain,
+            short_url,
+            settings.format,
+            qr_code,
+        )
+        .await;
+        Ok(CreateResponseModel {
+            short_url: format!("{}/{}", domain, short_url),
+            qr_code_image,
+            qr_status,
+        })
+    }
+
+    async fn publish(
+        &self,
+        short_url: &str,
+        settings: &QrSettings,
+    ) -> Result<CreateResponseModel, ApiError> {
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let qr_code = render_qr(&qr_payload(&domain, short_url), settings)?;
+        let qr_code_image = publish_qr(
+            self.s3_client_wrapper.as_ref(),
+            &domain,
+            short_url,
+            settings.format,
+            qr_code,
+        )
+        .await?;
+
+        Ok(CreateResponseModel {
+            short_url: format!("{}/{}", domain, short_url),
+            qr_code_image,
+            qr_status: QrStatus::Ready,
+        })
+    }
+
+    async fn stored_settings(&self, short_url: &str) -> Result<QrSettings, ApiError> {
+        stored_settings(self.qr_options_repository.as_ref(), short_url).await
+    }
+}
+
+pub(crate) async fn stored_settings(
+    qr_options_repository: &dyn QrOptionsRepositoryTrait,
+    short_url: &str,
+) -> Result<QrSettings, ApiError> {
+    match qr_options_repository.find(short_url).await {
+        Ok(Some(record)) => Ok(QrSettings::from_record(&record)),
+        Ok(None) => Ok(QrSettings::default()),
+        Err(e) => {
+            error!("Failed to get qr options: {:?}", e);
+            Err(ApiError::InternalServerError)
+        }
+    }
+}
+
+/// Uploads the link's code rendered again with its stored settings, for maintenance jobs.
+pub(crate) async fn reupload_stored_qr(
+    qr_options_repository: &dyn QrOptionsRepositoryTrait,
+    s3_client_wrapper: &dyn S3ClientWrapperTrait,
+    domain: &str,
+    short_url: &str,
+) -> Result<(), ApiError> {
+    let settings = stored_settings(qr_options_repository, short_url).await?;
+    let qr_code = render_qr(&qr_payload(domain, short_url), &settings)?;
+    upload_qr(s3_client_wrapper, short_url, settings.format, qr_code)
+        .await
+        .map(|_| ())
+}
+
+#[async_trait]
+impl QrServiceTrait for QrService {
+    async fn regenerate_qr(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        options: QrOptions,
+    ) -> Result<CreateResponseModel, ApiError> {
+        let changed_by = admin_actor(token)?;
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let stored = self.stored_settings(short_url).await?;
+        if options == QrOptions::default() {
+            return self.publish(short_url, &stored).await;
+        }
+
+        // the logo is uploaded on its own, new options keep the current one
+        let settings = QrSettings::from_options(&options)?.with_logo(stored.logo);
+        self.save_and_publish(short_url, settings, &changed_by)
+            .await
+    }
+
+    async fn upload_logo(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        logo: Vec<u8>,
+    ) -> Result<CreateResponseModel, ApiError> {
+        let changed_by = admin_actor(token)?;
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let logo = normalize_logo(&logo)?;
+        let settings = self.stored_settings(short_url).await?;
+        if settings.symbology != Symbology::Qr {
+            warn!(
+                "Logo upload for a {} code: {}",
+                settings.symbology.as_str(),
+                short_url
+            );
+            return Err(ApiError::BadRequest("Only QR codes can carry a logo"));
+        }
+        self.save_and_publish(short_url, settings.with_logo(Some(logo)), &changed_by)
+            .await
+    }
+
+    async fn remove_logo(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+    ) -> Result<CreateResponseModel, ApiError> {
+        let changed_by = admin_actor(token)?;
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let settings = self.stored_settings(short_url).await?.with_logo(None);
+        self.save_and_publish(short_url, settings, &changed_by)
+            .await
+    }
+
+    async fn get_qr_image(
+        &self,
+        short_url: &str,
+        query: QrImageQuery,
+    ) -> Result<QrImageModel, ApiError> {
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let settings = self.stored_settings(short_url).await?.with_overrides(
+            query.symbology,
+            query.format,
+            query.size,
+        )?;
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let payload = qr_payload(&domain, short_url);
+        let etag = qr_etag(&payload, &settings);
+
+        let body = match self.qr_image_cache.get(&etag) {
+            Some(body) => body,
+            None => {
+                let body = render_qr(&payload, &settings)?;
+                self.qr_image_cache.put(etag.clone(), body.clone());
+                body
+            }
+        };
+
+        Ok(QrImageModel {
+            etag,
+            content_type: settings.format.content_type(),
+            body,
+        })
+    }
+
+    async fn get_stored_image(&self, file_name: &str) -> Result<QrImageModel, ApiError> {
+        // only QR images are served, other objects such as click dumps stay private
+        let Some(format) = qr_file_format(file_name) else {
+            warn!(
+                "Request for a stored file that is not a qr code: {}",
+                file_name
+            );
+            return Err(ApiError::NotFound("The QR image was not found"));
+        };
+
+        match self.s3_client_wrapper.get_image(file_name).await {
+            Ok(Some(body)) => Ok(QrImageModel {
+                etag: format!("\"{:x}\"", Sha256::digest(&body)),
+                content_type: format.content_type(),
+                body,
+            }),
+            Ok(None) => Err(ApiError::NotFound("The QR image was not found")),
+            Err(e) => {
+                error!("Failed to get stored qr code {}: {:?}", file_name, e);
+                Err(ApiError::InternalServerError)
+            }
+        }
+    }
+
+    async fn export_qr(
+        &self,
+        request: QrExportRequest,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, ApiError>>, ApiError> {
+        let mut codes = request.codes;
+        // a code listed twice would give the ZIP two entries with the same name
+        let mut seen = HashSet::new();
+        codes.retain(|code| seen.insert(code.clone()));
+        if codes.is_empty() || codes.len() > MAX_EXPORT_CODES {
+            warn!("Invalid qr export of {} codes", codes.len());
+            return Err(ApiError::BadRequest(
+                "A QR export must have between 1 and 1000 codes",
+            ));
+        }
+        let image = request.image.unwrap_or_default();
+        // overrides are checked before anything is streamed, so a bad size is still a 400
+        QrSettings::default().with_overrides(image.symbology, image.format, image.size)?;
+
+        // the links are looked up front so a missing one fails the request instead of truncating the file
+        let mut links = Vec::with_capacity(codes.len());
+        for code in codes {
+            match self.url_repository.find(&code).await {
+                Ok(Some(url)) => links.push(url),
+                Ok(None) => {
+                    warn!("Short url not found: {:?}", code);
+                    return Err(ApiError::NotFound("The url with this format was not found"));
+                }
+                Err(e) => {
+                    error!("Failed to get long url: {:?}", e);
+                    return Err(ApiError::InternalServerError);
+                }
+            }
+        }
+
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let qr_options_repository = self.qr_options_repository.clone();
+        let (writer, header) = QrExportWriter::new(request.format.unwrap_or_default());
+
+        // the codes are rendered one page at a time, only the page being written is held in memory
+        let pages = stream::iter(links).chunks(LABELS_PER_PAGE).boxed();
+        let body = stream::unfold(Some((pages, writer)), move |state| {
+            let qr_options_repository = qr_options_repository.clone();
+            let domain = domain.clone();
+            async move {
+                let (mut pages, mut writer) = state?;
+                let Some(urls) = pages.next().await else {
+                    return Some((Ok(writer.finish()), None));
+                };
+
+                let mut page = Vec::with_capacity(urls.len());
+                for url in urls {
+                    let settings = match stored_settings(qr_options_repository.as_ref(), &url.id)
+                        .await
+                        .and_then(|settings| {
+                            settings.with_overrides(image.symbology, image.format, image.size)
+                        }) {
+                        Ok(settings) => settings,
+                        Err(e) => return Some((Err(e), None)),
+                    };
+                    page.push(ExportLink {
+                        short_url: url.id,
+                        destination: url.url,
+                        settings,
+                    });
+                }
+
+                match writer.write(&domain, &page) {
+                    Ok(chunk) => Some((Ok(chunk), Some((pages, writer)))),
+                    Err(e) => Some((Err(e), None)),
+                }
+            }
+        });
+
+        Ok(stream::iter((!header.is_empty()).then_some(Ok(header)))
+            .chain(body)
+            .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::qr_models::{
+        QrExportFormat, QrExportRequest, QrFormat, QrImageQuery, QrOptions, QrStatus, Symbology,
+    };
+    use crate::qr::cache::MockQrImageCacheTrait;
+    use crate::qr::settings::QrSettings;
+    use crate::services::qr_service::{QrService, QrServiceTrait};
+    use error_stack::Report;
+    use futures::StreamExt;
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use mockall::predicate::{always, eq};
+    use std::env;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use url_shortener_database::models::qr_models::UrlQrOptions;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::qr_options_repository::MockQrOptionsRepositoryTrait;
+    use url_shortener_database::repositories::qr_upload_repository::MockQrUploadRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::s3::error::S3Error;
+    use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_TOKEN: &str = "admin-secret";
+
+    fn url_repository() -> MockUrlRepositoryTrait {
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Some(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    }))
+                })
+            });
+        url_repository
+    }
+
+    /// The queued upload is completed once the image is stored.
+    fn qr_upload_repository() -> MockQrUploadRepositoryTrait {
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_complete()
+            .returning(|_| Box::pin(async { Ok(()) }));
+        qr_upload_repository
+    }
+
+    #[tokio::test]
+    async fn regenerate_qr_stores_new_options() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        qr_options_repository
+            .expect_save()
+            .withf(|options, changed_by, queue_qr_upload| {
+                changed_by == "admin"
+                    && *queue_qr_upload
+                    && options.format == "svg"
+                    && options.size == 512
+            })
+            .times(1)
+            .returning(|options, _, _| Box::pin(async { Ok(options) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("1234556.svg"), eq("image/svg+xml"))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        s3_client
+            .expect_image_url()
+            .with(eq("1234556.svg"))
+            .returning(|_| {
+                Box::pin(async { Ok(Some("https://cdn.example.com/1234556.svg".to_string())) })
+            });
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let options = QrOptions {
+            format: Some(QrFormat::Svg),
+            size: Some(512),
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service
+            .regenerate_qr(Some(TEST_TOKEN), TEST_SHORT_URL, options)
+            .await;
+
+        // Assert
+        let response = result.unwrap();
+        assert_eq!(
+            response.qr_code_image,
+            "https://cdn.example.com/1234556.svg"
+        );
+    }
+
+    #[tokio::test]
+    async fn regenerate_qr_without_options_uses_stored_ones() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Some(UrlQrOptions {
+                        url_id: TEST_SHORT_URL.to_string(),
+                        symbology: "qr".to_string(),
+                        format: "svg".to_string(),
+                        size: 256,
+                        error_correction: "high".to_string(),
+                        foreground: "#000000".to_string(),
+                        background: "#FFFFFF".to_string(),
+                        quiet_zone: 4,
+                        frame: Some("banner".to_string()),
+                        caption: Some("SCAN ME".to_string()),
+                        logo: None,
+                        logo_size: 20,
+                    }))
+                })
+            });
+        qr_options_repository.expect_save().never();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("1234556.svg"), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        s3_client
+            .expect_image_url()
+            .with(eq("1234556.svg"))
+            .returning(|_| {
+                Box::pin(async { Ok(Some("https://cdn.example.com/1234556.svg".to_string())) })
+            });
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let result = qr_service
+            .regenerate_qr(Some(TEST_TOKEN), TEST_SHORT_URL, QrOptions::default())
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn regenerate_qr_without_admin_token_returns_unauthorized() {
+        // Arrange
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository.expect_save().never();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client.expect_upload_image().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let missing = qr_service
+            .regenerate_qr(None, TEST_SHORT_URL, QrOptions::default())
+            .await;
+        let wrong = qr_service
+            .regenerate_qr(Some("wrong"), TEST_SHORT_URL, QrOptions::default())
+            .await;
+
+        // Assert
+        for result in [missing, wrong] {
+            assert_eq!(
+                result.unwrap_err(),
+                ApiError::Unauthorized("Invalid admin token")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn regenerate_qr_on_upload_error_queues_the_upload_and_returns_pending() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        qr_options_repository
+            .expect_save()
+            .withf(|_, _, queue_qr_upload| *queue_qr_upload)
+            .times(1)
+            .returning(|options, _, _| Box::pin(async { Ok(options) }));
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository.expect_complete().never();
+        qr_upload_repository
+            .expect_record_failure()
+            .with(eq(TEST_SHORT_URL), always(), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(S3Error)) }));
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let options = QrOptions {
+            size: Some(512),
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service
+            .regenerate_qr(Some(TEST_TOKEN), TEST_SHORT_URL, options)
+            .await;
+
+        // Assert
+        let response = result.unwrap();
+        assert_eq!(response.qr_status, QrStatus::Pending);
+        assert_eq!(
+            response.qr_code_image,
+            "http://localhost:8080/api/url/1234556/qr"
+        );
+    }
+
+    #[tokio::test]
+    async fn regenerate_qr_rejects_invalid_options() {
+        // Arrange
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        qr_options_repository.expect_save().never();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client.expect_upload_image().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let options = QrOptions {
+            foreground: Some("#FFFFFF".to_string()),
+            background: Some("#000000".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service
+            .regenerate_qr(Some(TEST_TOKEN), TEST_SHORT_URL, options)
+            .await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("The QR foreground must be darker than its background")
+        );
+    }
+
+    #[tokio::test]
+    async fn get_qr_image_renders_and_caches_on_miss() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let mut qr_image_cache = MockQrImageCacheTrait::new();
+        qr_image_cache.expect_get().returning(|_| None);
+        qr_image_cache.expect_put().times(1).return_const(());
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(qr_image_cache),
+        );
+        let query = QrImageQuery {
+            format: Some(QrFormat::Svg),
+            size: Some(512),
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service.get_qr_image(TEST_SHORT_URL, query).await;
+
+        // Assert
+        let image = result.unwrap();
+        assert_eq!(image.content_type, "image/svg+xml");
+        assert!(image.etag.starts_with('"') && image.etag.ends_with('"'));
+        assert!(String::from_utf8(image.body)
+            .unwrap()
+            .contains(r#"width="512""#));
+    }
+
+    #[tokio::test]
+    async fn get_qr_image_returns_cached_image() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let mut qr_image_cache = MockQrImageCacheTrait::new();
+        qr_image_cache
+            .expect_get()
+            .with(always())
+            .returning(|_| Some(vec![1, 2, 3]));
+        qr_image_cache.expect_put().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(qr_image_cache),
+        );
+
+        // Act
+        let result = qr_service
+            .get_qr_image(TEST_SHORT_URL, QrImageQuery::default())
+            .await;
+
+        // Assert
+        let image = result.unwrap();
+        assert_eq!(image.content_type, "image/png");
+        assert_eq!(image.body, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn get_qr_image_rejects_invalid_size() {
+        // Arrange
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let query = QrImageQuery {
+            format: None,
+            size: Some(10_000),
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service.get_qr_image(TEST_SHORT_URL, query).await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("The QR size must be between 64 and 4096 pixels")
+        );
+    }
+
+    #[tokio::test]
+    async fn upload_logo_keeps_options_and_forces_high_error_correction() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut logo = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(64, 64))
+            .write_to(&mut Cursor::new(&mut logo), ImageFormat::Png)
+            .unwrap();
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        qr_options_repository
+            .expect_save()
+            .withf(|options, changed_by, _| {
+                changed_by == "admin" && options.logo.is_some() && options.error_correction == "low"
+            })
+            .times(1)
+            .returning(|options, _, _| Box::pin(async { Ok(options) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("1234556.png"), always())
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        s3_client
+            .expect_image_url()
+            .with(eq("1234556.png"))
+            .returning(|_| {
+                Box::pin(async { Ok(Some("https://cdn.example.com/1234556.png".to_string())) })
+            });
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let result = qr_service
+            .upload_logo(Some(TEST_TOKEN), TEST_SHORT_URL, logo)
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn upload_and_remove_logo_without_admin_token_return_unauthorized() {
+        // Arrange
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository.expect_save().never();
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client.expect_upload_image().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let missing_upload = qr_service
+            .upload_logo(None, TEST_SHORT_URL, b"logo".to_vec())
+            .await;
+        let wrong_upload = qr_service
+            .upload_logo(Some("wrong"), TEST_SHORT_URL, b"logo".to_vec())
+            .await;
+        let missing_remove = qr_service.remove_logo(None, TEST_SHORT_URL).await;
+        let wrong_remove = qr_service.remove_logo(Some("wrong"), TEST_SHORT_URL).await;
+
+        // Assert
+        for result in [missing_upload, wrong_upload, missing_remove, wrong_remove] {
+            assert_eq!(
+                result.unwrap_err(),
+                ApiError::Unauthorized("Invalid admin token")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn upload_logo_rejects_invalid_image() {
+        // Arrange
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository.expect_save().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let result = qr_service
+            .upload_logo(Some(TEST_TOKEN), TEST_SHORT_URL, b"not an image".to_vec())
+            .await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("The logo must be a PNG or JPEG image of at most 256 KB")
+        );
+    }
+
+    #[tokio::test]
+    async fn upload_logo_rejects_other_symbologies() {
+        // Arrange
+        let mut logo = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(64, 64))
+            .write_to(&mut Cursor::new(&mut logo), ImageFormat::Png)
+            .unwrap();
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository.expect_find().returning(|_| {
+            Box::pin(async {
+                let settings = QrSettings {
+                    symbology: Symbology::Aztec,
+                    ..Default::default()
+                };
+                Ok(Some(settings.to_record(TEST_SHORT_URL)))
+            })
+        });
+        qr_options_repository.expect_save().never();
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let result = qr_service
+            .upload_logo(Some(TEST_TOKEN), TEST_SHORT_URL, logo)
+            .await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("Only QR codes can carry a logo")
+        );
+    }
+
+    #[tokio::test]
+    async fn export_qr_streams_a_zip_entry_per_code() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let request = QrExportRequest {
+            codes: vec![TEST_SHORT_URL.to_string(), TEST_SHORT_URL.to_string()],
+            format: Some(QrExportFormat::Zip),
+            image: Some(QrImageQuery {
+                format: Some(QrFormat::Svg),
+                ..Default::default()
+            }),
+        };
+
+        // Act
+        let chunks = qr_service
+            .export_qr(request)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        // Assert
+        // the duplicate is dropped, leaving one entry and the central directory
+        assert_eq!(chunks.len(), 2);
+        let entry = chunks[0].as_ref().unwrap();
+        assert_eq!(entry[..4], [0x50, 0x4b, 0x03, 0x04]);
+        assert_eq!(entry[30..41], *b"1234556.svg");
+        let directory = chunks[1].as_ref().unwrap();
+        assert_eq!(directory[..4], [0x50, 0x4b, 0x01, 0x02]);
+    }
+
+    #[tokio::test]
+    async fn export_qr_writes_a_pdf_label_sheet() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let qr_service = QrService::new(
+            Arc::new(url_repository()),
+            Arc::new(qr_options_repository),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let request = QrExportRequest {
+            codes: vec![TEST_SHORT_URL.to_string()],
+            format: Some(QrExportFormat::Pdf),
+            image: None,
+        };
+
+        // Act
+        let chunks = qr_service
+            .export_qr(request)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        // Assert
+        let document = chunks
+            .into_iter()
+            .map(|chunk| chunk.unwrap())
+            .collect::<Vec<_>>()
+            .concat();
+        let text = String::from_utf8_lossy(&document);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("(localhost:8080/1234556) Tj"));
+        assert!(text.contains("(https://www.google.com) Tj"));
+        assert!(text.ends_with("%%EOF\n"));
+    }
+
+    #[tokio::test]
+    async fn export_qr_rejects_unknown_codes() {
+        // Arrange
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let qr_service = QrService::new(
+            Arc::new(url_repository),
+            Arc::new(MockQrOptionsRepositoryTrait::new()),
+            Arc::new(qr_upload_repository()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+        let request = QrExportRequest {
+            codes: vec!["missing".to_string()],
+            ..Default::default()
+        };
+
+        // Act
+        let result = qr_service.export_qr(request).await;
+
+        // Assert
+        assert_eq!(
+            result.err().unwrap(),
+            ApiError::NotFound("The url with this format was not found")
+        );
+        assert_eq!(
+            qr_service
+                .export_qr(QrExportRequest::default())
+                .await
+                .err()
+                .unwrap(),
+            ApiError::BadRequest("A QR export must have between 1 and 1000 codes")
+        );
+    }
+
+    #[tokio::test]
+    async fn get_stored_image_serves_only_qr_images() {
+        // Arrange
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_get_image()
+            .with(eq("1234556.png"))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(Some(b"png".to_vec())) }));
+        s3_client
+            .expect_get_image()
+            .with(eq("abc.svg"))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let qr_service = QrService::new(
+            Arc::new(MockUrlRepositoryTrait::new()),
+            Arc::new(MockQrOptionsRepositoryTrait::new()),
+            Arc::new(qr_upload_repository()),
+            Arc::new(s3_client),
+            Arc::new(MockQrImageCacheTrait::new()),
+        );
+
+        // Act
+        let image = qr_service.get_stored_image("1234556.png").await.unwrap();
+
+        // Assert
+        assert_eq!(image.content_type, "image/png");
+        assert_eq!(image.body, b"png");
+        assert!(image.etag.starts_with('"'));
+        for file_name in ["abc.svg", "clicks/2025-03-01.parquet", "abc.txt"] {
+            assert_eq!(
+                qr_service.get_stored_image(file_name).await.err().unwrap(),
+                ApiError::NotFound("The QR image was not found")
+            );
+        }
+    }
+}
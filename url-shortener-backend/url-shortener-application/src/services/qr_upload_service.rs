@@ -0,0 +1,215 @@
+use crate::models::errors::ApiError;
+use crate::services::qr_service::reupload_stored_qr;
+use async_trait::async_trait;
+use coi::Inject;
+use log::{error, warn};
+use std::env;
+use std::sync::Arc;
+use url_shortener_database::repositories::qr_options_repository::QrOptionsRepositoryTrait;
+use url_shortener_database::repositories::qr_upload_repository::QrUploadRepositoryTrait;
+use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
+
+const UPLOAD_BATCH_SIZE: i64 = 100;
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+/// Doubles the wait after every failed attempt, up to an hour.
+pub(crate) fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = attempts.clamp(0, 16) as u32;
+    (BASE_RETRY_DELAY_SECS << exponent).min(MAX_RETRY_DELAY_SECS)
+}
+
+#[async_trait]
+pub trait QrUploadServiceTrait: Inject {
+    /// Uploads the QR images of links whose upload on creation failed and whose retry is due.
+    async fn upload_pending(&self) -> Result<usize, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrUploadServiceTrait with QrUploadService::new(qr_upload_repository, qr_options_repository, s3_client_wrapper))]
+struct QrUploadService {
+    #[coi(inject)]
+    qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
+    #[coi(inject)]
+    qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+    #[coi(inject)]
+    s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+}
+
+impl QrUploadService {
+    pub fn new(
+        qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
+        qr_options_repository: Arc<dyn QrOptionsRepositoryTrait>,
+        s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            qr_upload_repository,
+            qr_options_repository,
+            s3_client_wrapper,
+        }
+    }
+}
+
+#[async_trait]
+impl QrUploadServiceTrait for QrUploadService {
+    async fn upload_pending(&self) -> Result<usize, ApiError> {
+        let uploads = self
+            .qr_upload_repository
+            .find_due(UPLOAD_BATCH_SIZE)
+            .await
+            .map_err(|e| {
+                error!("Failed to read pending qr uploads: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+        if uploads.is_empty() {
+            return Ok(0);
+        }
+
+        let domain = env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
+        let mut uploaded = 0;
+        for upload in uploads {
+            let result = reupload_stored_qr(
+                self.qr_options_repository.as_ref(),
+                self.s3_client_wrapper.as_ref(),
+                &domain,
+                &upload.url_id,
+            )
+            .await;
+
+            let recorded = match result {
+                Ok(()) => {
+                    uploaded += 1;
+                    self.qr_upload_repository.complete(&upload.url_id).await
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to upload qr code for {} after {} attempts: {:?}",
+                        upload.url_id,
+                        upload.attempts + 1,
+                        e
+                    );
+                    self.qr_upload_repository
+                        .record_failure(
+                            &upload.url_id,
+                            format!("{:?}", e),
+                            retry_delay_secs(upload.attempts + 1),
+                        )
+                        .await
+                }
+            };
+            if let Err(e) = recorded {
+                error!(
+                    "Failed to update pending qr upload for {}: {:?}",
+                    upload.url_id, e
+                );
+            }
+        }
+
+        Ok(uploaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::qr_upload_service::{
+        retry_delay_secs, QrUploadService, QrUploadServiceTrait,
+    };
+    use chrono::Utc;
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::qr_upload_models::QrUpload;
+    use url_shortener_database::repositories::qr_options_repository::MockQrOptionsRepositoryTrait;
+    use url_shortener_database::repositories::qr_upload_repository::MockQrUploadRepositoryTrait;
+    use url_shortener_infrastructure::s3::error::S3Error;
+    use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
+
+    fn upload(url_id: &str, attempts: i32) -> QrUpload {
+        QrUpload {
+            url_id: url_id.to_string(),
+            attempts,
+            last_error: None,
+            next_attempt_at: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_an_hour() {
+        // Act & Assert
+        assert_eq!(retry_delay_secs(0), 30);
+        assert_eq!(retry_delay_secs(1), 60);
+        assert_eq!(retry_delay_secs(3), 240);
+        assert_eq!(retry_delay_secs(10), 3600);
+        assert_eq!(retry_delay_secs(i32::MAX), 3600);
+    }
+
+    #[tokio::test]
+    async fn upload_pending_completes_uploaded_and_backs_off_failed() {
+        // Arrange
+        env::set_var("APP_DOMAIN", "http://localhost:8080");
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_find_due()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(vec![upload("abc", 0), upload("abd", 2)]) }));
+        qr_upload_repository
+            .expect_complete()
+            .with(eq("abc"))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        qr_upload_repository
+            .expect_record_failure()
+            .with(eq("abd"), always(), eq(240))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        let mut qr_options_repository = MockQrOptionsRepositoryTrait::new();
+        qr_options_repository
+            .expect_find()
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let mut s3_client = MockS3ClientWrapperTrait::new();
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("abc.png"), eq("image/png"))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        s3_client
+            .expect_upload_image()
+            .with(always(), eq("abd.png"), eq("image/png"))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(S3Error)) }));
+        let service = QrUploadService::new(
+            Arc::new(qr_upload_repository),
+            Arc::new(qr_options_repository),
+            Arc::new(s3_client),
+        );
+
+        // Act
+        let uploaded = service.upload_pending().await.unwrap();
+
+        // Assert
+        assert_eq!(uploaded, 1);
+    }
+
+    #[tokio::test]
+    async fn upload_pending_without_due_uploads_does_nothing() {
+        // Arrange
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_find_due()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(Vec::new()) }));
+        let service = QrUploadService::new(
+            Arc::new(qr_upload_repository),
+            Arc::new(MockQrOptionsRepositoryTrait::new()),
+            Arc::new(MockS3ClientWrapperTrait::new()),
+        );
+
+        // Act
+        let uploaded = service.upload_pending().await.unwrap();
+
+        // Assert
+        assert_eq!(uploaded, 0);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::analytics::privacy::{retention_policy, RetentionPolicy};
+use crate::models::errors::ApiError;
+use async_trait::async_trait;
+use chrono::Utc;
+use coi::Inject;
+use log::error;
+use std::sync::Arc;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+
+const PURGE_BATCH_SIZE: i64 = 5_000;
+
+#[async_trait]
+pub trait RetentionServiceTrait: Inject {
+    /// Aggregates or deletes raw clicks older than the configured retention age.
+    async fn apply_retention(&self) -> Result<u64, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn RetentionServiceTrait with RetentionService::new(click_repository))]
+struct RetentionService {
+    #[coi(inject)]
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+}
+
+impl RetentionService {
+    pub fn new(click_repository: Arc<dyn ClickRepositoryTrait>) -> Self {
+        Self { click_repository }
+    }
+
+    async fn purge(&self, policy: RetentionPolicy) -> Result<u64, ApiError> {
+        let cutoff = Utc::now() - policy.max_age;
+
+        // small batches keep row locks short while the redirect path keeps inserting
+        let mut purged = 0;
+        loop {
+            let batch = self
+                .click_repository
+                .purge_before(cutoff, policy.aggregate, PURGE_BATCH_SIZE)
+                .await
+                .map_err(|e| {
+                    error!("Failed to purge clicks before {}: {:?}", cutoff, e);
+                    ApiError::InternalServerError
+                })?;
+            purged += batch;
+
+            if batch < PURGE_BATCH_SIZE as u64 {
+                return Ok(purged);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionServiceTrait for RetentionService {
+    async fn apply_retention(&self) -> Result<u64, ApiError> {
+        let Some(policy) = retention_policy() else {
+            return Ok(0);
+        };
+        self.purge(policy).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::privacy::RetentionPolicy;
+    use crate::models::errors::ApiError;
+    use chrono::{Duration, Utc};
+    use error_stack::Report;
+    use mockall::predicate::{always, eq, function};
+    use mockall::Sequence;
+    use std::sync::Arc;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+
+    const TEST_POLICY: RetentionPolicy = RetentionPolicy {
+        max_age: Duration::days(90),
+        aggregate: true,
+    };
+
+    #[tokio::test]
+    async fn purge_repeats_until_batch_is_partial() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut sequence = Sequence::new();
+        click_repository
+            .expect_purge_before()
+            .with(
+                function(|cutoff: &chrono::DateTime<Utc>| {
+                    *cutoff < Utc::now() - Duration::days(89)
+                }),
+                eq(true),
+                always(),
+            )
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, limit| Box::pin(async move { Ok(limit as u64) }));
+        click_repository
+            .expect_purge_before()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, _| Box::pin(async { Ok(3) }));
+        let retention_service = super::RetentionService::new(Arc::new(click_repository));
+
+        // Act
+        let result = retention_service.purge(TEST_POLICY).await;
+
+        // Assert
+        assert_eq!(result, Ok(super::PURGE_BATCH_SIZE as u64 + 3));
+    }
+
+    #[tokio::test]
+    async fn purge_on_database_returns_internal_server_error() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        click_repository
+            .expect_purge_before()
+            .returning(|_, _, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let retention_service = super::RetentionService::new(Arc::new(click_repository));
+
+        // Act
+        let result = retention_service.purge(TEST_POLICY).await;
+
+        // Assert
+        assert_eq!(result, Err(ApiError::InternalServerError));
+    }
+}
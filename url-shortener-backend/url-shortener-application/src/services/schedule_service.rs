@@ -0,0 +1,484 @@
+use crate::models::errors::ApiError;
+use crate::models::response_model::ScheduleResponseModel;
+use crate::models::url_models::CreateScheduleRequest;
+use crate::services::validation::{ensure_admin, ensure_url_exists, validate_url};
+use async_trait::async_trait;
+use chrono::Utc;
+use coi::Inject;
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use url_shortener_database::repositories::schedule_repository::ScheduleRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+const APPLY_BATCH_SIZE: i64 = 100;
+const CACHE_INVALIDATION_ATTEMPTS: u32 = 3;
+const CACHE_INVALIDATION_BACKOFF_MS: u64 = 100;
+
+#[async_trait]
+pub trait ScheduleServiceTrait: Inject {
+    /// Changing where a link goes is an admin task, like every other rewrite of a link.
+    async fn create_schedule(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        create_schedule_request: CreateScheduleRequest,
+    ) -> Result<ScheduleResponseModel, ApiError>;
+    async fn get_pending_schedules(
+        &self,
+        short_url: &str,
+    ) -> Result<Vec<ScheduleResponseModel>, ApiError>;
+    async fn cancel_schedule(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        id: i64,
+    ) -> Result<(), ApiError>;
+    async fn apply_due_schedules(&self) -> Result<usize, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ScheduleServiceTrait with ScheduleService::new(schedule_repository, url_repository, redis_client_wrapper))]
+struct ScheduleService {
+    #[coi(inject)]
+    schedule_repository: Arc<dyn ScheduleRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl ScheduleService {
+    pub fn new(
+        schedule_repository: Arc<dyn ScheduleRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            schedule_repository,
+            url_repository,
+            redis_client_wrapper,
+        }
+    }
+
+    async fn invalidate_url_cache(&self, short_url: &str) -> bool {
+        for attempt in 1..=CACHE_INVALIDATION_ATTEMPTS {
+            match self.redis_client_wrapper.delete_cache(short_url).await {
+                Ok(()) => return true,
+                Err(e) => warn!("Failed to invalidate url cache, attempt {}: {}", attempt, e),
+            }
+            if attempt < CACHE_INVALIDATION_ATTEMPTS {
+                let backoff = CACHE_INVALIDATION_BACKOFF_MS << (attempt - 1);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+            }
+        }
+        false
+    }
+}
+
+#[async_trait]
+impl ScheduleServiceTrait for ScheduleService {
+    async fn create_schedule(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        create_schedule_request: CreateScheduleRequest,
+    ) -> Result<ScheduleResponseModel, ApiError> {
+        ensure_admin(token)?;
+        validate_url(&create_schedule_request.url)?;
+
+        if create_schedule_request.scheduled_at <= Utc::now() {
+            warn!(
+                "Schedule time is in the past: {:?}",
+                create_schedule_request.scheduled_at
+            );
+            return Err(ApiError::BadRequest("Scheduled time must be in the future"));
+        }
+
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let schedule = self
+            .schedule_repository
+            .create(
+                short_url,
+                &create_schedule_request.url,
+                create_schedule_request.scheduled_at,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to create schedule: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        Ok(schedule.into())
+    }
+
+    async fn get_pending_schedules(
+        &self,
+        short_url: &str,
+    ) -> Result<Vec<ScheduleResponseModel>, ApiError> {
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let schedules = self
+            .schedule_repository
+            .find_pending(short_url)
+            .await
+            .map_err(|e| {
+                error!("Failed to get schedules: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        Ok(schedules.into_iter().map(Into::into).collect())
+    }
+
+    async fn cancel_schedule(
+        &self,
+        token: Option<&str>,
+        short_url: &str,
+        id: i64,
+    ) -> Result<(), ApiError> {
+        ensure_admin(token)?;
+
+        let deleted = self
+            .schedule_repository
+            .delete_pending(short_url, id)
+            .await
+            .map_err(|e| {
+                error!("Failed to cancel schedule: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        if !deleted {
+            warn!("Pending schedule not found: {} - {}", short_url, id);
+            return Err(ApiError::NotFound("The schedule was not found"));
+        }
+
+        Ok(())
+    }
+
+    async fn apply_due_schedules(&self) -> Result<usize, ApiError> {
+        let applied = self
+            .schedule_repository
+            .apply_due(Utc::now(), APPLY_BATCH_SIZE)
+            .await
+            .map_err(|e| {
+                error!("Failed to apply schedules: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        let mut stale = Vec::new();
+        for schedule in &applied {
+            info!(
+                "Applied schedule {} for url {}: {}",
+                schedule.id, schedule.url_id, schedule.destination
+            );
+            if !self.invalidate_url_cache(&schedule.url_id).await {
+                stale.push(schedule.url_id.as_str());
+            }
+        }
+
+        // the schedules are applied either way, a stale destination lasts until its cache expires
+        if !stale.is_empty() {
+            error!(
+                "Failed to invalidate url cache after schedules: {:?}",
+                stale
+            );
+            return Err(ApiError::InternalServerError);
+        }
+
+        Ok(applied.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::url_models::CreateScheduleRequest;
+    use crate::services::schedule_service::ScheduleServiceTrait;
+    use chrono::{Duration, Utc};
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use mockall::Sequence;
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::models::schedule_models::UrlSchedule;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::schedule_repository::MockScheduleRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::redis::error::CacheError;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_TOKEN: &str = "admin-secret";
+
+    fn setup_mocks() -> (
+        MockScheduleRepositoryTrait,
+        MockUrlRepositoryTrait,
+        MockRedisClientWrapperTrait,
+    ) {
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        let schedule_repository = MockScheduleRepositoryTrait::new();
+        let url_repository = MockUrlRepositoryTrait::new();
+        let redis_client = MockRedisClientWrapperTrait::new();
+        (schedule_repository, url_repository, redis_client)
+    }
+
+    fn test_schedule() -> UrlSchedule {
+        UrlSchedule {
+            id: 1,
+            url_id: TEST_SHORT_URL.to_string(),
+            destination: TEST_VALID_URL.to_string(),
+            scheduled_at: Utc::now(),
+            applied_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_schedule_in_past_returns_bad_request() {
+        // Arrange
+        let (schedule_repository, url_repository, redis_client) = setup_mocks();
+        let request = CreateScheduleRequest {
+            url: TEST_VALID_URL.to_string(),
+            scheduled_at: Utc::now() - Duration::hours(1),
+        };
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service
+            .create_schedule(Some(TEST_TOKEN), TEST_SHORT_URL, request)
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("Scheduled time must be in the future")
+        );
+    }
+
+    #[tokio::test]
+    async fn create_schedule_unknown_url_returns_not_found() {
+        // Arrange
+        let (schedule_repository, mut url_repository, redis_client) = setup_mocks();
+        url_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Ok(None) }));
+        let request = CreateScheduleRequest {
+            url: TEST_VALID_URL.to_string(),
+            scheduled_at: Utc::now() + Duration::hours(1),
+        };
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service
+            .create_schedule(Some(TEST_TOKEN), TEST_SHORT_URL, request)
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The url with this format was not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn create_schedule_returns_ok() {
+        // Arrange
+        let (mut schedule_repository, mut url_repository, redis_client) = setup_mocks();
+        url_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Some(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    }))
+                })
+            });
+        schedule_repository
+            .expect_create()
+            .with(eq(TEST_SHORT_URL), eq(TEST_VALID_URL), always())
+            .returning(|_, _, _| Box::pin(async { Ok(test_schedule()) }));
+        let request = CreateScheduleRequest {
+            url: TEST_VALID_URL.to_string(),
+            scheduled_at: Utc::now() + Duration::hours(1),
+        };
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service
+            .create_schedule(Some(TEST_TOKEN), TEST_SHORT_URL, request)
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().url, TEST_VALID_URL);
+    }
+
+    #[tokio::test]
+    async fn cancel_schedule_missing_returns_not_found() {
+        // Arrange
+        let (mut schedule_repository, url_repository, redis_client) = setup_mocks();
+        schedule_repository
+            .expect_delete_pending()
+            .with(eq(TEST_SHORT_URL), eq(1))
+            .returning(|_, _| Box::pin(async { Ok(false) }));
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service
+            .cancel_schedule(Some(TEST_TOKEN), TEST_SHORT_URL, 1)
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::NotFound("The schedule was not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn create_and_cancel_schedule_without_admin_token_return_unauthorized() {
+        // Arrange
+        let (mut schedule_repository, url_repository, redis_client) = setup_mocks();
+        schedule_repository.expect_create().never();
+        schedule_repository.expect_delete_pending().never();
+        let request = || CreateScheduleRequest {
+            url: TEST_VALID_URL.to_string(),
+            scheduled_at: Utc::now() + Duration::hours(1),
+        };
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let missing = schedule_service
+            .create_schedule(None, TEST_SHORT_URL, request())
+            .await;
+        let wrong = schedule_service
+            .create_schedule(Some("wrong"), TEST_SHORT_URL, request())
+            .await;
+        let cancel = schedule_service
+            .cancel_schedule(Some("wrong"), TEST_SHORT_URL, 1)
+            .await;
+
+        // Assert
+        for result in [missing.map(|_| ()), wrong.map(|_| ()), cancel] {
+            assert_eq!(
+                result.unwrap_err(),
+                ApiError::Unauthorized("Invalid admin token")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn apply_due_schedules_retries_cache_invalidation() {
+        // Arrange
+        let (mut schedule_repository, url_repository, mut redis_client) = setup_mocks();
+        let mut sequence = Sequence::new();
+        schedule_repository
+            .expect_apply_due()
+            .with(always(), always())
+            .returning(|_, _| Box::pin(async { Ok(vec![test_schedule()]) }));
+        redis_client
+            .expect_delete_cache()
+            .with(eq(TEST_SHORT_URL))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError {})) }));
+        redis_client
+            .expect_delete_cache()
+            .with(eq(TEST_SHORT_URL))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service.apply_due_schedules().await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn apply_due_schedules_on_cache_returns_internal_server_error() {
+        // Arrange
+        let (mut schedule_repository, url_repository, mut redis_client) = setup_mocks();
+        schedule_repository
+            .expect_apply_due()
+            .with(always(), always())
+            .returning(|_, _| Box::pin(async { Ok(vec![test_schedule()]) }));
+        redis_client
+            .expect_delete_cache()
+            .with(eq(TEST_SHORT_URL))
+            .times(3)
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError {})) }));
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service.apply_due_schedules().await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+
+    #[tokio::test]
+    async fn apply_due_schedules_on_database_returns_internal_server_error() {
+        // Arrange
+        let (mut schedule_repository, url_repository, redis_client) = setup_mocks();
+        schedule_repository
+            .expect_apply_due()
+            .with(always(), always())
+            .returning(|_, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        let schedule_service = super::ScheduleService::new(
+            Arc::new(schedule_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = schedule_service.apply_due_schedules().await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+}
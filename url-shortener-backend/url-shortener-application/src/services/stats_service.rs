@@ -0,0 +1,724 @@
+use crate::analytics::counters::unflushed_counter_key;
+use crate::analytics::referrer::{classify_referrer, referrer_domain};
+use crate::analytics::user_agent::parse_user_agent;
+use crate::analytics::visitors::{days_in_range, unique_visitors_key};
+use crate::models::click_models::ClickSource;
+use crate::models::errors::ApiError;
+use crate::models::stats_models::{
+    BreakdownStatsModel, CityStatsModel, ConversionStatsModel, DailyVisitorsModel,
+    PeriodStatsModel, ReferrerStatsModel, StatsInterval, StatsQuery, StatsResponseModel,
+};
+use crate::services::validation::ensure_url_exists;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use coi::Inject;
+use log::{error, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+use url_shortener_database::models::click_models::{LocationCount, ValueCount};
+use url_shortener_database::repositories::click_count_repository::ClickCountRepositoryTrait;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+use url_shortener_database::repositories::conversion_repository::ConversionRepositoryTrait;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const MAX_RANGE_DAYS: i64 = 366;
+const MAX_HOURLY_RANGE_DAYS: i64 = 31;
+const TOP_REFERRERS: usize = 10;
+const TOP_CITIES: usize = 10;
+const UNKNOWN_COUNTRY: &str = "Unknown";
+
+#[async_trait]
+pub trait StatsServiceTrait: Inject {
+    async fn get_stats(
+        &self,
+        short_url: &str,
+        stats_query: StatsQuery,
+    ) -> Result<StatsResponseModel, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn StatsServiceTrait with StatsService::new(click_repository, click_count_repository, conversion_repository, url_repository, redis_client_wrapper))]
+struct StatsService {
+    #[coi(inject)]
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+    #[coi(inject)]
+    click_count_repository: Arc<dyn ClickCountRepositoryTrait>,
+    #[coi(inject)]
+    conversion_repository: Arc<dyn ConversionRepositoryTrait>,
+    #[coi(inject)]
+    url_repository: Arc<dyn UrlRepositoryTrait>,
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl StatsService {
+    pub fn new(
+        click_repository: Arc<dyn ClickRepositoryTrait>,
+        click_count_repository: Arc<dyn ClickCountRepositoryTrait>,
+        conversion_repository: Arc<dyn ConversionRepositoryTrait>,
+        url_repository: Arc<dyn UrlRepositoryTrait>,
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+    ) -> Self {
+        Self {
+            click_repository,
+            click_count_repository,
+            conversion_repository,
+            url_repository,
+            redis_client_wrapper,
+        }
+    }
+
+    /// Postgres has every flushed count, the clicks Redis still counts as unflushed for the link
+    /// are added on top.
+    async fn lifetime_clicks(&self, short_url: &str) -> Option<i64> {
+        // Redis is read first, so a flush released in between is counted twice rather than missed
+        let unflushed = self
+            .redis_client_wrapper
+            .get_counter(&unflushed_counter_key(short_url))
+            .await
+            .map_err(|e| warn!("Failed to get unflushed clicks: {:?}", e))
+            .unwrap_or_default();
+        let stored = self
+            .click_count_repository
+            .total(short_url)
+            .await
+            .map_err(|e| warn!("Failed to get lifetime clicks: {:?}", e))
+            .ok()?;
+
+        Some(stored + unflushed.max(0))
+    }
+
+    async fn unique_visitors(
+        &self,
+        short_url: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> (Option<u64>, Vec<DailyVisitorsModel>) {
+        let days = days_in_range(from, to);
+        let keys: Vec<String> = days
+            .iter()
+            .map(|day| unique_visitors_key(short_url, *day))
+            .collect();
+
+        // PFCOUNT over several keys counts the union, so repeat visitors across days are counted once
+        let (total, daily) = tokio::join!(
+            self.redis_client_wrapper.count_hyperloglog(keys.clone()),
+            self.redis_client_wrapper.count_each_hyperloglog(keys),
+        );
+
+        let total = total
+            .map_err(|e| warn!("Failed to count unique visitors: {}", e))
+            .ok();
+        let daily = daily
+            .map(|counts| {
+                days.into_iter()
+                    .zip(counts)
+                    .map(|(day, visitors)| DailyVisitorsModel { day, visitors })
+                    .collect()
+            })
+            .unwrap_or_else(|e| {
+                warn!("Failed to count daily unique visitors: {}", e);
+                Vec::new()
+            });
+
+        (total, daily)
+    }
+
+    fn top_referrers(counts: Vec<ValueCount>) -> Vec<ReferrerStatsModel> {
+        let mut by_domain: HashMap<Option<String>, i64> = HashMap::new();
+        for count in counts {
+            let domain = count.value.as_deref().and_then(referrer_domain);
+            *by_domain.entry(domain).or_default() += count.count;
+        }
+
+        let mut referrers: Vec<ReferrerStatsModel> = by_domain
+            .into_iter()
+            .map(|(domain, clicks)| ReferrerStatsModel {
+                category: classify_referrer(domain.as_deref()),
+                domain,
+                clicks,
+            })
+            .collect();
+        referrers.sort_by(|a, b| {
+            b.clicks
+                .cmp(&a.clicks)
+                .then_with(|| a.domain.cmp(&b.domain))
+        });
+        referrers.truncate(TOP_REFERRERS);
+        referrers
+    }
+
+    fn user_agent_breakdowns(
+        counts: Vec<ValueCount>,
+    ) -> (
+        Vec<BreakdownStatsModel>,
+        Vec<BreakdownStatsModel>,
+        Vec<BreakdownStatsModel>,
+    ) {
+        let mut browsers = HashMap::new();
+        let mut operating_systems = HashMap::new();
+        let mut devices = HashMap::new();
+
+        for count in counts {
+            let info = parse_user_agent(count.value.as_deref());
+            *browsers.entry(info.browser).or_default() += count.count;
+            *operating_systems.entry(info.os).or_default() += count.count;
+            *devices.entry(info.device).or_default() += count.count;
+        }
+
+        (
+            Self::sorted_breakdown(browsers),
+            Self::sorted_breakdown(operating_systems),
+            Self::sorted_breakdown(devices),
+        )
+    }
+
+    /// Splits the clicks into visits through the printed QR code and everything else.
+    fn source_totals(counts: Vec<ValueCount>) -> (i64, i64) {
+        counts
+            .into_iter()
+            .fold((0, 0), |(direct, qr), count| match count.value.as_deref() {
+                Some(source) if source == ClickSource::Qr.as_str() => (direct, qr + count.count),
+                _ => (direct + count.count, qr),
+            })
+    }
+
+    fn location_breakdowns(
+        counts: Vec<LocationCount>,
+    ) -> (Vec<BreakdownStatsModel>, Vec<CityStatsModel>) {
+        let mut countries = HashMap::new();
+        let mut cities = Vec::new();
+
+        for count in counts {
+            let country = count
+                .country
+                .clone()
+                .unwrap_or_else(|| UNKNOWN_COUNTRY.to_string());
+            *countries.entry(country).or_default() += count.count;
+            if let Some(city) = count.city {
+                cities.push(CityStatsModel {
+                    country: count.country,
+                    region: count.region,
+                    city,
+                    clicks: count.count,
+                });
+            }
+        }
+
+        cities.sort_by(|a, b| b.clicks.cmp(&a.clicks).then_with(|| a.city.cmp(&b.city)));
+        cities.truncate(TOP_CITIES);
+        (Self::sorted_breakdown(countries), cities)
+    }
+
+    fn sorted_breakdown(counts: HashMap<String, i64>) -> Vec<BreakdownStatsModel> {
+        let mut breakdown: Vec<BreakdownStatsModel> = counts
+            .into_iter()
+            .map(|(name, clicks)| BreakdownStatsModel { name, clicks })
+            .collect();
+        breakdown.sort_by(|a, b| b.clicks.cmp(&a.clicks).then_with(|| a.name.cmp(&b.name)));
+        breakdown
+    }
+}
+
+#[async_trait]
+impl StatsServiceTrait for StatsService {
+    async fn get_stats(
+        &self,
+        short_url: &str,
+        stats_query: StatsQuery,
+    ) -> Result<StatsResponseModel, ApiError> {
+        let to = stats_query.to.unwrap_or_else(Utc::now);
+        let from = stats_query
+            .from
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_RANGE_DAYS));
+        let interval = stats_query.interval.unwrap_or_default();
+        let timezone = stats_query.timezone.unwrap_or_else(|| "UTC".to_string());
+        let include_bots = stats_query.include_bots.unwrap_or_default();
+
+        let tz: Tz = timezone.parse().map_err(|e| {
+            warn!("Invalid timezone {:?}: {:?}", timezone, e);
+            ApiError::BadRequest("Invalid timezone")
+        })?;
+
+        if from >= to {
+            warn!("Invalid stats range: {} - {}", from, to);
+            return Err(ApiError::BadRequest(
+                "The start of the range must be before its end",
+            ));
+        }
+
+        let max_range = match interval {
+            StatsInterval::Hour => Duration::days(MAX_HOURLY_RANGE_DAYS),
+            _ => Duration::days(MAX_RANGE_DAYS),
+        };
+        if to - from > max_range {
+            warn!(
+                "Stats range too large: {} - {} by {}",
+                from,
+                to,
+                interval.as_str()
+            );
+            return Err(ApiError::BadRequest("The requested range is too large"));
+        }
+
+        ensure_url_exists(self.url_repository.as_ref(), short_url).await?;
+
+        let (
+            (unique_visitors, daily_unique_visitors),
+            lifetime_clicks,
+            total,
+            series,
+            referrers,
+            user_agents,
+            locations,
+            sources,
+            conversion_events,
+            converted_clicks,
+        ) = tokio::join!(
+            self.unique_visitors(short_url, from, to),
+            self.lifetime_clicks(short_url),
+            self.click_repository
+                .count(short_url, from, to, include_bots),
+            self.click_repository.count_by_period(
+                short_url,
+                from,
+                to,
+                interval.as_str(),
+                tz.name(),
+                include_bots
+            ),
+            self.click_repository
+                .count_by_referrer(short_url, from, to, include_bots),
+            self.click_repository
+                .count_by_user_agent(short_url, from, to, include_bots),
+            self.click_repository
+                .count_by_location(short_url, from, to, include_bots),
+            self.click_repository
+                .count_by_source(short_url, from, to, include_bots),
+            self.conversion_repository
+                .count_by_event(short_url, from, to),
+            self.conversion_repository
+                .count_converted_clicks(short_url, from, to),
+        );
+        let map_error = |e| {
+            error!("Failed to get stats: {:?}", e);
+            ApiError::InternalServerError
+        };
+        let (total, series, referrers, user_agents, locations, sources) = (
+            total.map_err(map_error)?,
+            series.map_err(map_error)?,
+            referrers.map_err(map_error)?,
+            user_agents.map_err(map_error)?,
+            locations.map_err(map_error)?,
+            sources.map_err(map_error)?,
+        );
+        let (conversion_events, converted_clicks) = (
+            conversion_events.map_err(map_error)?,
+            converted_clicks.map_err(map_error)?,
+        );
+
+        let (browsers, operating_systems, devices) = Self::user_agent_breakdowns(user_agents);
+        let (countries, cities) = Self::location_breakdowns(locations);
+        let (direct_clicks, qr_scans) = Self::source_totals(sources);
+
+        Ok(StatsResponseModel {
+            total_clicks: total,
+            direct_clicks,
+            qr_scans,
+            lifetime_clicks,
+            unique_visitors,
+            daily_unique_visitors,
+            from,
+            to,
+            interval,
+            timezone: tz.name().to_string(),
+            include_bots,
+            series: series
+                .into_iter()
+                .map(|p| PeriodStatsModel {
+                    period_start: p.period_start.with_timezone(&tz),
+                    clicks: p.count,
+                })
+                .collect(),
+            referrers: Self::top_referrers(referrers),
+            browsers,
+            operating_systems,
+            devices,
+            countries,
+            cities,
+            conversions: converted_clicks,
+            // a share of the clicks in range, so it is only meaningful once there are clicks
+            conversion_rate: (total > 0).then(|| converted_clicks as f64 / total as f64),
+            conversion_events: conversion_events
+                .into_iter()
+                .map(|c| ConversionStatsModel {
+                    event: c.event,
+                    conversions: c.count,
+                    value: c.value,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::stats_models::{
+        BreakdownStatsModel, ReferrerCategory, StatsInterval, StatsQuery,
+    };
+    use crate::services::stats_service::StatsServiceTrait;
+    use chrono::{Duration, Utc};
+    use error_stack::Report;
+    use mockall::predicate::{always, eq};
+    use std::sync::Arc;
+    use url_shortener_database::models::click_models::{LocationCount, PeriodCount, ValueCount};
+    use url_shortener_database::models::conversion_models::ConversionCount;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::click_count_repository::MockClickCountRepositoryTrait;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+    use url_shortener_database::repositories::conversion_repository::MockConversionRepositoryTrait;
+    use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+    const TEST_VALID_URL: &str = "https://www.google.com";
+    const TEST_CHROME_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    fn setup_mocks() -> (
+        MockClickRepositoryTrait,
+        MockUrlRepositoryTrait,
+        MockRedisClientWrapperTrait,
+    ) {
+        let click_repository = MockClickRepositoryTrait::new();
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_get_counter()
+            .with(eq("click_count_unflushed:1234556"))
+            .returning(|_| Box::pin(async { Ok(3) }));
+        let mut url_repository = MockUrlRepositoryTrait::new();
+        url_repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Some(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    }))
+                })
+            });
+        (click_repository, url_repository, redis_client)
+    }
+
+    /// Seven clicks are flushed, three more are still counted in Redis.
+    fn click_count_repository() -> MockClickCountRepositoryTrait {
+        let mut click_count_repository = MockClickCountRepositoryTrait::new();
+        click_count_repository
+            .expect_total()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Ok(7) }));
+        click_count_repository
+    }
+
+    #[tokio::test]
+    async fn get_stats_invalid_timezone_returns_bad_request() {
+        // Arrange
+        let (click_repository, url_repository, redis_client) = setup_mocks();
+        let conversion_repository = MockConversionRepositoryTrait::new();
+        let stats_service = super::StatsService::new(
+            Arc::new(click_repository),
+            Arc::new(click_count_repository()),
+            Arc::new(conversion_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+        let query = StatsQuery {
+            timezone: Some("Mars/Olympus".to_string()),
+            ..Default::default()
+        };
+
+        // Act
+        let result = stats_service.get_stats(TEST_SHORT_URL, query).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("Invalid timezone")
+        );
+    }
+
+    #[tokio::test]
+    async fn get_stats_hourly_range_too_large_returns_bad_request() {
+        // Arrange
+        let (click_repository, url_repository, redis_client) = setup_mocks();
+        let conversion_repository = MockConversionRepositoryTrait::new();
+        let stats_service = super::StatsService::new(
+            Arc::new(click_repository),
+            Arc::new(click_count_repository()),
+            Arc::new(conversion_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+        let query = StatsQuery {
+            from: Some(Utc::now() - Duration::days(60)),
+            interval: Some(StatsInterval::Hour),
+            ..Default::default()
+        };
+
+        // Act
+        let result = stats_service.get_stats(TEST_SHORT_URL, query).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("The requested range is too large")
+        );
+    }
+
+    #[tokio::test]
+    async fn get_stats_returns_aggregations() {
+        // Arrange
+        let (mut click_repository, url_repository, mut redis_client) = setup_mocks();
+        redis_client
+            .expect_count_hyperloglog()
+            .returning(|_| Box::pin(async { Ok(2) }));
+        redis_client
+            .expect_count_each_hyperloglog()
+            .returning(|keys| {
+                let counts = vec![1; keys.len()];
+                Box::pin(async move { Ok(counts) })
+            });
+        click_repository
+            .expect_count()
+            .with(eq(TEST_SHORT_URL), always(), always(), eq(true))
+            .returning(|_, _, _, _| Box::pin(async { Ok(4) }));
+        click_repository
+            .expect_count_by_period()
+            .with(
+                eq(TEST_SHORT_URL),
+                always(),
+                always(),
+                eq("day"),
+                eq("Europe/Bucharest"),
+                eq(true),
+            )
+            .returning(|_, _, _, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![PeriodCount {
+                        period_start: Utc::now(),
+                        count: 4,
+                    }])
+                })
+            });
+        click_repository
+            .expect_count_by_referrer()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![
+                        ValueCount {
+                            value: Some("https://www.facebook.com/".to_string()),
+                            count: 1,
+                        },
+                        ValueCount {
+                            value: Some("https://l.facebook.com/l.php".to_string()),
+                            count: 2,
+                        },
+                        ValueCount {
+                            value: None,
+                            count: 1,
+                        },
+                    ])
+                })
+            });
+        click_repository
+            .expect_count_by_user_agent()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![
+                        ValueCount {
+                            value: Some(TEST_CHROME_UA.to_string()),
+                            count: 3,
+                        },
+                        ValueCount {
+                            value: None,
+                            count: 1,
+                        },
+                    ])
+                })
+            });
+        click_repository
+            .expect_count_by_location()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![
+                        LocationCount {
+                            country: Some("RO".to_string()),
+                            region: Some("B".to_string()),
+                            city: Some("Bucharest".to_string()),
+                            count: 2,
+                        },
+                        LocationCount {
+                            country: Some("RO".to_string()),
+                            region: Some("CJ".to_string()),
+                            city: Some("Cluj-Napoca".to_string()),
+                            count: 1,
+                        },
+                        LocationCount {
+                            country: None,
+                            region: None,
+                            city: None,
+                            count: 1,
+                        },
+                    ])
+                })
+            });
+        click_repository
+            .expect_count_by_source()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![
+                        ValueCount {
+                            value: Some("link".to_string()),
+                            count: 3,
+                        },
+                        ValueCount {
+                            value: Some("qr".to_string()),
+                            count: 1,
+                        },
+                    ])
+                })
+            });
+        let mut conversion_repository = MockConversionRepositoryTrait::new();
+        conversion_repository
+            .expect_count_by_event()
+            .with(eq(TEST_SHORT_URL), always(), always())
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(vec![ConversionCount {
+                        event: "signup".to_string(),
+                        count: 2,
+                        value: Some(19.98),
+                    }])
+                })
+            });
+        conversion_repository
+            .expect_count_converted_clicks()
+            .returning(|_, _, _| Box::pin(async { Ok(1) }));
+        let stats_service = super::StatsService::new(
+            Arc::new(click_repository),
+            Arc::new(click_count_repository()),
+            Arc::new(conversion_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+        let query = StatsQuery {
+            timezone: Some("Europe/Bucharest".to_string()),
+            include_bots: Some(true),
+            ..Default::default()
+        };
+
+        // Act
+        let result = stats_service.get_stats(TEST_SHORT_URL, query).await;
+
+        // Assert
+        assert!(result.is_ok());
+        let stats = result.unwrap();
+        assert_eq!(stats.total_clicks, 4);
+        assert_eq!(stats.direct_clicks, 3);
+        assert_eq!(stats.qr_scans, 1);
+        assert_eq!(stats.conversions, 1);
+        assert_eq!(stats.conversion_rate, Some(0.25));
+        assert_eq!(stats.conversion_events[0].conversions, 2);
+        assert!(stats.include_bots);
+        assert_eq!(stats.lifetime_clicks, Some(10));
+        assert_eq!(stats.unique_visitors, Some(2));
+        assert_eq!(stats.daily_unique_visitors.len(), 31);
+        assert_eq!(stats.series.len(), 1);
+        assert_eq!(stats.referrers[0].domain.as_deref(), Some("facebook.com"));
+        assert_eq!(stats.referrers[0].category, ReferrerCategory::Social);
+        assert_eq!(stats.referrers[0].clicks, 3);
+        assert_eq!(
+            stats.browsers[0],
+            BreakdownStatsModel {
+                name: "Chrome".to_string(),
+                clicks: 3
+            }
+        );
+        assert_eq!(stats.devices[0].name, "Desktop");
+        assert_eq!(
+            stats.countries[0],
+            BreakdownStatsModel {
+                name: "RO".to_string(),
+                clicks: 3
+            }
+        );
+        assert_eq!(stats.countries[1].name, "Unknown");
+        assert_eq!(stats.cities.len(), 2);
+        assert_eq!(stats.cities[0].city, "Bucharest");
+    }
+
+    #[tokio::test]
+    async fn get_stats_on_database_returns_internal_server_error() {
+        // Arrange
+        let (mut click_repository, url_repository, mut redis_client) = setup_mocks();
+        redis_client
+            .expect_count_hyperloglog()
+            .returning(|_| Box::pin(async { Ok(2) }));
+        redis_client
+            .expect_count_each_hyperloglog()
+            .returning(|keys| {
+                let counts = vec![1; keys.len()];
+                Box::pin(async move { Ok(counts) })
+            });
+        click_repository
+            .expect_count()
+            .returning(|_, _, _, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+        click_repository
+            .expect_count_by_period()
+            .returning(|_, _, _, _, _, _| Box::pin(async { Ok(vec![]) }));
+        click_repository
+            .expect_count_by_referrer()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        click_repository
+            .expect_count_by_user_agent()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        click_repository
+            .expect_count_by_location()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        click_repository
+            .expect_count_by_source()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+        let mut conversion_repository = MockConversionRepositoryTrait::new();
+        conversion_repository
+            .expect_count_by_event()
+            .returning(|_, _, _| Box::pin(async { Ok(vec![]) }));
+        conversion_repository
+            .expect_count_converted_clicks()
+            .returning(|_, _, _| Box::pin(async { Ok(0) }));
+        let stats_service = super::StatsService::new(
+            Arc::new(click_repository),
+            Arc::new(click_count_repository()),
+            Arc::new(conversion_repository),
+            Arc::new(url_repository),
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = stats_service
+            .get_stats(TEST_SHORT_URL, StatsQuery::default())
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::analytics::leaderboard::{ranking_key, trending_weights, window_keys};
+use crate::models::errors::ApiError;
+use crate::models::top_models::{
+    TopLinkModel, TopLinksQuery, TopLinksResponseModel, TrendingLinkModel, TrendingLinksQuery,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use coi::Inject;
+use log::{error, warn};
+use std::sync::Arc;
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+const TRENDING_RANKING: &str = "trending";
+
+#[async_trait]
+pub trait TopLinksServiceTrait: Inject {
+    async fn get_top_links(
+        &self,
+        top_links_query: TopLinksQuery,
+    ) -> Result<TopLinksResponseModel, ApiError>;
+    async fn get_trending_links(
+        &self,
+        trending_links_query: TrendingLinksQuery,
+    ) -> Result<Vec<TrendingLinkModel>, ApiError>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn TopLinksServiceTrait with TopLinksService::new(redis_client_wrapper))]
+struct TopLinksService {
+    #[coi(inject)]
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
+}
+
+impl TopLinksService {
+    pub fn new(redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>) -> Self {
+        Self {
+            redis_client_wrapper,
+        }
+    }
+
+    fn validate_limit(limit: Option<usize>) -> Result<usize, ApiError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 || limit > MAX_LIMIT {
+            warn!("Invalid top links limit: {}", limit);
+            return Err(ApiError::BadRequest("The limit must be between 1 and 100"));
+        }
+        Ok(limit)
+    }
+
+    async fn rank(
+        &self,
+        keys: Vec<(String, f64)>,
+        name: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, f64)>, ApiError> {
+        self.redis_client_wrapper
+            .top_of_sorted_sets(keys, &ranking_key(name), limit)
+            .await
+            .map_err(|e| {
+                error!("Failed to rank links by {}: {:?}", name, e);
+                ApiError::InternalServerError
+            })
+    }
+}
+
+#[async_trait]
+impl TopLinksServiceTrait for TopLinksService {
+    async fn get_top_links(
+        &self,
+        top_links_query: TopLinksQuery,
+    ) -> Result<TopLinksResponseModel, ApiError> {
+        let limit = Self::validate_limit(top_links_query.limit)?;
+        let window = top_links_query.window.unwrap_or_default();
+
+        let keys = window_keys(window, Utc::now())
+            .into_iter()
+            .map(|key| (key, 1.0))
+            .collect();
+        let ranked = self.rank(keys, window.as_str(), limit).await?;
+
+        Ok(TopLinksResponseModel {
+            window,
+            links: ranked
+                .into_iter()
+                .map(|(code, clicks)| TopLinkModel {
+                    code,
+                    clicks: clicks.round() as i64,
+                })
+                .collect(),
+        })
+    }
+
+    async fn get_trending_links(
+        &self,
+        trending_links_query: TrendingLinksQuery,
+    ) -> Result<Vec<TrendingLinkModel>, ApiError> {
+        let limit = Self::validate_limit(trending_links_query.limit)?;
+
+        let ranked = self
+            .rank(trending_weights(Utc::now()), TRENDING_RANKING, limit)
+            .await?;
+
+        // links at or below their usual pace are not trending, however many clicks they have
+        Ok(ranked
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(code, score)| TrendingLinkModel { code, score })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::errors::ApiError;
+    use crate::models::top_models::{TopLinkModel, TopLinksQuery, TopWindow, TrendingLinksQuery};
+    use crate::services::top_links_service::TopLinksServiceTrait;
+    use mockall::predicate::{eq, function};
+    use std::sync::Arc;
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
+
+    const TEST_SHORT_URL: &str = "1234556";
+
+    #[tokio::test]
+    async fn get_top_links_ranks_window_buckets() {
+        // Arrange
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_top_of_sorted_sets()
+            .with(
+                function(|keys: &Vec<(String, f64)>| {
+                    keys.len() == 12
+                        && keys
+                            .iter()
+                            .all(|(key, _)| key.starts_with("top_links:hour:"))
+                }),
+                eq("top_links:ranking:hour"),
+                eq(5),
+            )
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(vec![(TEST_SHORT_URL.to_string(), 7.0)]) }));
+        let top_links_service = super::TopLinksService::new(Arc::new(redis_client));
+        let query = TopLinksQuery {
+            window: Some(TopWindow::Hour),
+            limit: Some(5),
+        };
+
+        // Act
+        let result = top_links_service.get_top_links(query).await;
+
+        // Assert
+        assert!(result.is_ok());
+        let top = result.unwrap();
+        assert_eq!(top.window, TopWindow::Hour);
+        assert_eq!(
+            top.links,
+            vec![TopLinkModel {
+                code: TEST_SHORT_URL.to_string(),
+                clicks: 7
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_top_links_limit_too_large_returns_bad_request() {
+        // Arrange
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client.expect_top_of_sorted_sets().never();
+        let top_links_service = super::TopLinksService::new(Arc::new(redis_client));
+        let query = TopLinksQuery {
+            limit: Some(1_000),
+            ..Default::default()
+        };
+
+        // Act
+        let result = top_links_service.get_top_links(query).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            ApiError::BadRequest("The limit must be between 1 and 100")
+        );
+    }
+
+    #[tokio::test]
+    async fn get_trending_links_skips_links_without_acceleration() {
+        // Arrange
+        let mut redis_client = MockRedisClientWrapperTrait::new();
+        redis_client
+            .expect_top_of_sorted_sets()
+            .with(
+                function(|keys: &Vec<(String, f64)>| keys.len() == 36),
+                eq("top_links:ranking:trending"),
+                eq(10),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(vec![
+                        (TEST_SHORT_URL.to_string(), 4.5),
+                        ("steady".to_string(), 0.0),
+                    ])
+                })
+            });
+        let top_links_service = super::TopLinksService::new(Arc::new(redis_client));
+
+        // Act
+        let result = top_links_service
+            .get_trending_links(TrendingLinksQuery::default())
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+        let trending = result.unwrap();
+        assert_eq!(trending.len(), 1);
+        assert_eq!(trending[0].code, TEST_SHORT_URL);
+    }
+}
@@ -1,50 +1,67 @@
+use crate::analytics::conversions::{
+    click_url_key, conversion_tracking_key, new_click_id, CLICK_ID_TTL_SECS, TRACKING_DISABLED,
+    TRACKING_ENABLED,
+};
 use crate::models::errors::ApiError;
 use crate::models::response_model::CreateResponseModel;
-use crate::models::url_models::CreateUrlRequest;
+use crate::models::url_models::{CreateUrlRequest, RedirectModel};
+use crate::qr::publish::{prerender_enabled, publish_queued_qr};
+use crate::qr::renderer::{qr_payload, render_qr};
+use crate::qr::settings::QrSettings;
+use crate::services::validation::validate_url;
 use async_trait::async_trait;
 use coi::Inject;
-use log::{error, warn};
-use qrcode_generator::QrCodeEcc;
+use log::warn;
 use rand::distr::Alphanumeric;
 use rand::{rng, Rng};
 use std::sync::Arc;
-use url::Url;
+use url_shortener_database::repositories::qr_upload_repository::QrUploadRepositoryTrait;
 use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
-use url_shortener_infrastructure::redis::redis_client::{RedisClientWrapperTrait};
+use url_shortener_infrastructure::redis::redis_client::RedisClientWrapperTrait;
 use url_shortener_infrastructure::s3::s3_client::S3ClientWrapperTrait;
 
 const CODE_LENGTH: usize = 6;
-const QR_CODE_SIZE: usize = 256;
+/// A cached destination expires even when an invalidation after a change was missed.
+const URL_CACHE_TTL_SECS: i64 = 60 * 60;
 #[async_trait]
 pub trait UrlServiceTrait: Inject {
     async fn create_short_url(
         &self,
         create_url_request: CreateUrlRequest,
     ) -> Result<CreateResponseModel, ApiError>;
-    async fn get_long_url(&self, short_url: &str) -> Result<String, ApiError>;
+    /// Visitors asking not to be tracked get no click id, even on links tracking conversions.
+    async fn get_long_url(
+        &self,
+        short_url: &str,
+        do_not_track: bool,
+    ) -> Result<RedirectModel, ApiError>;
 }
 
 #[derive(Inject)]
-#[coi(provides pub dyn UrlServiceTrait with UrlService::new(url_repository, s3_client_wrapper, redis_client_wrapper))]
+#[coi(provides pub dyn UrlServiceTrait with UrlService::new(url_repository, qr_upload_repository, s3_client_wrapper, redis_client_wrapper))]
 struct UrlService {
     #[coi(inject)]
     url_repository: Arc<dyn UrlRepositoryTrait>,
     #[coi(inject)]
+    qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
+    #[coi(inject)]
     s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
     #[coi(inject)]
-    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>
+    redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
 }
 
 impl UrlService {
     pub fn new(
         url_repository: Arc<dyn UrlRepositoryTrait>,
+        qr_upload_repository: Arc<dyn QrUploadRepositoryTrait>,
         s3_client_wrapper: Arc<dyn S3ClientWrapperTrait>,
-        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>
+        redis_client_wrapper: Arc<dyn RedisClientWrapperTrait>,
     ) -> Self {
         Self {
             url_repository,
+            qr_upload_repository,
             s3_client_wrapper,
-            redis_client_wrapper
+            redis_client_wrapper,
         }
     }
 
@@ -58,30 +75,48 @@ impl UrlService {
         short_code
     }
 
-    fn validate_url(url: &str) -> Result<(), ApiError> {
-        if url.is_empty() {
-            warn!("Url is empty {url:?}");
-            return Err(ApiError::BadRequest("Url is empty"));
-        }
-        match Url::parse(url) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                warn!("Parsing url failed {e:?}");
-                Err(ApiError::BadRequest("Invalid url"))
+    /// A tracked click's id is mapped to the link before the visitor is sent on, so a conversion
+    /// reported right away finds it even though the click itself is stored later.
+    async fn redirect(
+        &self,
+        short_url: &str,
+        url: String,
+        track_conversions: bool,
+        do_not_track: bool,
+    ) -> RedirectModel {
+        let click_id = (track_conversions && !do_not_track).then(new_click_id);
+        if let Some(click_id) = &click_id {
+            if let Err(e) = self
+                .redis_client_wrapper
+                .set_cache_with_expiry(&click_url_key(click_id), short_url, CLICK_ID_TTL_SECS)
+                .await
+            {
+                // the stored click still maps it once the queue is flushed
+                warn!("Failed to store click id {}: {}", click_id, e);
             }
         }
-    }
 
-    fn generate_qr_code(url: &str) -> Result<Vec<u8>, ApiError> {
-        let result = qrcode_generator::to_png_to_vec(url, QrCodeEcc::Low, QR_CODE_SIZE);
+        RedirectModel { url, click_id }
+    }
 
-        match result {
-            Ok(r) => Ok(r),
-            Err(e) => {
-                error!("Failed to generate qr code: {:?}", e);
-                Err(ApiError::InternalServerError)
-            }
-        }
+    async fn cache_url(&self, short_url: &str, url: &str, track_conversions: bool) {
+        let tracking = if track_conversions {
+            TRACKING_ENABLED
+        } else {
+            TRACKING_DISABLED
+        };
+        let _ = self
+            .redis_client_wrapper
+            .set_cache_with_expiry(short_url, url, URL_CACHE_TTL_SECS)
+            .await;
+        let _ = self
+            .redis_client_wrapper
+            .set_cache_with_expiry(
+                &conversion_tracking_key(short_url),
+                tracking,
+                URL_CACHE_TTL_SECS,
+            )
+            .await;
     }
 }
 
@@ -91,68 +126,87 @@ impl UrlServiceTrait for UrlService {
         &self,
         create_url_request: CreateUrlRequest,
     ) -> Result<CreateResponseModel, ApiError> {
-        let validate_url_result = Self::validate_url(&create_url_request.url);
-
-        if validate_url_result.is_err() {
-            return Err(validate_url_result.unwrap_err());
-        }
+        validate_url(&create_url_request.url)?;
+        let qr_settings = match &create_url_request.qr {
+            Some(options) => QrSettings::from_options(options)?,
+            None => QrSettings::default(),
+        };
 
         let code = Self::generate_short_code();
         let url = url_shortener_database::models::url_models::Url {
             id: code,
             url: create_url_request.url.clone(),
+            track_conversions: create_url_request.track_conversions,
+            campaign: create_url_request.campaign.clone(),
         };
 
-        let result = self.url_repository.create(url).await.map_err(|e| {
-            log::error!("Failed to create short url: {:?}", e);
-            ApiError::InternalServerError
-        })?;
-
+        // rendered before anything is saved, so a link is never stored with settings that cannot be drawn
         let domain = std::env::var("APP_DOMAIN").expect("APP_DOMAIN must be set");
-        let url_qr = format!("{}/{}", domain, result.id);
-        let qr_code = Self::generate_qr_code(&url_qr)?;
-        let file_name = format!("{}.png", result.id);
-        let s3_result = self
-            .s3_client_wrapper
-            .upload_image(qr_code, &file_name)
+        let qr_code = render_qr(&qr_payload(&domain, &url.id), &qr_settings)?;
+        let qr_options = create_url_request
+            .qr
+            .is_some()
+            .then(|| qr_settings.to_record(&url.id));
+        let prerender = prerender_enabled();
+
+        // the link, its options and the queued upload commit together, the queue entry is only
+        // removed once the image is stored so a failed upload is retried in the background
+        let result = self
+            .url_repository
+            .create(url, qr_options, prerender)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to create short url: {:?}", e);
+                ApiError::InternalServerError
+            })?;
+
+        let (qr_code_image, qr_status) = publish_queued_qr(
+            self.qr_upload_repository.as_ref(),
+            self.s3_client_wrapper.as_ref(),
+            &domain,
+            &result.id,
+            qr_settings.format,
+            qr_code,
+        )
+        .await;
+
+        self.cache_url(&result.id, &result.url, result.track_conversions)
             .await;
+        Ok(CreateResponseModel {
+            short_url: format!("{}/{}", domain, result.id),
+            qr_code_image,
+            qr_status,
+        })
+    }
 
-        match s3_result {
-            Ok(_) => {
-                let cloud_front_url =
-                    std::env::var("CLOUD_FRONT_URL").expect("CLOUD_FRONT_URL must be set");
-                let _ = self.redis_client_wrapper.set_cache(&result.id, &result.url).await;
-                Ok(CreateResponseModel {
-                    short_url: format!("{}/{}", domain, result.id),
-                    qr_code_image: format!("{}/{}", cloud_front_url, file_name),
-                })
-            }
-            Err(e) => {
-                log::error!("Failed to upload qr code: {:?}", e);
-                Err(ApiError::InternalServerError)
+    async fn get_long_url(
+        &self,
+        short_url: &str,
+        do_not_track: bool,
+    ) -> Result<RedirectModel, ApiError> {
+        let tracking_key = conversion_tracking_key(short_url);
+        let (url, tracking) = tokio::join!(
+            self.redis_client_wrapper.get_cache(short_url),
+            self.redis_client_wrapper.get_cache(&tracking_key),
+        );
+        match (url, tracking) {
+            (Ok(url), Ok(tracking)) => {
+                return Ok(self
+                    .redirect(short_url, url, tracking == TRACKING_ENABLED, do_not_track)
+                    .await)
             }
+            (Err(e), _) | (_, Err(e)) => warn!("Failed to fetch url cache: {}", e),
         }
-    }
 
-    async fn get_long_url(&self, short_url: &str) -> Result<String, ApiError> {
-        
-        let url_cache = self.redis_client_wrapper.get_cache(short_url).await;
-        
-        if url_cache.is_ok() {
-            return Ok(url_cache.unwrap());
-        }
-        
-        if  url_cache.is_err() {
-            warn!("Failed to fetch url cache: {}", url_cache.unwrap_err());
-        } 
-        
         let url = self.url_repository.find(short_url).await;
         match url {
             Ok(u) => match u {
                 Some(u) => {
-                    let _ = self.redis_client_wrapper.set_cache(short_url, &u.url).await;
-                    Ok(u.url)
-                },
+                    self.cache_url(short_url, &u.url, u.track_conversions).await;
+                    Ok(self
+                        .redirect(short_url, u.url, u.track_conversions, do_not_track)
+                        .await)
+                }
                 None => {
                     warn!("Short url not found: {:?}", short_url);
                     Err(ApiError::NotFound("The url with this format was not found"))
@@ -169,6 +223,7 @@ impl UrlServiceTrait for UrlService {
 #[cfg(test)]
 mod tests {
     use crate::models::errors::ApiError;
+    use crate::models::qr_models::QrStatus;
     use crate::models::url_models::CreateUrlRequest;
     use crate::services::url_service::UrlServiceTrait;
     use error_stack::Report;
@@ -177,22 +232,27 @@ mod tests {
     use std::sync::Arc;
     use url_shortener_database::models::errors::DatabaseError;
     use url_shortener_database::models::url_models::Url;
+    use url_shortener_database::repositories::qr_upload_repository::MockQrUploadRepositoryTrait;
     use url_shortener_database::repositories::url_repository::MockUrlRepositoryTrait;
     use url_shortener_infrastructure::redis::error::CacheError;
-    use url_shortener_infrastructure::redis::redis_client::{MockRedisClientWrapperTrait};
+    use url_shortener_infrastructure::redis::redis_client::MockRedisClientWrapperTrait;
     use url_shortener_infrastructure::s3::error::S3Error;
     use url_shortener_infrastructure::s3::s3_client::MockS3ClientWrapperTrait;
-    
+
     const TEST_SHORT_URL: &str = "1234556";
     const TEST_VALID_URL: &str = "https://www.google.com";
 
-    fn setup_mocks() -> (MockUrlRepositoryTrait, MockS3ClientWrapperTrait, MockRedisClientWrapperTrait) {
+    fn setup_mocks() -> (
+        MockUrlRepositoryTrait,
+        MockS3ClientWrapperTrait,
+        MockRedisClientWrapperTrait,
+    ) {
         let repository = MockUrlRepositoryTrait::new();
         let s3_client = MockS3ClientWrapperTrait::new();
         let redis_client = MockRedisClientWrapperTrait::new();
         (repository, s3_client, redis_client)
     }
-    
+
     #[tokio::test]
     async fn get_long_url_cache_miss_returns_internal_server_error() {
         // Arrange
@@ -204,15 +264,20 @@ mod tests {
             .with(eq(TEST_SHORT_URL))
             .returning(|_| Box::pin(async { Err(Report::from(DatabaseError {})) }));
 
-        redis_client.expect_get_cache()
+        redis_client
+            .expect_get_cache()
             .with(always())
-            .returning(|_| Box::pin(async { Err(Report::new(CacheError{})) }));
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError {})) }));
 
-
-        let url_service = super::UrlService::new(Arc::new(repository), s3_client, Arc::new(redis_client));
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
 
         // Act
-        let result = url_service.get_long_url(TEST_SHORT_URL).await;
+        let result = url_service.get_long_url(TEST_SHORT_URL, false).await;
 
         // Assert
         assert!(result.is_err());
@@ -230,14 +295,20 @@ mod tests {
             .with(eq(TEST_SHORT_URL))
             .returning(|_| Box::pin(async { Ok(None) }));
 
-        redis_client.expect_get_cache()
+        redis_client
+            .expect_get_cache()
             .with(always())
-            .returning(|_| Box::pin(async { Err(Report::new(CacheError{})) }));
-        
-        let url_service = super::UrlService::new(Arc::new(repository), s3_client, Arc::new(redis_client));
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError {})) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
 
         // Act
-        let result = url_service.get_long_url(TEST_SHORT_URL).await;
+        let result = url_service.get_long_url(TEST_SHORT_URL, false).await;
 
         // Assert
         assert!(result.is_err());
@@ -252,54 +323,145 @@ mod tests {
         // Arrange
         let (repository, client, mut redis_client) = setup_mocks();
         let s3_client = Arc::new(client);
-        
-        redis_client.expect_get_cache()
+
+        redis_client
+            .expect_get_cache()
             .with(always())
             .returning(|_| Box::pin(async { Ok("url".to_string()) }));
-        
-        let url_service = super::UrlService::new(Arc::new(repository), s3_client, Arc::new(redis_client));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
 
         // Act
-        let result = url_service.get_long_url(TEST_SHORT_URL).await;
+        let result = url_service.get_long_url(TEST_SHORT_URL, false).await;
 
         // Assert
         assert!(result.is_ok());
         assert!(result.ok().is_some());
     }
 
+    #[tokio::test]
+    async fn get_long_url_tracked_link_returns_click_id() {
+        // Arrange
+        let (repository, client, mut redis_client) = setup_mocks();
+        let s3_client = Arc::new(client);
+
+        redis_client
+            .expect_get_cache()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Ok(TEST_VALID_URL.to_string()) }));
+        redis_client
+            .expect_get_cache()
+            .with(eq("conversion_tracking:1234556"))
+            .returning(|_| Box::pin(async { Ok("1".to_string()) }));
+        // mapped before the redirect, the click itself is only stored once the queue is flushed
+        redis_client
+            .expect_set_cache_with_expiry()
+            .withf(|key, short_url, _| key.starts_with("click_url:") && short_url == TEST_SHORT_URL)
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = url_service.get_long_url(TEST_SHORT_URL, false).await;
+
+        // Assert
+        assert!(result.is_ok());
+        let redirect = result.unwrap();
+        let click_id = redirect.click_id.clone().unwrap();
+        assert_eq!(
+            redirect.location(),
+            format!("{}/?sclid={}", TEST_VALID_URL, click_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn get_long_url_tracked_link_with_do_not_track_has_no_click_id() {
+        // Arrange
+        let (repository, client, mut redis_client) = setup_mocks();
+        let s3_client = Arc::new(client);
+
+        redis_client
+            .expect_get_cache()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Ok(TEST_VALID_URL.to_string()) }));
+        redis_client
+            .expect_get_cache()
+            .with(eq("conversion_tracking:1234556"))
+            .returning(|_| Box::pin(async { Ok("1".to_string()) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
+
+        // Act
+        let result = url_service.get_long_url(TEST_SHORT_URL, true).await;
+
+        // Assert
+        assert!(result.is_ok());
+        let redirect = result.unwrap();
+        assert_eq!(redirect.click_id, None);
+        assert_eq!(redirect.location(), TEST_VALID_URL);
+    }
+
     #[tokio::test]
     async fn get_long_url_cache_miss_returns_ok() {
         // Arrange
         let (mut repository, client, mut redis_client) = setup_mocks();
         let s3_client = Arc::new(client);
 
-        repository.expect_find().with(eq(TEST_SHORT_URL)).returning(|_| {
-            Box::pin(async {
-                Ok(Some(Url {
-                    id: "".to_string(),
-                    url: "".to_string(),
-                }))
-            })
-        });
+        repository
+            .expect_find()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Some(Url {
+                        id: "".to_string(),
+                        url: "".to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    }))
+                })
+            });
 
-        redis_client.expect_get_cache()
+        redis_client
+            .expect_get_cache()
             .with(always())
-            .returning(|_| Box::pin(async { Err(Report::new(CacheError{})) }));
-
-        redis_client.expect_set_cache()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Ok(()) }));
-
-        let url_service = super::UrlService::new(Arc::new(repository), s3_client, Arc::new(redis_client));
+            .returning(|_| Box::pin(async { Err(Report::new(CacheError {})) }));
+
+        redis_client
+            .expect_set_cache_with_expiry()
+            .with(always(), always(), eq(3600))
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            Arc::new(redis_client),
+        );
 
         // Act
-        let result = url_service.get_long_url(TEST_SHORT_URL).await;
+        let result = url_service.get_long_url(TEST_SHORT_URL, false).await;
 
         // Assert
         assert!(result.is_ok());
         assert!(result.ok().is_some());
     }
-    
+
     #[tokio::test]
     async fn create_url_returns_url_empty_error() {
         // Arrange
@@ -310,8 +472,16 @@ mod tests {
 
         let request = CreateUrlRequest {
             url: "".to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
-        let url_service = super::UrlService::new(repository, s3_client, redis_client);
+        let url_service = super::UrlService::new(
+            repository,
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            redis_client,
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
@@ -331,8 +501,16 @@ mod tests {
 
         let request = CreateUrlRequest {
             url: "invalid_url".to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
-        let url_service = super::UrlService::new(repository, s3_client, redis_client);
+        let url_service = super::UrlService::new(
+            repository,
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            redis_client,
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
@@ -351,13 +529,21 @@ mod tests {
 
         let request = CreateUrlRequest {
             url: TEST_VALID_URL.to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
         repository
             .expect_create()
-            .with(always())
-            .returning(|_| Box::pin(async { Err(Report::from(DatabaseError {})) }));
-
-        let url_service = super::UrlService::new(Arc::new(repository), s3_client, redis_client);
+            .with(always(), always(), always())
+            .returning(|_, _, _| Box::pin(async { Err(Report::from(DatabaseError {})) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(MockQrUploadRepositoryTrait::new()),
+            s3_client,
+            redis_client,
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
@@ -367,105 +553,184 @@ mod tests {
         assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
     }
     #[tokio::test]
-    async fn create_url_on_s3_returns_internal_server_error() {
+    async fn create_url_on_s3_error_returns_pending_qr() {
         // Arrange
         env::set_var("APP_DOMAIN", "yes");
-        let (mut repository, mut s3_client, redis_client) = setup_mocks();
-        let redis_client = Arc::new(redis_client);
+        let (mut repository, mut s3_client, mut redis_client) = setup_mocks();
 
         let request = CreateUrlRequest {
             url: TEST_VALID_URL.to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
-        repository.expect_create().with(always()).returning(|_| {
-            Box::pin(async {
-                Ok(Url {
-                    id: TEST_SHORT_URL.to_string(),
-                    url: TEST_VALID_URL.to_string(),
+        repository
+            .expect_create()
+            .with(always(), eq(None), eq(true))
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    })
                 })
-            })
-        });
+            });
 
         s3_client
             .expect_upload_image()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Err(Report::new(S3Error {})) }));
-
-        let url_service = super::UrlService::new(Arc::new(repository), Arc::new(s3_client), redis_client);
+            .with(always(), always(), always())
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(S3Error {})) }));
+
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_record_failure()
+            .with(eq(TEST_SHORT_URL), always(), eq(30))
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        qr_upload_repository.expect_complete().never();
+
+        redis_client
+            .expect_set_cache_with_expiry()
+            .with(always(), always(), eq(3600))
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(qr_upload_repository),
+            Arc::new(s3_client),
+            Arc::new(redis_client),
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
 
         // Assert
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ApiError::InternalServerError);
+        let response = result.unwrap();
+        assert_eq!(response.qr_status, QrStatus::Pending);
+        assert_eq!(
+            response.qr_code_image,
+            format!("yes/api/url/{}/qr", TEST_SHORT_URL)
+        );
     }
 
     #[tokio::test]
     async fn create_url_cache_ok_returns_ok() {
         // Arrange
         env::set_var("APP_DOMAIN", "yes");
-        env::set_var("CLOUD_FRONT_URL", "yes");
         let (mut repository, mut s3_client, mut redis_client) = setup_mocks();
 
         let request = CreateUrlRequest {
             url: TEST_VALID_URL.to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
-        repository.expect_create().with(always()).returning(|_| {
-            Box::pin(async {
-                Ok(Url {
-                    id: TEST_SHORT_URL.to_string(),
-                    url: TEST_VALID_URL.to_string(),
+        repository
+            .expect_create()
+            .with(always(), eq(None), eq(true))
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    })
                 })
-            })
-        });
+            });
 
         s3_client
             .expect_upload_image()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Ok(()) }));
-        
-        redis_client.expect_set_cache()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Ok(()) }));
+            .with(always(), always(), always())
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
 
-        let url_service = super::UrlService::new(Arc::new(repository), Arc::new(s3_client), Arc::new(redis_client));
+        s3_client
+            .expect_image_url()
+            .with(always())
+            .returning(|_| Box::pin(async { Ok(None) }));
+
+        redis_client
+            .expect_set_cache_with_expiry()
+            .with(always(), always(), eq(3600))
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_complete()
+            .with(eq(TEST_SHORT_URL))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(qr_upload_repository),
+            Arc::new(s3_client),
+            Arc::new(redis_client),
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
 
         // Assert
-        assert!(result.is_ok());
+        assert_eq!(result.unwrap().qr_status, QrStatus::Ready);
     }
 
     #[tokio::test]
     async fn create_url_cache_error_returns_ok() {
         // Arrange
         env::set_var("APP_DOMAIN", "yes");
-        env::set_var("CLOUD_FRONT_URL", "yes");
         let (mut repository, mut s3_client, mut redis_client) = setup_mocks();
 
         let request = CreateUrlRequest {
             url: TEST_VALID_URL.to_string(),
+            track_conversions: false,
+            campaign: None,
+            qr: None,
         };
-        repository.expect_create().with(always()).returning(|_| {
-            Box::pin(async {
-                Ok(Url {
-                    id: TEST_SHORT_URL.to_string(),
-                    url: TEST_VALID_URL.to_string(),
+        repository
+            .expect_create()
+            .with(always(), eq(None), eq(true))
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Url {
+                        id: TEST_SHORT_URL.to_string(),
+                        url: TEST_VALID_URL.to_string(),
+                        track_conversions: false,
+                        campaign: None,
+                    })
                 })
-            })
-        });
+            });
 
         s3_client
             .expect_upload_image()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Ok(()) }));
+            .with(always(), always(), always())
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
 
-        redis_client.expect_set_cache()
-            .with(always(), always())
-            .returning(|_, _| Box::pin(async { Err(Report::new(CacheError{})) }));
+        s3_client
+            .expect_image_url()
+            .with(always())
+            .returning(|_| Box::pin(async { Ok(None) }));
+
+        redis_client
+            .expect_set_cache_with_expiry()
+            .with(always(), always(), eq(3600))
+            .returning(|_, _, _| Box::pin(async { Err(Report::new(CacheError {})) }));
+
+        // a failure to clear the queue entry only means the image is uploaded again later
+        let mut qr_upload_repository = MockQrUploadRepositoryTrait::new();
+        qr_upload_repository
+            .expect_complete()
+            .with(eq(TEST_SHORT_URL))
+            .returning(|_| Box::pin(async { Err(Report::from(DatabaseError {})) }));
 
-        let url_service = super::UrlService::new(Arc::new(repository), Arc::new(s3_client), Arc::new(redis_client));
+        let url_service = super::UrlService::new(
+            Arc::new(repository),
+            Arc::new(qr_upload_repository),
+            Arc::new(s3_client),
+            Arc::new(redis_client),
+        );
 
         // Act
         let result = url_service.create_short_url(request).await;
@@ -0,0 +1,73 @@
+use crate::analytics::conversions::postback_token_matches;
+use crate::models::errors::ApiError;
+use log::{error, warn};
+use std::env;
+use url::Url;
+use url_shortener_database::repositories::url_repository::UrlRepositoryTrait;
+
+pub(crate) fn validate_url(url: &str) -> Result<(), ApiError> {
+    if url.is_empty() {
+        warn!("Url is empty {url:?}");
+        return Err(ApiError::BadRequest("Url is empty"));
+    }
+    match Url::parse(url) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            warn!("Parsing url failed {e:?}");
+            Err(ApiError::BadRequest("Invalid url"))
+        }
+    }
+}
+
+pub(crate) async fn ensure_url_exists(
+    url_repository: &dyn UrlRepositoryTrait,
+    short_url: &str,
+) -> Result<(), ApiError> {
+    match url_repository.find(short_url).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => {
+            warn!("Short url not found: {:?}", short_url);
+            Err(ApiError::NotFound("The url with this format was not found"))
+        }
+        Err(e) => {
+            error!("Failed to get long url: {:?}", e);
+            Err(ApiError::InternalServerError)
+        }
+    }
+}
+
+const DEFAULT_ADMIN_PRINCIPAL: &str = "admin";
+
+/// Admin tasks are refused outright while no `ADMIN_TOKEN` is configured.
+pub(crate) fn ensure_admin(token: Option<&str>) -> Result<(), ApiError> {
+    if env::var("ADMIN_TOKEN").is_err() {
+        warn!("Admin request refused, ADMIN_TOKEN is not set");
+        return Err(ApiError::Unauthorized("Invalid admin token"));
+    }
+
+    if is_admin_token(token) {
+        Ok(())
+    } else {
+        warn!("Admin request with an invalid token");
+        Err(ApiError::Unauthorized("Invalid admin token"))
+    }
+}
+
+/// Checks the admin token and names who the change is recorded as in the link history.
+pub(crate) fn admin_actor(token: Option<&str>) -> Result<String, ApiError> {
+    ensure_admin(token)?;
+
+    Ok(env::var("ADMIN_PRINCIPAL")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_ADMIN_PRINCIPAL.to_string()))
+}
+
+fn is_admin_token(token: Option<&str>) -> bool {
+    match (env::var("ADMIN_TOKEN"), token) {
+        (Ok(expected), Some(token)) => {
+            !expected.is_empty() && postback_token_matches(&expected, token)
+        }
+        _ => false,
+    }
+}
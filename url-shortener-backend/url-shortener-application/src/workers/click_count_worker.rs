@@ -0,0 +1,21 @@
+use crate::services::click_count_service::ClickCountServiceTrait;
+use log::{debug, error};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_click_count_worker(
+    click_count_service: Arc<dyn ClickCountServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match click_count_service.flush_counts().await {
+            Ok(0) => {}
+            Ok(flushed) => debug!("Flushed {} click counters", flushed),
+            Err(e) => error!("Click count worker failed: {:?}", e),
+        }
+    }
+}
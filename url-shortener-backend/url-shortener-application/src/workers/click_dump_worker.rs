@@ -0,0 +1,21 @@
+use crate::services::click_dump_service::ClickDumpServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_click_dump_worker(
+    click_dump_service: Arc<dyn ClickDumpServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match click_dump_service.dump_pending_days().await {
+            Ok(0) => {}
+            Ok(dumped) => info!("Dumped clicks for {} days", dumped),
+            Err(e) => error!("Click dump worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::analytics::click_events::CLICK_EVENTS_CHANNEL;
+use crate::models::click_event_models::ClickEventModel;
+use crate::queues::click_event_hub::ClickEventHubTrait;
+use futures::StreamExt;
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use url_shortener_infrastructure::redis::pubsub::RedisSubscriber;
+
+/// Relays clicks published by every instance to the viewers connected to this one,
+/// resubscribing after `retry_period` whenever the Redis connection drops.
+pub async fn run_click_event_worker(
+    subscriber: RedisSubscriber,
+    click_event_hub: Arc<dyn ClickEventHubTrait>,
+    retry_period: Duration,
+) {
+    loop {
+        match subscriber.subscribe(CLICK_EVENTS_CHANNEL).await {
+            Ok(mut messages) => {
+                info!("Subscribed to click events");
+                while let Some(message) = messages.next().await {
+                    match serde_json::from_str::<ClickEventModel>(&message) {
+                        Ok(event) => click_event_hub.dispatch(event),
+                        Err(e) => warn!("Skipping malformed click event: {}", e),
+                    }
+                }
+                warn!("Click event subscription closed");
+            }
+            Err(e) => error!("Failed to subscribe to click events: {:?}", e),
+        }
+
+        tokio::time::sleep(retry_period).await;
+    }
+}
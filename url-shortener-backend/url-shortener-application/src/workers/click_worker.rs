@@ -0,0 +1,174 @@
+use log::{debug, error};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use url_shortener_database::models::click_models::Click;
+use url_shortener_database::repositories::click_repository::ClickRepositoryTrait;
+
+/// How many batches are kept for the next flush while inserts fail, older clicks are dropped.
+const MAX_RETAINED_BATCHES: usize = 100;
+
+pub async fn run_click_worker(
+    mut receiver: Receiver<Click>,
+    click_repository: Arc<dyn ClickRepositoryTrait>,
+    batch_size: usize,
+    flush_period: Duration,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut interval = tokio::time::interval(flush_period);
+    // after a failed insert only the interval retries, so an outage is not hit on every click
+    let mut retrying = false;
+
+    loop {
+        tokio::select! {
+            received = receiver.recv() => match received {
+                Some(click) => {
+                    batch.push(click);
+                    if batch.len() >= batch_size && !retrying {
+                        retrying = !flush(click_repository.as_ref(), &mut batch, batch_size).await;
+                    }
+                }
+                None => {
+                    flush(click_repository.as_ref(), &mut batch, batch_size).await;
+                    break;
+                }
+            },
+            _ = interval.tick() => {
+                retrying = !flush(click_repository.as_ref(), &mut batch, batch_size).await;
+            }
+        }
+    }
+}
+
+/// Inserts the clicks a batch at a time, the batch that fails is kept with every click after it
+/// for the next flush. Returns whether everything was inserted.
+async fn flush(
+    click_repository: &dyn ClickRepositoryTrait,
+    batch: &mut Vec<Click>,
+    batch_size: usize,
+) -> bool {
+    while !batch.is_empty() {
+        let clicks: Vec<Click> = batch.iter().take(batch_size).cloned().collect();
+        let count = clicks.len();
+        match click_repository.insert_batch(clicks).await {
+            Ok(inserted) => {
+                debug!("Inserted {} clicks", inserted);
+                batch.drain(..count);
+            }
+            Err(e) => {
+                error!("Failed to insert {} clicks, retrying: {:?}", count, e);
+                let excess = batch
+                    .len()
+                    .saturating_sub(batch_size * MAX_RETAINED_BATCHES);
+                if excess > 0 {
+                    error!("Dropping {} clicks after failed inserts", excess);
+                    batch.drain(..excess);
+                }
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use error_stack::Report;
+    use mockall::predicate::function;
+    use mockall::Sequence;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::channel;
+    use url_shortener_database::models::click_models::Click;
+    use url_shortener_database::models::errors::DatabaseError;
+    use url_shortener_database::repositories::click_repository::MockClickRepositoryTrait;
+
+    fn test_click() -> Click {
+        Click {
+            url_id: "1234556".to_string(),
+            clicked_at: Utc::now(),
+            referrer: None,
+            user_agent: None,
+            ip_hash: None,
+            destination: "https://www.google.com".to_string(),
+            is_bot: false,
+            bot_reason: None,
+            country: None,
+            region: None,
+            city: None,
+            source: "link".to_string(),
+            click_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn click_worker_flushes_in_batches() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        click_repository
+            .expect_insert_batch()
+            .with(function(|clicks: &Vec<Click>| clicks.len() == 2))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(2) }));
+        click_repository
+            .expect_insert_batch()
+            .with(function(|clicks: &Vec<Click>| clicks.len() == 1))
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(1) }));
+        let (sender, receiver) = channel(10);
+        for _ in 0..3 {
+            sender.send(test_click()).await.unwrap();
+        }
+        drop(sender);
+
+        // Act
+        super::run_click_worker(
+            receiver,
+            Arc::new(click_repository),
+            2,
+            Duration::from_secs(3600),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn click_worker_keeps_failed_batch_for_next_flush() {
+        // Arrange
+        let mut click_repository = MockClickRepositoryTrait::new();
+        let mut sequence = Sequence::new();
+        click_repository
+            .expect_insert_batch()
+            .with(function(|clicks: &Vec<Click>| clicks.len() == 2))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Box::pin(async { Err(Report::new(DatabaseError {})) }));
+        click_repository
+            .expect_insert_batch()
+            .with(function(|clicks: &Vec<Click>| clicks.len() == 2))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Box::pin(async { Ok(2) }));
+        click_repository
+            .expect_insert_batch()
+            .with(function(|clicks: &Vec<Click>| clicks.len() == 1))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Box::pin(async { Ok(1) }));
+        let (sender, receiver) = channel(10);
+        for _ in 0..3 {
+            sender.send(test_click()).await.unwrap();
+        }
+        drop(sender);
+
+        // Act
+        super::run_click_worker(
+            receiver,
+            Arc::new(click_repository),
+            2,
+            Duration::from_secs(3600),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,9 @@
+pub mod click_count_worker;
+pub mod click_dump_worker;
+pub mod click_event_worker;
+pub mod click_worker;
+pub mod qr_backfill_worker;
+pub mod qr_reconcile_worker;
+pub mod qr_upload_worker;
+pub mod retention_worker;
+pub mod schedule_worker;
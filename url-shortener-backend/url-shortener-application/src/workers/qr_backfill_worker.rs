@@ -0,0 +1,21 @@
+use crate::services::qr_backfill_service::QrBackfillServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_qr_backfill_worker(
+    qr_backfill_service: Arc<dyn QrBackfillServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match qr_backfill_service.run_active_backfill().await {
+            Ok(0) => {}
+            Ok(processed) => info!("Backfilled qr codes for {} links", processed),
+            Err(e) => error!("QR backfill worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::services::qr_reconcile_service::QrReconcileServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_qr_reconcile_worker(
+    qr_reconcile_service: Arc<dyn QrReconcileServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match qr_reconcile_service.run_active_reconcile().await {
+            Ok(0) => {}
+            Ok(found) => info!("Reconciled {} qr images", found),
+            Err(e) => error!("QR reconcile worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::services::qr_upload_service::QrUploadServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_qr_upload_worker(
+    qr_upload_service: Arc<dyn QrUploadServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match qr_upload_service.upload_pending().await {
+            Ok(0) => {}
+            Ok(uploaded) => info!("Uploaded {} pending qr codes", uploaded),
+            Err(e) => error!("QR upload worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::services::retention_service::RetentionServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_retention_worker(
+    retention_service: Arc<dyn RetentionServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match retention_service.apply_retention().await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} expired clicks", purged),
+            Err(e) => error!("Retention worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::services::schedule_service::ScheduleServiceTrait;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn run_schedule_worker(
+    schedule_service: Arc<dyn ScheduleServiceTrait>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match schedule_service.apply_due_schedules().await {
+            Ok(0) => {}
+            Ok(applied) => info!("Applied {} scheduled destination changes", applied),
+            Err(e) => error!("Schedule worker failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+-- the links table predates the migrations, existing databases already have it
+CREATE TABLE IF NOT EXISTS urls (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS url_schedules (
+    id BIGSERIAL PRIMARY KEY,
+    url_id TEXT NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    destination TEXT NOT NULL,
+    scheduled_at TIMESTAMPTZ NOT NULL,
+    applied_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_url_schedules_pending
+    ON url_schedules (scheduled_at)
+    WHERE applied_at IS NULL;
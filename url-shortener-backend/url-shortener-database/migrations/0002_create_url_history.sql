@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS url_history (
+    id BIGSERIAL PRIMARY KEY,
+    url_id TEXT NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    old_value TEXT NULL,
+    new_value TEXT NOT NULL,
+    changed_by TEXT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_url_history_url_id ON url_history (url_id, id);
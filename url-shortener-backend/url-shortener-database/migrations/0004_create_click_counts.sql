@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS click_counts (
+    url_id TEXT NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    count BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (url_id, day)
+);
+
+-- every flush id is recorded in the same transaction as its counts, a replayed flush is skipped
+CREATE TABLE IF NOT EXISTS click_count_flushes (
+    id TEXT PRIMARY KEY,
+    flushed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
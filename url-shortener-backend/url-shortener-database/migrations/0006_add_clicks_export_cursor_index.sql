@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS idx_clicks_url_id_id ON clicks (url_id, id);
@@ -0,0 +1,7 @@
+-- a day is recorded only after its file is fully uploaded, missing days are dumped again
+CREATE TABLE IF NOT EXISTS click_dumps (
+    day DATE PRIMARY KEY,
+    object_key TEXT NOT NULL,
+    row_count BIGINT NOT NULL,
+    dumped_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- raw clicks past the retention age are rolled up here without any per-visitor data
+CREATE TABLE IF NOT EXISTS click_aggregates (
+    url_id TEXT NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    referrer TEXT NOT NULL DEFAULT '',
+    user_agent TEXT NOT NULL DEFAULT '',
+    is_bot BOOLEAN NOT NULL,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (url_id, day, referrer, user_agent, is_bot)
+);
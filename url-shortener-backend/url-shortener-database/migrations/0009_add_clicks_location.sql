@@ -0,0 +1,4 @@
+-- resolved at ingestion from the visitor address, which itself is never stored
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS country TEXT NULL;
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS region TEXT NULL;
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS city TEXT NULL;
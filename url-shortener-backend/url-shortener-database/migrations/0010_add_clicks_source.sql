@@ -0,0 +1,2 @@
+-- 'qr' for visits through the encoded QR code, 'link' for everything else
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS source TEXT NOT NULL DEFAULT 'link';
@@ -0,0 +1,18 @@
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS track_conversions BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- the opaque id appended to the destination, only set for links tracking conversions
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS click_id TEXT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_clicks_click_id ON clicks (click_id) WHERE click_id IS NOT NULL;
+
+-- a postback repeated for the same click and event is only counted once
+CREATE TABLE IF NOT EXISTS conversions (
+    id BIGSERIAL PRIMARY KEY,
+    click_id TEXT NOT NULL,
+    url_id TEXT NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    value DOUBLE PRECISION NULL,
+    converted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (click_id, event)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversions_url_id_converted_at ON conversions (url_id, converted_at);
@@ -0,0 +1,11 @@
+-- links without a row here use the default QR rendering
+CREATE TABLE IF NOT EXISTS url_qr_options (
+    url_id TEXT PRIMARY KEY REFERENCES urls (id) ON DELETE CASCADE,
+    format TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    error_correction TEXT NOT NULL,
+    foreground TEXT NOT NULL,
+    background TEXT NOT NULL,
+    quiet_zone INTEGER NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
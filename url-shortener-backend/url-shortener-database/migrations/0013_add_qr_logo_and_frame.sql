@@ -0,0 +1,6 @@
+-- logos are normalized to a small PNG before they are stored, so they stay cheap to read with the options
+ALTER TABLE url_qr_options
+    ADD COLUMN IF NOT EXISTS frame TEXT,
+    ADD COLUMN IF NOT EXISTS caption TEXT,
+    ADD COLUMN IF NOT EXISTS logo BYTEA,
+    ADD COLUMN IF NOT EXISTS logo_size INTEGER NOT NULL DEFAULT 20;
@@ -0,0 +1,3 @@
+-- the same options also render Data Matrix and Aztec codes for scanners without QR support
+ALTER TABLE url_qr_options
+    ADD COLUMN IF NOT EXISTS symbology TEXT NOT NULL DEFAULT 'qr';
@@ -0,0 +1,17 @@
+-- progress is saved after every page of links, an interrupted job resumes after its last link
+CREATE TABLE IF NOT EXISTS qr_backfill_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'pending',
+    domain TEXT NOT NULL,
+    destination_prefix TEXT NULL,
+    last_url_id TEXT NOT NULL DEFAULT '',
+    total BIGINT NOT NULL,
+    processed BIGINT NOT NULL DEFAULT 0,
+    failed BIGINT NOT NULL DEFAULT 0,
+    error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_qr_backfill_jobs_active ON qr_backfill_jobs (id) WHERE status IN ('pending', 'running');
@@ -0,0 +1,11 @@
+-- written in the same transaction as its link and removed once the link's QR image is uploaded,
+-- so a failed upload is retried instead of leaving a live link without its image
+CREATE TABLE IF NOT EXISTS qr_upload_outbox (
+    url_id TEXT PRIMARY KEY REFERENCES urls (id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_qr_upload_outbox_next_attempt_at ON qr_upload_outbox (next_attempt_at);
@@ -0,0 +1,3 @@
+-- links can be grouped under a campaign so their clicks can be exported together
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS campaign TEXT NULL;
+CREATE INDEX IF NOT EXISTS idx_urls_campaign ON urls (campaign) WHERE campaign IS NOT NULL;
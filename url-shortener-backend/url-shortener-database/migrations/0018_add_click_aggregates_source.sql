@@ -0,0 +1,4 @@
+-- the source says how a link was reached, not who reached it, so it survives the roll up
+ALTER TABLE click_aggregates ADD COLUMN IF NOT EXISTS source TEXT NOT NULL DEFAULT 'link';
+ALTER TABLE click_aggregates DROP CONSTRAINT IF EXISTS click_aggregates_pkey;
+ALTER TABLE click_aggregates ADD PRIMARY KEY (url_id, day, referrer, user_agent, is_bot, source);
@@ -0,0 +1,17 @@
+-- a worker leases the backfill it runs and renews the lease whenever it saves progress,
+-- another worker only takes the backfill over once the lease has run out
+ALTER TABLE qr_backfill_jobs
+    ADD COLUMN IF NOT EXISTS leased_by TEXT NULL,
+    ADD COLUMN IF NOT EXISTS leased_until TIMESTAMPTZ NULL;
+
+-- jobs queued side by side before only one could be active, the oldest keeps running
+UPDATE qr_backfill_jobs
+SET status = 'failed',
+    error = 'Another QR backfill was already in progress',
+    updated_at = NOW(),
+    finished_at = NOW()
+WHERE status IN ('pending', 'running')
+    AND id > (SELECT MIN(id) FROM qr_backfill_jobs WHERE status IN ('pending', 'running'));
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_qr_backfill_jobs_single_active ON qr_backfill_jobs ((TRUE))
+    WHERE status IN ('pending', 'running');
@@ -0,0 +1,22 @@
+-- reconciliation runs in the background, the job keeps its findings for the status endpoint.
+-- It is leased like a backfill, a worker that stopped is replaced and the run starts over,
+-- deleting orphans and regenerating missing images is safe to repeat
+CREATE TABLE IF NOT EXISTS qr_reconcile_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'pending',
+    dry_run BOOLEAN NOT NULL,
+    orphaned TEXT[] NOT NULL DEFAULT '{}',
+    missing TEXT[] NOT NULL DEFAULT '{}',
+    deleted BIGINT NOT NULL DEFAULT 0,
+    regenerated BIGINT NOT NULL DEFAULT 0,
+    failed BIGINT NOT NULL DEFAULT 0,
+    error TEXT NULL,
+    leased_by TEXT NULL,
+    leased_until TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_qr_reconcile_jobs_single_active ON qr_reconcile_jobs ((TRUE))
+    WHERE status IN ('pending', 'running');
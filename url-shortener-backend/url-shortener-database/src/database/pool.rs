@@ -14,6 +14,15 @@ pub async fn crete_database_connection() -> Result<PgPool, Report<DatabaseError>
     Ok(pool)
 }
 
+/// Applies the migrations embedded from `migrations/` that the database has not run yet.
+pub async fn run_migrations(pool: &PgPool) -> Result<(), Report<DatabaseError>> {
+    sqlx::migrate!("./migrations")
+        .run(pool)
+        .await
+        .attach_printable_lazy(|| "Failed to run database migrations")
+        .change_context(DatabaseError)
+}
+
 #[derive(Inject)]
 pub struct PgPoolWrapper(PgPool);
 
@@ -0,0 +1,71 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Click {
+    pub url_id: String,
+    pub clicked_at: DateTime<Utc>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_hash: Option<String>,
+    pub destination: String,
+    pub is_bot: bool,
+    pub bot_reason: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub source: String,
+    pub click_id: Option<String>,
+}
+
+/// A stored click, the id only serves as a stable cursor when reading clicks in pages.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClickRecord {
+    pub id: i64,
+    pub url_id: String,
+    pub clicked_at: DateTime<Utc>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_hash: Option<String>,
+    pub destination: String,
+    pub is_bot: bool,
+    pub bot_reason: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub source: String,
+}
+
+/// The clicks an export reads, those of a single link or of every link in a campaign.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickSelection {
+    Link(String),
+    Campaign(String),
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PeriodCount {
+    pub period_start: DateTime<Utc>,
+    pub count: i64,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValueCount {
+    pub value: Option<String>,
+    pub count: i64,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LocationCount {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub count: i64,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClickCount {
+    pub url_id: String,
+    pub day: NaiveDate,
+    pub count: i64,
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub click_id: String,
+    pub url_id: String,
+    pub event: String,
+    pub value: Option<f64>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConversionCount {
+    pub event: String,
+    pub count: i64,
+    pub value: Option<f64>,
+}
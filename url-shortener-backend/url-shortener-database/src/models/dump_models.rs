@@ -0,0 +1,10 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClickDump {
+    pub day: NaiveDate,
+    pub object_key: String,
+    pub row_count: i64,
+    pub dumped_at: DateTime<Utc>,
+}
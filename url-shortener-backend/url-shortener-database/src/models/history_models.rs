@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const URL_FIELD: &str = "url";
+pub const QR_OPTIONS_FIELD: &str = "qr_options";
+pub const QR_LOGO_FIELD: &str = "qr_logo";
+pub const CHANGED_BY_API: &str = "api";
+pub const CHANGED_BY_SCHEDULER: &str = "scheduler";
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct UrlHistory {
+    pub id: i64,
+    pub url_id: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+}
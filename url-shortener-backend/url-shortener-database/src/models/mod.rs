@@ -1,2 +1,11 @@
+pub mod click_models;
+pub mod conversion_models;
+pub mod dump_models;
 pub mod errors;
+pub mod history_models;
+pub mod qr_backfill_models;
+pub mod qr_models;
+pub mod qr_reconcile_models;
+pub mod qr_upload_models;
+pub mod schedule_models;
 pub mod url_models;
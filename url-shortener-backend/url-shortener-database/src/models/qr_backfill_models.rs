@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const BACKFILL_PENDING: &str = "pending";
+pub const BACKFILL_RUNNING: &str = "running";
+pub const BACKFILL_COMPLETED: &str = "completed";
+pub const BACKFILL_FAILED: &str = "failed";
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QrBackfillJob {
+    pub id: i64,
+    pub status: String,
+    pub domain: String,
+    pub destination_prefix: Option<String>,
+    /// The id of the last link processed, links are visited in id order.
+    pub last_url_id: String,
+    pub total: i64,
+    pub processed: i64,
+    pub failed: i64,
+    pub error: Option<String>,
+    /// The worker running the backfill, it holds the job until `leased_until`.
+    pub leased_by: Option<String>,
+    pub leased_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,83 @@
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UrlQrOptions {
+    pub url_id: String,
+    pub symbology: String,
+    pub format: String,
+    pub size: i32,
+    pub error_correction: String,
+    pub foreground: String,
+    pub background: String,
+    pub quiet_zone: i32,
+    pub frame: Option<String>,
+    pub caption: Option<String>,
+    pub logo: Option<Vec<u8>>,
+    pub logo_size: i32,
+}
+
+/// How the history records the options, the logo is left out as it has entries of its own.
+#[derive(Serialize, Deserialize)]
+struct QrOptionsSnapshot {
+    symbology: String,
+    format: String,
+    size: i32,
+    error_correction: String,
+    foreground: String,
+    background: String,
+    quiet_zone: i32,
+    frame: Option<String>,
+    caption: Option<String>,
+    logo_size: i32,
+}
+
+impl UrlQrOptions {
+    pub fn options_snapshot(&self) -> String {
+        let snapshot = QrOptionsSnapshot {
+            symbology: self.symbology.clone(),
+            format: self.format.clone(),
+            size: self.size,
+            error_correction: self.error_correction.clone(),
+            foreground: self.foreground.clone(),
+            background: self.background.clone(),
+            quiet_zone: self.quiet_zone,
+            frame: self.frame.clone(),
+            caption: self.caption.clone(),
+            logo_size: self.logo_size,
+        };
+        serde_json::to_string(&snapshot).expect("the options serialize to JSON")
+    }
+
+    /// The logo as base64, empty when the code has none.
+    pub fn logo_snapshot(&self) -> String {
+        self.logo
+            .as_ref()
+            .map(|logo| BASE64_STANDARD.encode(logo))
+            .unwrap_or_default()
+    }
+
+    pub fn restore_options(&mut self, snapshot: &str) -> Result<(), serde_json::Error> {
+        let snapshot = serde_json::from_str::<QrOptionsSnapshot>(snapshot)?;
+        self.symbology = snapshot.symbology;
+        self.format = snapshot.format;
+        self.size = snapshot.size;
+        self.error_correction = snapshot.error_correction;
+        self.foreground = snapshot.foreground;
+        self.background = snapshot.background;
+        self.quiet_zone = snapshot.quiet_zone;
+        self.frame = snapshot.frame;
+        self.caption = snapshot.caption;
+        self.logo_size = snapshot.logo_size;
+        Ok(())
+    }
+
+    pub fn restore_logo(&mut self, snapshot: &str) -> Result<(), base64::DecodeError> {
+        self.logo = match snapshot {
+            "" => None,
+            logo => Some(BASE64_STANDARD.decode(logo)?),
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QrReconcileJob {
+    pub id: i64,
+    pub status: String,
+    pub dry_run: bool,
+    pub orphaned: Vec<String>,
+    pub missing: Vec<String>,
+    pub deleted: i64,
+    pub regenerated: i64,
+    pub failed: i64,
+    pub error: Option<String>,
+    /// The worker running the job, it holds the job until `leased_until`.
+    pub leased_by: Option<String>,
+    pub leased_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// What a finished run found and fixed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QrReconcileReport {
+    /// Stored images no link uses, such as those of deleted links.
+    pub orphaned: Vec<String>,
+    /// Images links should have but that were never stored.
+    pub missing: Vec<String>,
+    pub deleted: i64,
+    pub regenerated: i64,
+    pub failed: i64,
+}
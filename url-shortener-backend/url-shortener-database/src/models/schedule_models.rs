@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct UrlSchedule {
+    pub id: i64,
+    pub url_id: String,
+    pub destination: String,
+    pub scheduled_at: DateTime<Utc>,
+    pub applied_at: Option<DateTime<Utc>>,
+}
@@ -4,4 +4,6 @@ use serde::{Deserialize, Serialize};
 pub struct Url {
     pub id: String,
     pub url: String,
+    pub track_conversions: bool,
+    pub campaign: Option<String>,
 }
@@ -0,0 +1,121 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::click_models::ClickCount;
+use crate::models::errors::DatabaseError;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait ClickCountRepositoryTrait: Inject {
+    async fn apply_flush(
+        &self,
+        flush_id: &str,
+        counts: Vec<ClickCount>,
+    ) -> Result<bool, Report<DatabaseError>>;
+    async fn total(&self, url_id: &str) -> Result<i64, Report<DatabaseError>>;
+    /// Forgets the flushes applied before `before`, their claims can no longer be replayed.
+    async fn prune_flushes(&self, before: DateTime<Utc>) -> Result<u64, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickCountRepositoryTrait with ClickCountRepository::new(db))]
+pub struct ClickCountRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl ClickCountRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ClickCountRepositoryTrait for ClickCountRepository {
+    async fn apply_flush(
+        &self,
+        flush_id: &str,
+        counts: Vec<ClickCount>,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let mut tx = self
+            .db
+            .get()
+            .begin()
+            .await
+            .attach_printable_lazy(|| "Failed to start flush transaction")
+            .change_context(DatabaseError)?;
+
+        let inserted = sqlx::query(
+            "INSERT INTO click_count_flushes (id) VALUES ($1) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(flush_id)
+        .execute(&mut *tx)
+        .await
+        .attach_printable_lazy(|| format!("Failed to record flush: {}", flush_id))
+        .change_context(DatabaseError)?;
+
+        if inserted.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        let url_ids: Vec<&str> = counts.iter().map(|c| c.url_id.as_str()).collect();
+        let days: Vec<NaiveDate> = counts.iter().map(|c| c.day).collect();
+        let values: Vec<i64> = counts.iter().map(|c| c.count).collect();
+
+        // counts of links deleted in the meantime are dropped instead of failing the whole flush
+        sqlx::query(
+            r#"
+        INSERT INTO click_counts (url_id, day, count)
+        SELECT c.url_id, c.day, c.count
+        FROM UNNEST($1::text[], $2::date[], $3::bigint[]) AS c (url_id, day, count)
+        WHERE EXISTS (SELECT 1 FROM urls u WHERE u.id = c.url_id)
+        ON CONFLICT (url_id, day) DO UPDATE SET count = click_counts.count + EXCLUDED.count
+        "#,
+        )
+        .bind(&url_ids)
+        .bind(&days)
+        .bind(&values)
+        .execute(&mut *tx)
+        .await
+        .attach_printable_lazy(|| format!("Failed to flush click counts: {}", flush_id))
+        .change_context(DatabaseError)?;
+
+        tx.commit()
+            .await
+            .attach_printable_lazy(|| format!("Failed to commit flush: {}", flush_id))
+            .change_context(DatabaseError)?;
+
+        Ok(true)
+    }
+
+    async fn total(&self, url_id: &str) -> Result<i64, Report<DatabaseError>> {
+        let total = sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE(SUM(count), 0)::bigint FROM click_counts WHERE url_id = $1",
+        )
+        .bind(url_id)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to get click count for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(total)
+    }
+
+    async fn prune_flushes(&self, before: DateTime<Utc>) -> Result<u64, Report<DatabaseError>> {
+        let result = sqlx::query("DELETE FROM click_count_flushes WHERE flushed_at < $1")
+            .bind(before)
+            .execute(&self.db.get())
+            .await
+            .attach_printable_lazy(|| format!("Failed to prune flushes before: {}", before))
+            .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// for mocking
+impl Inject for MockClickCountRepositoryTrait {}
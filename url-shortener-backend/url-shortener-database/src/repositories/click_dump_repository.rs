@@ -0,0 +1,77 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::dump_models::ClickDump;
+use crate::models::errors::DatabaseError;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait ClickDumpRepositoryTrait: Inject {
+    async fn last_dumped_day(&self) -> Result<Option<NaiveDate>, Report<DatabaseError>>;
+    async fn record_dump(
+        &self,
+        day: NaiveDate,
+        object_key: &str,
+        row_count: i64,
+    ) -> Result<ClickDump, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickDumpRepositoryTrait with ClickDumpRepository::new(db))]
+pub struct ClickDumpRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl ClickDumpRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ClickDumpRepositoryTrait for ClickDumpRepository {
+    async fn last_dumped_day(&self) -> Result<Option<NaiveDate>, Report<DatabaseError>> {
+        let day = sqlx::query_scalar::<_, Option<NaiveDate>>("SELECT MAX(day) FROM click_dumps")
+            .fetch_one(&self.db.get())
+            .await
+            .attach_printable_lazy(|| "Failed to find the last click dump")
+            .change_context(DatabaseError)?;
+
+        Ok(day)
+    }
+
+    async fn record_dump(
+        &self,
+        day: NaiveDate,
+        object_key: &str,
+        row_count: i64,
+    ) -> Result<ClickDump, Report<DatabaseError>> {
+        // re-dumping a day overwrites the same object, so the latest run wins
+        let dump = sqlx::query_as::<_, ClickDump>(
+            r#"
+        INSERT INTO click_dumps (day, object_key, row_count)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (day) DO UPDATE
+        SET object_key = EXCLUDED.object_key, row_count = EXCLUDED.row_count, dumped_at = NOW()
+        RETURNING day, object_key, row_count, dumped_at
+        "#,
+        )
+        .bind(day)
+        .bind(object_key)
+        .bind(row_count)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to record click dump: {}", day))
+        .change_context(DatabaseError)?;
+
+        Ok(dump)
+    }
+}
+
+// for mocking
+impl Inject for MockClickDumpRepositoryTrait {}
@@ -0,0 +1,518 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::click_models::{
+    Click, ClickRecord, ClickSelection, LocationCount, PeriodCount, ValueCount,
+};
+use crate::models::errors::DatabaseError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use sqlx::{Postgres, QueryBuilder};
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait ClickRepositoryTrait: Inject {
+    async fn insert_batch(&self, clicks: Vec<Click>) -> Result<u64, Report<DatabaseError>>;
+    async fn count(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<i64, Report<DatabaseError>>;
+    async fn count_by_period(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        period: &str,
+        timezone: &str,
+        include_bots: bool,
+    ) -> Result<Vec<PeriodCount>, Report<DatabaseError>>;
+    async fn count_by_referrer(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>>;
+    async fn count_by_user_agent(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>>;
+    async fn count_by_location(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<LocationCount>, Report<DatabaseError>>;
+    async fn count_by_source(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>>;
+    async fn find_url_id_by_click_id(
+        &self,
+        click_id: &str,
+    ) -> Result<Option<String>, Report<DatabaseError>>;
+    async fn find_page(
+        &self,
+        selection: &ClickSelection,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<ClickRecord>, Report<DatabaseError>>;
+    async fn find_all_page(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<ClickRecord>, Report<DatabaseError>>;
+    async fn first_clicked_at(&self) -> Result<Option<DateTime<Utc>>, Report<DatabaseError>>;
+    async fn purge_before(
+        &self,
+        cutoff: DateTime<Utc>,
+        aggregate: bool,
+        limit: i64,
+    ) -> Result<u64, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ClickRepositoryTrait with ClickRepository::new(db))]
+pub struct ClickRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl ClickRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ClickRepositoryTrait for ClickRepository {
+    async fn insert_batch(&self, clicks: Vec<Click>) -> Result<u64, Report<DatabaseError>> {
+        if clicks.is_empty() {
+            return Ok(0);
+        }
+
+        let mut query_builder = QueryBuilder::<Postgres>::new(
+            "INSERT INTO clicks (url_id, clicked_at, referrer, user_agent, ip_hash, destination, is_bot, bot_reason, country, region, city, source, click_id) ",
+        );
+        query_builder.push_values(&clicks, |mut row, click| {
+            row.push_bind(&click.url_id)
+                .push_bind(click.clicked_at)
+                .push_bind(&click.referrer)
+                .push_bind(&click.user_agent)
+                .push_bind(&click.ip_hash)
+                .push_bind(&click.destination)
+                .push_bind(click.is_bot)
+                .push_bind(&click.bot_reason)
+                .push_bind(&click.country)
+                .push_bind(&click.region)
+                .push_bind(&click.city)
+                .push_bind(&click.source)
+                .push_bind(&click.click_id);
+        });
+
+        let result = query_builder
+            .build()
+            .execute(&self.db.get())
+            .await
+            .attach_printable_lazy(|| format!("Failed to insert {} clicks", clicks.len()))
+            .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn count(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<i64, Report<DatabaseError>> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+        SELECT
+            (SELECT COUNT(*)
+            FROM clicks
+            WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot))
+            + (SELECT COALESCE(SUM(count), 0)::BIGINT
+            FROM click_aggregates
+            WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($4 OR NOT is_bot))
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(include_bots)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count clicks for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(count)
+    }
+
+    async fn count_by_period(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        period: &str,
+        timezone: &str,
+        include_bots: bool,
+    ) -> Result<Vec<PeriodCount>, Report<DatabaseError>> {
+        // clicks are bucketed once through the (url_id, clicked_at) index before the empty periods
+        // are filled in, periods are generated in the requested timezone so days and weeks start
+        // at local midnight, rolled up clicks only know their UTC day and count in the period holding
+        // its midnight
+        let counts = sqlx::query_as::<_, PeriodCount>(
+            r#"
+        WITH buckets AS (
+            SELECT local_start, SUM(count)::BIGINT AS count
+            FROM (
+                SELECT date_trunc($4, clicked_at AT TIME ZONE $5) AS local_start, COUNT(*) AS count
+                FROM clicks
+                WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($6 OR NOT is_bot)
+                GROUP BY 1
+                UNION ALL
+                SELECT date_trunc($4, (day::timestamp AT TIME ZONE 'UTC') AT TIME ZONE $5), SUM(count)
+                FROM click_aggregates
+                WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($6 OR NOT is_bot)
+                GROUP BY 1
+            ) counted
+            GROUP BY local_start
+        ),
+        periods AS (
+            SELECT generate_series(
+                date_trunc($4, $2 AT TIME ZONE $5),
+                $3 AT TIME ZONE $5,
+                ('1 ' || $4)::interval
+            ) AS local_start
+        )
+        SELECT p.local_start AT TIME ZONE $5 AS period_start, COALESCE(b.count, 0) AS count
+        FROM buckets b
+        RIGHT JOIN periods p ON b.local_start = p.local_start
+        ORDER BY p.local_start
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(period)
+        .bind(timezone)
+        .bind(include_bots)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count clicks by {} for url: {}", period, url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn count_by_referrer(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>> {
+        let counts = sqlx::query_as::<_, ValueCount>(
+            r#"
+        SELECT value, SUM(count)::BIGINT AS count
+        FROM (
+            SELECT referrer AS value, COUNT(*) AS count
+            FROM clicks
+            WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot)
+            GROUP BY referrer
+            UNION ALL
+            SELECT NULLIF(referrer, ''), SUM(count)
+            FROM click_aggregates
+            WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($4 OR NOT is_bot)
+            GROUP BY 1
+        ) counted
+        GROUP BY value
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(include_bots)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count referrers for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn count_by_user_agent(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>> {
+        let counts = sqlx::query_as::<_, ValueCount>(
+            r#"
+        SELECT value, SUM(count)::BIGINT AS count
+        FROM (
+            SELECT user_agent AS value, COUNT(*) AS count
+            FROM clicks
+            WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot)
+            GROUP BY user_agent
+            UNION ALL
+            SELECT NULLIF(user_agent, ''), SUM(count)
+            FROM click_aggregates
+            WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($4 OR NOT is_bot)
+            GROUP BY 1
+        ) counted
+        GROUP BY value
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(include_bots)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count user agents for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn count_by_location(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<LocationCount>, Report<DatabaseError>> {
+        let counts = sqlx::query_as::<_, LocationCount>(
+            r#"
+        SELECT country, region, city, SUM(count)::BIGINT AS count
+        FROM (
+            SELECT country, region, city, COUNT(*) AS count
+            FROM clicks
+            WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot)
+            GROUP BY country, region, city
+            UNION ALL
+            SELECT NULL, NULL, NULL, SUM(count)
+            FROM click_aggregates
+            WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($4 OR NOT is_bot)
+        ) counted
+        WHERE count > 0
+        GROUP BY country, region, city
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(include_bots)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count locations for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn count_by_source(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+    ) -> Result<Vec<ValueCount>, Report<DatabaseError>> {
+        let counts = sqlx::query_as::<_, ValueCount>(
+            r#"
+        SELECT value, SUM(count)::BIGINT AS count
+        FROM (
+            SELECT source AS value, COUNT(*) AS count
+            FROM clicks
+            WHERE url_id = $1 AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot)
+            GROUP BY source
+            UNION ALL
+            SELECT source, SUM(count)
+            FROM click_aggregates
+            WHERE url_id = $1 AND (day::timestamp AT TIME ZONE 'UTC') >= $2 AND (day::timestamp AT TIME ZONE 'UTC') < $3 AND ($4 OR NOT is_bot)
+            GROUP BY source
+        ) counted
+        GROUP BY value
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .bind(include_bots)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count sources for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn find_url_id_by_click_id(
+        &self,
+        click_id: &str,
+    ) -> Result<Option<String>, Report<DatabaseError>> {
+        let url_id =
+            sqlx::query_scalar::<_, String>("SELECT url_id FROM clicks WHERE click_id = $1")
+                .bind(click_id)
+                .fetch_optional(&self.db.get())
+                .await
+                .attach_printable_lazy(|| format!("Failed to find click: {}", click_id))
+                .change_context(DatabaseError)?;
+
+        Ok(url_id)
+    }
+
+    async fn find_page(
+        &self,
+        selection: &ClickSelection,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_bots: bool,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<ClickRecord>, Report<DatabaseError>> {
+        let (filter, value) = match selection {
+            ClickSelection::Link(url_id) => ("url_id = $1", url_id),
+            ClickSelection::Campaign(campaign) => (
+                "url_id IN (SELECT id FROM urls WHERE campaign = $1)",
+                campaign,
+            ),
+        };
+        let query = format!(
+            r#"
+        SELECT id, url_id, clicked_at, referrer, user_agent, ip_hash, destination, is_bot, bot_reason, country, region, city, source
+        FROM clicks
+        WHERE {} AND clicked_at >= $2 AND clicked_at < $3 AND ($4 OR NOT is_bot) AND id > $5
+        ORDER BY id
+        LIMIT $6
+        "#,
+            filter
+        );
+        let clicks = sqlx::query_as::<_, ClickRecord>(&query)
+            .bind(value)
+            .bind(from)
+            .bind(to)
+            .bind(include_bots)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&self.db.get())
+            .await
+            .attach_printable_lazy(|| {
+                format!(
+                    "Failed to find clicks for: {:?} after: {}",
+                    selection, after_id
+                )
+            })
+            .change_context(DatabaseError)?;
+
+        Ok(clicks)
+    }
+
+    async fn find_all_page(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<ClickRecord>, Report<DatabaseError>> {
+        let clicks = sqlx::query_as::<_, ClickRecord>(
+            r#"
+        SELECT id, url_id, clicked_at, referrer, user_agent, ip_hash, destination, is_bot, bot_reason, country, region, city, source
+        FROM clicks
+        WHERE clicked_at >= $1 AND clicked_at < $2 AND id > $3
+        ORDER BY id
+        LIMIT $4
+        "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find clicks from: {} to: {} after: {}", from, to, after_id))
+        .change_context(DatabaseError)?;
+
+        Ok(clicks)
+    }
+
+    async fn first_clicked_at(&self) -> Result<Option<DateTime<Utc>>, Report<DatabaseError>> {
+        let first =
+            sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT MIN(clicked_at) FROM clicks")
+                .fetch_one(&self.db.get())
+                .await
+                .attach_printable_lazy(|| "Failed to find the first click")
+                .change_context(DatabaseError)?;
+
+        Ok(first)
+    }
+
+    async fn purge_before(
+        &self,
+        cutoff: DateTime<Utc>,
+        aggregate: bool,
+        limit: i64,
+    ) -> Result<u64, Report<DatabaseError>> {
+        // one statement, so a batch is either aggregated and deleted or left untouched
+        let purged = sqlx::query_scalar::<_, i64>(
+            r#"
+        WITH expired AS (
+            SELECT id FROM clicks
+            WHERE clicked_at < $1
+            ORDER BY id
+            LIMIT $3
+            FOR UPDATE SKIP LOCKED
+        ),
+        deleted AS (
+            DELETE FROM clicks c
+            USING expired e
+            WHERE c.id = e.id
+            RETURNING c.url_id, c.clicked_at, c.referrer, c.user_agent, c.is_bot, c.source
+        ),
+        aggregated AS (
+            INSERT INTO click_aggregates (url_id, day, referrer, user_agent, is_bot, source, count)
+            SELECT url_id, (clicked_at AT TIME ZONE 'UTC')::date, COALESCE(referrer, ''), COALESCE(user_agent, ''), is_bot, source, COUNT(*)
+            FROM deleted
+            WHERE $2
+            GROUP BY 1, 2, 3, 4, 5, 6
+            ON CONFLICT (url_id, day, referrer, user_agent, is_bot, source)
+            DO UPDATE SET count = click_aggregates.count + EXCLUDED.count
+        )
+        SELECT COUNT(*) FROM deleted
+        "#,
+        )
+        .bind(cutoff)
+        .bind(aggregate)
+        .bind(limit)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to purge clicks before: {}", cutoff))
+        .change_context(DatabaseError)?;
+
+        Ok(purged as u64)
+    }
+}
+
+// for mocking
+impl Inject for MockClickRepositoryTrait {}
@@ -0,0 +1,117 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::conversion_models::{Conversion, ConversionCount};
+use crate::models::errors::DatabaseError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait ConversionRepositoryTrait: Inject {
+    async fn create(&self, conversion: Conversion) -> Result<bool, Report<DatabaseError>>;
+    async fn count_by_event(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ConversionCount>, Report<DatabaseError>>;
+    async fn count_converted_clicks(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<i64, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ConversionRepositoryTrait with ConversionRepository::new(db))]
+pub struct ConversionRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl ConversionRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ConversionRepositoryTrait for ConversionRepository {
+    async fn create(&self, conversion: Conversion) -> Result<bool, Report<DatabaseError>> {
+        // postbacks are retried by their senders, a repeated event is acknowledged but not stored again
+        let result = sqlx::query(
+            r#"
+        INSERT INTO conversions (click_id, url_id, event, value)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (click_id, event) DO NOTHING
+        "#,
+        )
+        .bind(&conversion.click_id)
+        .bind(&conversion.url_id)
+        .bind(&conversion.event)
+        .bind(conversion.value)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to create conversion: {:?}", conversion))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_by_event(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ConversionCount>, Report<DatabaseError>> {
+        let counts = sqlx::query_as::<_, ConversionCount>(
+            r#"
+        SELECT event, COUNT(*) AS count, SUM(value) AS value
+        FROM conversions
+        WHERE url_id = $1 AND converted_at >= $2 AND converted_at < $3
+        GROUP BY event
+        ORDER BY count DESC, event
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count conversions for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(counts)
+    }
+
+    async fn count_converted_clicks(
+        &self,
+        url_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<i64, Report<DatabaseError>> {
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+        SELECT COUNT(DISTINCT click_id)
+        FROM conversions
+        WHERE url_id = $1 AND converted_at >= $2 AND converted_at < $3
+        "#,
+        )
+        .bind(url_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to count converted clicks for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(count)
+    }
+}
+
+// for mocking
+impl Inject for MockConversionRepositoryTrait {}
@@ -0,0 +1,201 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::history_models::{UrlHistory, QR_LOGO_FIELD, QR_OPTIONS_FIELD, URL_FIELD};
+use crate::repositories::qr_options_repository::{find_options_for_update, upsert_options};
+use crate::repositories::qr_upload_repository::queue_upload;
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use sqlx::PgConnection;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait HistoryRepositoryTrait: Inject {
+    async fn find_by_url(&self, url_id: &str) -> Result<Vec<UrlHistory>, Report<DatabaseError>>;
+    /// Restores the entry's value, a restored QR code is also queued for upload when asked.
+    async fn rollback(
+        &self,
+        url_id: &str,
+        id: i64,
+        changed_by: &str,
+        queue_qr_upload: bool,
+    ) -> Result<Option<UrlHistory>, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn HistoryRepositoryTrait with HistoryRepository::new(db))]
+pub struct HistoryRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl HistoryRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+/// Appends a history entry on the given connection, so callers can record
+/// the change inside the same transaction that performs it.
+pub(crate) async fn record_change(
+    con: &mut PgConnection,
+    url_id: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: &str,
+    changed_by: &str,
+) -> Result<UrlHistory, Report<DatabaseError>> {
+    sqlx::query_as::<_, UrlHistory>(
+        r#"
+        INSERT INTO url_history (url_id, field, old_value, new_value, changed_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, url_id, field, old_value, new_value, changed_by, changed_at
+        "#,
+    )
+    .bind(url_id)
+    .bind(field)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(changed_by)
+    .fetch_one(con)
+    .await
+    .attach_printable_lazy(|| format!("Failed to record {} change for url: {}", field, url_id))
+    .change_context(DatabaseError)
+}
+
+#[async_trait]
+impl HistoryRepositoryTrait for HistoryRepository {
+    async fn find_by_url(&self, url_id: &str) -> Result<Vec<UrlHistory>, Report<DatabaseError>> {
+        let history = sqlx::query_as::<_, UrlHistory>(
+            r#"
+        SELECT id, url_id, field, old_value, new_value, changed_by, changed_at
+        FROM url_history
+        WHERE url_id = $1
+        ORDER BY id DESC
+        "#,
+        )
+        .bind(url_id)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find history for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(history)
+    }
+
+    async fn rollback(
+        &self,
+        url_id: &str,
+        id: i64,
+        changed_by: &str,
+        queue_qr_upload: bool,
+    ) -> Result<Option<UrlHistory>, Report<DatabaseError>> {
+        let mut tx = self
+            .db
+            .get()
+            .begin()
+            .await
+            .attach_printable_lazy(|| "Failed to start rollback transaction")
+            .change_context(DatabaseError)?;
+
+        let entry = sqlx::query_as::<_, UrlHistory>(
+            r#"
+        SELECT id, url_id, field, old_value, new_value, changed_by, changed_at
+        FROM url_history
+        WHERE id = $1 AND url_id = $2
+        "#,
+        )
+        .bind(id)
+        .bind(url_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .attach_printable_lazy(|| {
+            format!("Failed to find history entry {} for url: {}", id, url_id)
+        })
+        .change_context(DatabaseError)?;
+
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        let current = match entry.field.as_str() {
+            URL_FIELD => {
+                let current = sqlx::query_scalar::<_, String>(
+                    "SELECT url FROM urls WHERE id = $1 FOR UPDATE",
+                )
+                .bind(url_id)
+                .fetch_one(&mut *tx)
+                .await
+                .attach_printable_lazy(|| format!("Failed to lock url: {}", url_id))
+                .change_context(DatabaseError)?;
+
+                sqlx::query("UPDATE urls SET url = $1 WHERE id = $2")
+                    .bind(&entry.new_value)
+                    .bind(url_id)
+                    .execute(&mut *tx)
+                    .await
+                    .attach_printable_lazy(|| format!("Failed to roll back url: {:?}", entry))
+                    .change_context(DatabaseError)?;
+
+                current
+            }
+            QR_OPTIONS_FIELD | QR_LOGO_FIELD => {
+                let Some(mut options) = find_options_for_update(&mut tx, url_id).await? else {
+                    return Err(Report::new(DatabaseError).attach_printable(format!(
+                        "No qr options to roll back for url: {}",
+                        url_id
+                    )));
+                };
+
+                let current = if entry.field == QR_OPTIONS_FIELD {
+                    let current = options.options_snapshot();
+                    options
+                        .restore_options(&entry.new_value)
+                        .attach_printable_lazy(|| format!("Invalid qr options entry: {:?}", entry))
+                        .change_context(DatabaseError)?;
+                    current
+                } else {
+                    let current = options.logo_snapshot();
+                    options
+                        .restore_logo(&entry.new_value)
+                        .attach_printable_lazy(|| format!("Invalid qr logo entry: {}", entry.id))
+                        .change_context(DatabaseError)?;
+                    current
+                };
+
+                upsert_options(&mut tx, &options).await?;
+                if queue_qr_upload {
+                    queue_upload(&mut tx, url_id).await?;
+                }
+
+                current
+            }
+            field => {
+                return Err(Report::new(DatabaseError)
+                    .attach_printable(format!("Unsupported history field: {}", field)))
+            }
+        };
+
+        let change = record_change(
+            &mut tx,
+            url_id,
+            &entry.field,
+            Some(&current),
+            &entry.new_value,
+            changed_by,
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .attach_printable_lazy(|| "Failed to commit rollback transaction")
+            .change_context(DatabaseError)?;
+
+        Ok(Some(change))
+    }
+}
+
+// for mocking
+impl Inject for MockHistoryRepositoryTrait {}
@@ -1 +1,11 @@
+pub mod click_count_repository;
+pub mod click_dump_repository;
+pub mod click_repository;
+pub mod conversion_repository;
+pub mod history_repository;
+pub mod qr_backfill_repository;
+pub mod qr_options_repository;
+pub mod qr_reconcile_repository;
+pub mod qr_upload_repository;
+pub mod schedule_repository;
 pub mod url_repository;
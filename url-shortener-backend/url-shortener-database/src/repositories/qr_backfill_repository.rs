@@ -0,0 +1,211 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::qr_backfill_models::QrBackfillJob;
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait QrBackfillRepositoryTrait: Inject {
+    /// Queues a backfill, `None` when another one is still pending or running.
+    async fn create(
+        &self,
+        domain: &str,
+        destination_prefix: Option<String>,
+        total: i64,
+    ) -> Result<Option<QrBackfillJob>, Report<DatabaseError>>;
+    async fn find(&self, id: i64) -> Result<Option<QrBackfillJob>, Report<DatabaseError>>;
+    /// Leases the active backfill to the caller and marks it running, unless another worker
+    /// holds an unexpired lease on it.
+    async fn claim_active(
+        &self,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<Option<QrBackfillJob>, Report<DatabaseError>>;
+    /// Saves progress and renews the lease, `false` once the lease was taken over.
+    async fn update_progress(
+        &self,
+        id: i64,
+        leased_by: &str,
+        lease_seconds: i64,
+        last_url_id: &str,
+        processed: i64,
+        failed: i64,
+    ) -> Result<bool, Report<DatabaseError>>;
+    /// Ends the backfill and releases its lease, `false` once the lease was taken over.
+    async fn update_status(
+        &self,
+        id: i64,
+        leased_by: &str,
+        status: &str,
+        error: Option<String>,
+    ) -> Result<bool, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrBackfillRepositoryTrait with QrBackfillRepository::new(db))]
+pub struct QrBackfillRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl QrBackfillRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl QrBackfillRepositoryTrait for QrBackfillRepository {
+    async fn create(
+        &self,
+        domain: &str,
+        destination_prefix: Option<String>,
+        total: i64,
+    ) -> Result<Option<QrBackfillJob>, Report<DatabaseError>> {
+        // the unique index on active jobs makes a second one conflict, even when both start at once
+        let job = sqlx::query_as::<_, QrBackfillJob>(
+            r#"
+        INSERT INTO qr_backfill_jobs (domain, destination_prefix, total)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING id, status, domain, destination_prefix, last_url_id, total, processed, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        "#,
+        )
+        .bind(domain)
+        .bind(&destination_prefix)
+        .bind(total)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to create qr backfill for: {}", domain))
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn find(&self, id: i64) -> Result<Option<QrBackfillJob>, Report<DatabaseError>> {
+        let job = sqlx::query_as::<_, QrBackfillJob>(
+            r#"
+        SELECT id, status, domain, destination_prefix, last_url_id, total, processed, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        FROM qr_backfill_jobs
+        WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find qr backfill: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn claim_active(
+        &self,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<Option<QrBackfillJob>, Report<DatabaseError>> {
+        // SKIP LOCKED lets several host instances poll at once, only one of them gets the job
+        let job = sqlx::query_as::<_, QrBackfillJob>(
+            r#"
+        UPDATE qr_backfill_jobs
+        SET status = 'running',
+            leased_by = $1,
+            leased_until = NOW() + make_interval(secs => $2),
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id
+            FROM qr_backfill_jobs
+            WHERE status IN ('pending', 'running')
+                AND (leased_until IS NULL OR leased_until < NOW())
+            ORDER BY id
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, status, domain, destination_prefix, last_url_id, total, processed, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        "#,
+        )
+        .bind(leased_by)
+        .bind(lease_seconds as f64)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| "Failed to claim the active qr backfill")
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn update_progress(
+        &self,
+        id: i64,
+        leased_by: &str,
+        lease_seconds: i64,
+        last_url_id: &str,
+        processed: i64,
+        failed: i64,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            r#"
+        UPDATE qr_backfill_jobs
+        SET last_url_id = $4,
+            processed = $5,
+            failed = $6,
+            leased_until = NOW() + make_interval(secs => $3),
+            updated_at = NOW()
+        WHERE id = $1 AND leased_by = $2
+        "#,
+        )
+        .bind(id)
+        .bind(leased_by)
+        .bind(lease_seconds as f64)
+        .bind(last_url_id)
+        .bind(processed)
+        .bind(failed)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to save qr backfill progress: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_status(
+        &self,
+        id: i64,
+        leased_by: &str,
+        status: &str,
+        error: Option<String>,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            r#"
+        UPDATE qr_backfill_jobs
+        SET status = $3,
+            error = $4,
+            leased_by = NULL,
+            leased_until = NULL,
+            updated_at = NOW(),
+            finished_at = CASE WHEN $3 IN ('completed', 'failed') THEN NOW() END
+        WHERE id = $1 AND leased_by = $2
+        "#,
+        )
+        .bind(id)
+        .bind(leased_by)
+        .bind(status)
+        .bind(&error)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to update qr backfill {} to {}", id, status))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// for mocking
+impl Inject for MockQrBackfillRepositoryTrait {}
@@ -0,0 +1,225 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::history_models::{QR_LOGO_FIELD, QR_OPTIONS_FIELD};
+use crate::models::qr_models::UrlQrOptions;
+use crate::repositories::history_repository::record_change;
+use crate::repositories::qr_upload_repository::queue_upload;
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait QrOptionsRepositoryTrait: Inject {
+    async fn find(&self, url_id: &str) -> Result<Option<UrlQrOptions>, Report<DatabaseError>>;
+    /// The stored image format of each of the links that has options, read in one query.
+    async fn find_formats(
+        &self,
+        url_ids: &[String],
+    ) -> Result<HashMap<String, String>, Report<DatabaseError>>;
+    /// Saves the options and records what changed in the link's history, in one transaction
+    /// that also queues the upload of the new image when `queue_qr_upload` is set.
+    async fn save(
+        &self,
+        options: UrlQrOptions,
+        changed_by: &str,
+        queue_qr_upload: bool,
+    ) -> Result<UrlQrOptions, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrOptionsRepositoryTrait with QrOptionsRepository::new(db))]
+pub struct QrOptionsRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl QrOptionsRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+/// Locks the link's options on the caller's connection until its transaction ends.
+pub(crate) async fn find_options_for_update(
+    con: &mut PgConnection,
+    url_id: &str,
+) -> Result<Option<UrlQrOptions>, Report<DatabaseError>> {
+    sqlx::query_as::<_, UrlQrOptions>(
+        r#"
+    SELECT url_id, symbology, format, size, error_correction, foreground, background, quiet_zone,
+        frame, caption, logo, logo_size
+    FROM url_qr_options
+    WHERE url_id = $1
+    FOR UPDATE
+    "#,
+    )
+    .bind(url_id)
+    .fetch_optional(con)
+    .await
+    .attach_printable_lazy(|| format!("Failed to lock qr options for url: {}", url_id))
+    .change_context(DatabaseError)
+}
+
+/// Saves the link's options on the caller's connection, so they can commit together with the link,
+/// and records the options and the logo in its history when they changed.
+pub(crate) async fn save_options(
+    con: &mut PgConnection,
+    options: &UrlQrOptions,
+    changed_by: &str,
+) -> Result<UrlQrOptions, Report<DatabaseError>> {
+    let current = find_options_for_update(&mut *con, &options.url_id).await?;
+    let saved = upsert_options(&mut *con, options).await?;
+
+    let old_options = current.as_ref().map(UrlQrOptions::options_snapshot);
+    let new_options = saved.options_snapshot();
+    if old_options.as_ref() != Some(&new_options) {
+        record_change(
+            &mut *con,
+            &saved.url_id,
+            QR_OPTIONS_FIELD,
+            old_options.as_deref(),
+            &new_options,
+            changed_by,
+        )
+        .await?;
+    }
+
+    // a link saved without a logo has nothing to record
+    let old_logo = current.as_ref().map(UrlQrOptions::logo_snapshot);
+    let new_logo = saved.logo_snapshot();
+    if old_logo.as_deref().unwrap_or_default() != new_logo {
+        record_change(
+            &mut *con,
+            &saved.url_id,
+            QR_LOGO_FIELD,
+            old_logo.as_deref(),
+            &new_logo,
+            changed_by,
+        )
+        .await?;
+    }
+
+    Ok(saved)
+}
+
+pub(crate) async fn upsert_options(
+    con: &mut PgConnection,
+    options: &UrlQrOptions,
+) -> Result<UrlQrOptions, Report<DatabaseError>> {
+    sqlx::query_as::<_, UrlQrOptions>(
+        r#"
+    INSERT INTO url_qr_options (url_id, symbology, format, size, error_correction, foreground, background, quiet_zone,
+        frame, caption, logo, logo_size)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    ON CONFLICT (url_id) DO UPDATE
+    SET symbology = EXCLUDED.symbology,
+        format = EXCLUDED.format,
+        size = EXCLUDED.size,
+        error_correction = EXCLUDED.error_correction,
+        foreground = EXCLUDED.foreground,
+        background = EXCLUDED.background,
+        quiet_zone = EXCLUDED.quiet_zone,
+        frame = EXCLUDED.frame,
+        caption = EXCLUDED.caption,
+        logo = EXCLUDED.logo,
+        logo_size = EXCLUDED.logo_size,
+        updated_at = NOW()
+    RETURNING url_id, symbology, format, size, error_correction, foreground, background, quiet_zone,
+        frame, caption, logo, logo_size
+    "#,
+    )
+    .bind(&options.url_id)
+    .bind(&options.symbology)
+    .bind(&options.format)
+    .bind(options.size)
+    .bind(&options.error_correction)
+    .bind(&options.foreground)
+    .bind(&options.background)
+    .bind(options.quiet_zone)
+    .bind(&options.frame)
+    .bind(&options.caption)
+    .bind(&options.logo)
+    .bind(options.logo_size)
+    .fetch_one(con)
+    .await
+    .attach_printable_lazy(|| format!("Failed to save qr options for url: {}", options.url_id))
+    .change_context(DatabaseError)
+}
+
+#[async_trait]
+impl QrOptionsRepositoryTrait for QrOptionsRepository {
+    async fn find(&self, url_id: &str) -> Result<Option<UrlQrOptions>, Report<DatabaseError>> {
+        let options = sqlx::query_as::<_, UrlQrOptions>(
+            r#"
+        SELECT url_id, symbology, format, size, error_correction, foreground, background, quiet_zone,
+            frame, caption, logo, logo_size
+        FROM url_qr_options
+        WHERE url_id = $1
+        "#,
+        )
+        .bind(url_id)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find qr options for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(options)
+    }
+
+    async fn find_formats(
+        &self,
+        url_ids: &[String],
+    ) -> Result<HashMap<String, String>, Report<DatabaseError>> {
+        let formats = sqlx::query_as::<_, (String, String)>(
+            r#"
+        SELECT url_id, format
+        FROM url_qr_options
+        WHERE url_id = ANY($1)
+        "#,
+        )
+        .bind(url_ids)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find qr formats for {} urls", url_ids.len()))
+        .change_context(DatabaseError)?;
+
+        Ok(formats.into_iter().collect())
+    }
+
+    async fn save(
+        &self,
+        options: UrlQrOptions,
+        changed_by: &str,
+        queue_qr_upload: bool,
+    ) -> Result<UrlQrOptions, Report<DatabaseError>> {
+        let mut tx = self
+            .db
+            .get()
+            .begin()
+            .await
+            .attach_printable_lazy(|| "Failed to start qr options transaction")
+            .change_context(DatabaseError)?;
+
+        let saved = save_options(&mut tx, &options, changed_by).await?;
+        if queue_qr_upload {
+            queue_upload(&mut tx, &options.url_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .attach_printable_lazy(|| {
+                format!("Failed to commit qr options for url: {}", options.url_id)
+            })
+            .change_context(DatabaseError)?;
+
+        Ok(saved)
+    }
+}
+
+// for mocking
+impl Inject for MockQrOptionsRepositoryTrait {}
@@ -0,0 +1,227 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::qr_reconcile_models::{QrReconcileJob, QrReconcileReport};
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait QrReconcileRepositoryTrait: Inject {
+    /// Queues a reconciliation, `None` when another one is still pending or running.
+    async fn create(&self, dry_run: bool) -> Result<Option<QrReconcileJob>, Report<DatabaseError>>;
+    async fn find(&self, id: i64) -> Result<Option<QrReconcileJob>, Report<DatabaseError>>;
+    /// Leases the active reconciliation to the caller and marks it running, unless another
+    /// worker holds an unexpired lease on it.
+    async fn claim_active(
+        &self,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<Option<QrReconcileJob>, Report<DatabaseError>>;
+    /// `false` once the lease was taken over.
+    async fn renew_lease(
+        &self,
+        id: i64,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<bool, Report<DatabaseError>>;
+    /// Saves the findings and releases the lease, `false` once the lease was taken over.
+    async fn complete(
+        &self,
+        id: i64,
+        leased_by: &str,
+        report: QrReconcileReport,
+    ) -> Result<bool, Report<DatabaseError>>;
+    /// Ends the reconciliation with the error and releases the lease, `false` once the lease was taken over.
+    async fn fail(
+        &self,
+        id: i64,
+        leased_by: &str,
+        error: String,
+    ) -> Result<bool, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrReconcileRepositoryTrait with QrReconcileRepository::new(db))]
+pub struct QrReconcileRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl QrReconcileRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl QrReconcileRepositoryTrait for QrReconcileRepository {
+    async fn create(&self, dry_run: bool) -> Result<Option<QrReconcileJob>, Report<DatabaseError>> {
+        // the unique index on active jobs makes a second one conflict, even when both start at once
+        let job = sqlx::query_as::<_, QrReconcileJob>(
+            r#"
+        INSERT INTO qr_reconcile_jobs (dry_run)
+        VALUES ($1)
+        ON CONFLICT DO NOTHING
+        RETURNING id, status, dry_run, orphaned, missing, deleted, regenerated, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        "#,
+        )
+        .bind(dry_run)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| "Failed to create qr reconciliation")
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn find(&self, id: i64) -> Result<Option<QrReconcileJob>, Report<DatabaseError>> {
+        let job = sqlx::query_as::<_, QrReconcileJob>(
+            r#"
+        SELECT id, status, dry_run, orphaned, missing, deleted, regenerated, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        FROM qr_reconcile_jobs
+        WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find qr reconciliation: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn claim_active(
+        &self,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<Option<QrReconcileJob>, Report<DatabaseError>> {
+        // SKIP LOCKED lets several host instances poll at once, only one of them gets the job
+        let job = sqlx::query_as::<_, QrReconcileJob>(
+            r#"
+        UPDATE qr_reconcile_jobs
+        SET status = 'running',
+            leased_by = $1,
+            leased_until = NOW() + make_interval(secs => $2),
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id
+            FROM qr_reconcile_jobs
+            WHERE status IN ('pending', 'running')
+                AND (leased_until IS NULL OR leased_until < NOW())
+            ORDER BY id
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, status, dry_run, orphaned, missing, deleted, regenerated, failed, error,
+            leased_by, leased_until, created_at, updated_at, finished_at
+        "#,
+        )
+        .bind(leased_by)
+        .bind(lease_seconds as f64)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| "Failed to claim the active qr reconciliation")
+        .change_context(DatabaseError)?;
+
+        Ok(job)
+    }
+
+    async fn renew_lease(
+        &self,
+        id: i64,
+        leased_by: &str,
+        lease_seconds: i64,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            r#"
+        UPDATE qr_reconcile_jobs
+        SET leased_until = NOW() + make_interval(secs => $3), updated_at = NOW()
+        WHERE id = $1 AND leased_by = $2
+        "#,
+        )
+        .bind(id)
+        .bind(leased_by)
+        .bind(lease_seconds as f64)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to renew qr reconciliation lease: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn complete(
+        &self,
+        id: i64,
+        leased_by: &str,
+        report: QrReconcileReport,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            r#"
+        UPDATE qr_reconcile_jobs
+        SET status = 'completed',
+            orphaned = $3,
+            missing = $4,
+            deleted = $5,
+            regenerated = $6,
+            failed = $7,
+            leased_by = NULL,
+            leased_until = NULL,
+            updated_at = NOW(),
+            finished_at = NOW()
+        WHERE id = $1 AND leased_by = $2
+        "#,
+        )
+        .bind(id)
+        .bind(leased_by)
+        .bind(&report.orphaned)
+        .bind(&report.missing)
+        .bind(report.deleted)
+        .bind(report.regenerated)
+        .bind(report.failed)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to complete qr reconciliation: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn fail(
+        &self,
+        id: i64,
+        leased_by: &str,
+        error: String,
+    ) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            r#"
+        UPDATE qr_reconcile_jobs
+        SET status = 'failed',
+            error = $3,
+            leased_by = NULL,
+            leased_until = NULL,
+            updated_at = NOW(),
+            finished_at = NOW()
+        WHERE id = $1 AND leased_by = $2
+        "#,
+        )
+        .bind(id)
+        .bind(leased_by)
+        .bind(&error)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to fail qr reconciliation: {}", id))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// for mocking
+impl Inject for MockQrReconcileRepositoryTrait {}
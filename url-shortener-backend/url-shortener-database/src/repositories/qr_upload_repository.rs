@@ -0,0 +1,115 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::qr_upload_models::QrUpload;
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use sqlx::PgConnection;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait QrUploadRepositoryTrait: Inject {
+    /// The uploads whose next attempt is due, the longest waiting first.
+    async fn find_due(&self, limit: i64) -> Result<Vec<QrUpload>, Report<DatabaseError>>;
+    async fn complete(&self, url_id: &str) -> Result<(), Report<DatabaseError>>;
+    async fn record_failure(
+        &self,
+        url_id: &str,
+        error: String,
+        retry_in_secs: i64,
+    ) -> Result<(), Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn QrUploadRepositoryTrait with QrUploadRepository::new(db))]
+pub struct QrUploadRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl QrUploadRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+/// Queues the link's QR upload, on the caller's connection so it commits with the link.
+pub(crate) async fn queue_upload(
+    con: &mut PgConnection,
+    url_id: &str,
+) -> Result<(), Report<DatabaseError>> {
+    sqlx::query(
+        "INSERT INTO qr_upload_outbox (url_id) VALUES ($1) ON CONFLICT (url_id) DO NOTHING",
+    )
+    .bind(url_id)
+    .execute(con)
+    .await
+    .attach_printable_lazy(|| format!("Failed to queue qr upload for url: {}", url_id))
+    .change_context(DatabaseError)?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl QrUploadRepositoryTrait for QrUploadRepository {
+    async fn find_due(&self, limit: i64) -> Result<Vec<QrUpload>, Report<DatabaseError>> {
+        let uploads = sqlx::query_as::<_, QrUpload>(
+            r#"
+        SELECT url_id, attempts, last_error, next_attempt_at, created_at
+        FROM qr_upload_outbox
+        WHERE next_attempt_at <= NOW()
+        ORDER BY next_attempt_at
+        LIMIT $1
+        "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| "Failed to find due qr uploads")
+        .change_context(DatabaseError)?;
+
+        Ok(uploads)
+    }
+
+    async fn complete(&self, url_id: &str) -> Result<(), Report<DatabaseError>> {
+        sqlx::query("DELETE FROM qr_upload_outbox WHERE url_id = $1")
+            .bind(url_id)
+            .execute(&self.db.get())
+            .await
+            .attach_printable_lazy(|| format!("Failed to complete qr upload for url: {}", url_id))
+            .change_context(DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        url_id: &str,
+        error: String,
+        retry_in_secs: i64,
+    ) -> Result<(), Report<DatabaseError>> {
+        sqlx::query(
+            r#"
+        UPDATE qr_upload_outbox
+        SET attempts = attempts + 1,
+            last_error = $2,
+            next_attempt_at = NOW() + make_interval(secs => $3)
+        WHERE url_id = $1
+        "#,
+        )
+        .bind(url_id)
+        .bind(&error)
+        .bind(retry_in_secs as f64)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to record qr upload failure for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(())
+    }
+}
+
+// for mocking
+impl Inject for MockQrUploadRepositoryTrait {}
@@ -0,0 +1,185 @@
+use crate::database::pool::PgPoolWrapper;
+use crate::models::errors::DatabaseError;
+use crate::models::history_models::{CHANGED_BY_SCHEDULER, URL_FIELD};
+use crate::models::schedule_models::UrlSchedule;
+use crate::repositories::history_repository::record_change;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use mockall::automock;
+use std::sync::Arc;
+
+#[async_trait]
+#[automock]
+pub trait ScheduleRepositoryTrait: Inject {
+    async fn create(
+        &self,
+        url_id: &str,
+        destination: &str,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<UrlSchedule, Report<DatabaseError>>;
+    async fn find_pending(&self, url_id: &str) -> Result<Vec<UrlSchedule>, Report<DatabaseError>>;
+    async fn delete_pending(&self, url_id: &str, id: i64) -> Result<bool, Report<DatabaseError>>;
+    async fn apply_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<UrlSchedule>, Report<DatabaseError>>;
+}
+
+#[derive(Inject)]
+#[coi(provides pub dyn ScheduleRepositoryTrait with ScheduleRepository::new(db))]
+pub struct ScheduleRepository {
+    #[coi(inject)]
+    pub db: Arc<PgPoolWrapper>,
+}
+
+impl ScheduleRepository {
+    pub fn new(db: Arc<PgPoolWrapper>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ScheduleRepositoryTrait for ScheduleRepository {
+    async fn create(
+        &self,
+        url_id: &str,
+        destination: &str,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<UrlSchedule, Report<DatabaseError>> {
+        let result = sqlx::query_as::<_, UrlSchedule>(
+            r#"
+        INSERT INTO url_schedules (url_id, destination, scheduled_at)
+        VALUES ($1, $2, $3)
+        RETURNING id, url_id, destination, scheduled_at, applied_at
+        "#,
+        )
+        .bind(url_id)
+        .bind(destination)
+        .bind(scheduled_at)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to create schedule for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(result)
+    }
+
+    async fn find_pending(&self, url_id: &str) -> Result<Vec<UrlSchedule>, Report<DatabaseError>> {
+        let schedules = sqlx::query_as::<_, UrlSchedule>(
+            r#"
+        SELECT id, url_id, destination, scheduled_at, applied_at
+        FROM url_schedules
+        WHERE url_id = $1 AND applied_at IS NULL
+        ORDER BY scheduled_at
+        "#,
+        )
+        .bind(url_id)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find schedules for url: {}", url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(schedules)
+    }
+
+    async fn delete_pending(&self, url_id: &str, id: i64) -> Result<bool, Report<DatabaseError>> {
+        let result = sqlx::query(
+            "DELETE FROM url_schedules WHERE id = $1 AND url_id = $2 AND applied_at IS NULL",
+        )
+        .bind(id)
+        .bind(url_id)
+        .execute(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to delete schedule {} for url: {}", id, url_id))
+        .change_context(DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn apply_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<UrlSchedule>, Report<DatabaseError>> {
+        let mut tx = self
+            .db
+            .get()
+            .begin()
+            .await
+            .attach_printable_lazy(|| "Failed to start schedule transaction")
+            .change_context(DatabaseError)?;
+
+        // SKIP LOCKED lets several host instances run the worker without applying a row twice
+        let due = sqlx::query_as::<_, UrlSchedule>(
+            r#"
+        SELECT id, url_id, destination, scheduled_at, applied_at
+        FROM url_schedules
+        WHERE applied_at IS NULL AND scheduled_at <= $1
+        ORDER BY scheduled_at
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+        "#,
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await
+        .attach_printable_lazy(|| "Failed to fetch due schedules")
+        .change_context(DatabaseError)?;
+
+        let mut applied = Vec::with_capacity(due.len());
+        for mut schedule in due {
+            let current =
+                sqlx::query_scalar::<_, String>("SELECT url FROM urls WHERE id = $1 FOR UPDATE")
+                    .bind(&schedule.url_id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .attach_printable_lazy(|| format!("Failed to lock url: {}", schedule.url_id))
+                    .change_context(DatabaseError)?;
+
+            sqlx::query("UPDATE urls SET url = $1 WHERE id = $2")
+                .bind(&schedule.destination)
+                .bind(&schedule.url_id)
+                .execute(&mut *tx)
+                .await
+                .attach_printable_lazy(|| format!("Failed to apply schedule: {:?}", schedule))
+                .change_context(DatabaseError)?;
+
+            sqlx::query("UPDATE url_schedules SET applied_at = $1 WHERE id = $2")
+                .bind(now)
+                .bind(schedule.id)
+                .execute(&mut *tx)
+                .await
+                .attach_printable_lazy(|| {
+                    format!("Failed to mark schedule applied: {}", schedule.id)
+                })
+                .change_context(DatabaseError)?;
+
+            record_change(
+                &mut tx,
+                &schedule.url_id,
+                URL_FIELD,
+                Some(&current),
+                &schedule.destination,
+                CHANGED_BY_SCHEDULER,
+            )
+            .await?;
+
+            schedule.applied_at = Some(now);
+            applied.push(schedule);
+        }
+
+        tx.commit()
+            .await
+            .attach_printable_lazy(|| "Failed to commit schedule transaction")
+            .change_context(DatabaseError)?;
+
+        Ok(applied)
+    }
+}
+
+// for mocking
+impl Inject for MockScheduleRepositoryTrait {}
@@ -1,6 +1,11 @@
 use crate::database::pool::PgPoolWrapper;
 use crate::models::errors::DatabaseError;
+use crate::models::history_models::{CHANGED_BY_API, URL_FIELD};
+use crate::models::qr_models::UrlQrOptions;
 use crate::models::url_models::Url;
+use crate::repositories::history_repository::record_change;
+use crate::repositories::qr_options_repository::save_options;
+use crate::repositories::qr_upload_repository::queue_upload;
 use async_trait::async_trait;
 use coi::Inject;
 use error_stack::{Report, ResultExt};
@@ -10,8 +15,25 @@ use std::sync::Arc;
 #[async_trait]
 #[automock]
 pub trait UrlRepositoryTrait: Inject {
-    async fn create(&self, url: Url) -> Result<Url, Report<DatabaseError>>;
+    /// Saves the link together with its QR options and, when asked, a queued upload of its image,
+    /// so none of them exist without the others.
+    async fn create(
+        &self,
+        url: Url,
+        qr_options: Option<UrlQrOptions>,
+        queue_qr_upload: bool,
+    ) -> Result<Url, Report<DatabaseError>>;
     async fn find(&self, short_url: &str) -> Result<Option<Url>, Report<DatabaseError>>;
+    /// Links in id order after `after_id`, optionally only those whose destination starts with the prefix.
+    async fn find_page(
+        &self,
+        after_id: &str,
+        destination_prefix: Option<String>,
+        limit: i64,
+    ) -> Result<Vec<Url>, Report<DatabaseError>>;
+    async fn count(&self, destination_prefix: Option<String>)
+        -> Result<i64, Report<DatabaseError>>;
+    async fn campaign_exists(&self, campaign: &str) -> Result<bool, Report<DatabaseError>>;
 }
 
 #[derive(Inject)]
@@ -29,34 +51,126 @@ impl UrlRepository {
 
 #[async_trait]
 impl UrlRepositoryTrait for UrlRepository {
-    async fn create(&self, url: Url) -> Result<Url, Report<DatabaseError>> {
+    async fn create(
+        &self,
+        url: Url,
+        qr_options: Option<UrlQrOptions>,
+        queue_qr_upload: bool,
+    ) -> Result<Url, Report<DatabaseError>> {
+        let mut tx = self
+            .db
+            .get()
+            .begin()
+            .await
+            .attach_printable_lazy(|| "Failed to start create transaction")
+            .change_context(DatabaseError)?;
+
         let result = sqlx::query_as::<_, Url>(
             r#"
-        INSERT INTO urls (id, url)
-        VALUES ($1, $2)
-        RETURNING id, url
+        INSERT INTO urls (id, url, track_conversions, campaign)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, url, track_conversions, campaign
         "#,
         )
         .bind(&url.id)
         .bind(&url.url)
-        .fetch_one(&self.db.get())
+        .bind(url.track_conversions)
+        .bind(&url.campaign)
+        .fetch_one(&mut *tx)
         .await
-        .attach_printable_lazy(move || format!("Failed to create url: {:?}", url))
+        .attach_printable_lazy(|| format!("Failed to create url: {:?}", url))
         .change_context(DatabaseError)?;
 
+        record_change(
+            &mut tx,
+            &result.id,
+            URL_FIELD,
+            None,
+            &result.url,
+            CHANGED_BY_API,
+        )
+        .await?;
+        if let Some(qr_options) = &qr_options {
+            save_options(&mut tx, qr_options, CHANGED_BY_API).await?;
+        }
+        if queue_qr_upload {
+            queue_upload(&mut tx, &result.id).await?;
+        }
+
+        tx.commit()
+            .await
+            .attach_printable_lazy(move || format!("Failed to commit url: {:?}", url))
+            .change_context(DatabaseError)?;
+
         Ok(result)
     }
 
     async fn find(&self, short_url: &str) -> Result<Option<Url>, Report<DatabaseError>> {
-        let user = sqlx::query_as::<_, Url>("SELECT id, url FROM urls WHERE id = $1")
-            .bind(short_url)
-            .fetch_optional(&self.db.get())
-            .await
-            .attach_printable_lazy(|| format!("Failed to find url with id: {}", short_url))
-            .change_context(DatabaseError)?;
+        let user = sqlx::query_as::<_, Url>(
+            "SELECT id, url, track_conversions, campaign FROM urls WHERE id = $1",
+        )
+        .bind(short_url)
+        .fetch_optional(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find url with id: {}", short_url))
+        .change_context(DatabaseError)?;
 
         Ok(user)
     }
+
+    async fn find_page(
+        &self,
+        after_id: &str,
+        destination_prefix: Option<String>,
+        limit: i64,
+    ) -> Result<Vec<Url>, Report<DatabaseError>> {
+        let urls = sqlx::query_as::<_, Url>(
+            r#"
+        SELECT id, url, track_conversions, campaign
+        FROM urls
+        WHERE id > $1 AND ($2::TEXT IS NULL OR starts_with(url, $2))
+        ORDER BY id
+        LIMIT $3
+        "#,
+        )
+        .bind(after_id)
+        .bind(&destination_prefix)
+        .bind(limit)
+        .fetch_all(&self.db.get())
+        .await
+        .attach_printable_lazy(|| format!("Failed to find urls after id: {}", after_id))
+        .change_context(DatabaseError)?;
+
+        Ok(urls)
+    }
+
+    async fn count(
+        &self,
+        destination_prefix: Option<String>,
+    ) -> Result<i64, Report<DatabaseError>> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM urls WHERE $1::TEXT IS NULL OR starts_with(url, $1)",
+        )
+        .bind(&destination_prefix)
+        .fetch_one(&self.db.get())
+        .await
+        .attach_printable_lazy(|| "Failed to count urls")
+        .change_context(DatabaseError)?;
+
+        Ok(count)
+    }
+
+    async fn campaign_exists(&self, campaign: &str) -> Result<bool, Report<DatabaseError>> {
+        let exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM urls WHERE campaign = $1)")
+                .bind(campaign)
+                .fetch_one(&self.db.get())
+                .await
+                .attach_printable_lazy(|| format!("Failed to find campaign: {}", campaign))
+                .change_context(DatabaseError)?;
+
+        Ok(exists)
+    }
 }
 
 // for mocking
@@ -6,40 +6,250 @@ use coi::container;
 use coi_actix_web::AppExt;
 use dotenv::dotenv;
 use std::env;
+use std::time::Duration;
+use url_shortener_application::qr::cache::create_qr_image_cache;
+use url_shortener_application::queues::click_event_hub::{
+    create_click_event_hub, ClickEventHubTrait,
+};
+use url_shortener_application::queues::click_queue::create_click_queue;
+use url_shortener_application::services::click_count_service::{
+    ClickCountServiceProvider, ClickCountServiceTrait,
+};
+use url_shortener_application::services::click_dump_service::{
+    ClickDumpServiceProvider, ClickDumpServiceTrait,
+};
+use url_shortener_application::services::click_event_service::ClickEventServiceProvider;
+use url_shortener_application::services::click_service::ClickServiceProvider;
+use url_shortener_application::services::conversion_service::ConversionServiceProvider;
+use url_shortener_application::services::export_service::ExportServiceProvider;
+use url_shortener_application::services::history_service::HistoryServiceProvider;
+use url_shortener_application::services::qr_backfill_service::{
+    QrBackfillServiceProvider, QrBackfillServiceTrait,
+};
+use url_shortener_application::services::qr_reconcile_service::{
+    QrReconcileServiceProvider, QrReconcileServiceTrait,
+};
+use url_shortener_application::services::qr_service::QrServiceProvider;
+use url_shortener_application::services::qr_upload_service::{
+    QrUploadServiceProvider, QrUploadServiceTrait,
+};
+use url_shortener_application::services::retention_service::{
+    RetentionServiceProvider, RetentionServiceTrait,
+};
+use url_shortener_application::services::schedule_service::{
+    ScheduleServiceProvider, ScheduleServiceTrait,
+};
+use url_shortener_application::services::stats_service::StatsServiceProvider;
+use url_shortener_application::services::top_links_service::TopLinksServiceProvider;
 use url_shortener_application::services::url_service::UrlServiceProvider;
-use url_shortener_database::database::pool::{crete_database_connection, PgPoolProvider};
+use url_shortener_application::workers::click_count_worker::run_click_count_worker;
+use url_shortener_application::workers::click_dump_worker::run_click_dump_worker;
+use url_shortener_application::workers::click_event_worker::run_click_event_worker;
+use url_shortener_application::workers::click_worker::run_click_worker;
+use url_shortener_application::workers::qr_backfill_worker::run_qr_backfill_worker;
+use url_shortener_application::workers::qr_reconcile_worker::run_qr_reconcile_worker;
+use url_shortener_application::workers::qr_upload_worker::run_qr_upload_worker;
+use url_shortener_application::workers::retention_worker::run_retention_worker;
+use url_shortener_application::workers::schedule_worker::run_schedule_worker;
+use url_shortener_database::database::pool::{
+    crete_database_connection, run_migrations, PgPoolProvider,
+};
+use url_shortener_database::repositories::click_count_repository::ClickCountRepositoryProvider;
+use url_shortener_database::repositories::click_dump_repository::ClickDumpRepositoryProvider;
+use url_shortener_database::repositories::click_repository::{
+    ClickRepositoryProvider, ClickRepositoryTrait,
+};
+use url_shortener_database::repositories::conversion_repository::ConversionRepositoryProvider;
+use url_shortener_database::repositories::history_repository::HistoryRepositoryProvider;
+use url_shortener_database::repositories::qr_backfill_repository::QrBackfillRepositoryProvider;
+use url_shortener_database::repositories::qr_options_repository::QrOptionsRepositoryProvider;
+use url_shortener_database::repositories::qr_reconcile_repository::QrReconcileRepositoryProvider;
+use url_shortener_database::repositories::qr_upload_repository::QrUploadRepositoryProvider;
+use url_shortener_database::repositories::schedule_repository::ScheduleRepositoryProvider;
 use url_shortener_database::repositories::url_repository::UrlRepositoryProvider;
-use url_shortener_infrastructure::redis::config::create_redis_pool;
+use url_shortener_infrastructure::geoip::config::create_geoip_reader;
+use url_shortener_infrastructure::geoip::geoip_reader::GeoIpReaderProvider;
+use url_shortener_infrastructure::redis::config::{create_redis_connection, create_redis_pool};
+use url_shortener_infrastructure::redis::pubsub::RedisSubscriber;
 use url_shortener_infrastructure::redis::redis_client::RedisClientProvider;
-use url_shortener_infrastructure::s3::config::create_s3_client;
+use url_shortener_infrastructure::s3::config::{
+    create_s3_client, file_storage_directory, BucketConfig,
+};
 use url_shortener_infrastructure::s3::s3_client::S3ClientProvider;
 use url_shortener_webapi::register_api;
 
 const MAX_REQUEST_PER_SEC_ALLOWED: u32 = 10;
 const SECONDS_PER_REQUEST: u64 = 3;
+const DEFAULT_SCHEDULE_WORKER_INTERVAL_SECS: u64 = 30;
+const CLICK_QUEUE_CAPACITY: usize = 10_000;
+const CLICK_BATCH_SIZE: usize = 500;
+const CLICK_FLUSH_INTERVAL_MILLIS: u64 = 1_000;
+const CLICK_COUNT_FLUSH_INTERVAL_SECS: u64 = 60;
+const CLICK_EVENT_HUB_CAPACITY: usize = 1_000;
+const CLICK_EVENT_RETRY_INTERVAL_SECS: u64 = 5;
+const CLICK_DUMP_INTERVAL_SECS: u64 = 60 * 60;
+const RETENTION_INTERVAL_SECS: u64 = 60 * 60;
+const QR_IMAGE_CACHE_CAPACITY: usize = 500;
+const QR_BACKFILL_INTERVAL_SECS: u64 = 30;
+const QR_RECONCILE_INTERVAL_SECS: u64 = 30;
+const QR_UPLOAD_RETRY_INTERVAL_SECS: u64 = 30;
 
 #[actix_web::main]
 pub async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init();
 
+    // stored address hashes and visitor fingerprints are keyed by it, without one they could be
+    // recomputed by anyone from the address
+    env::var("IP_HASH_SALT")
+        .ok()
+        .filter(|salt| !salt.is_empty())
+        .expect("IP_HASH_SALT must be set");
     let pg_pool = crete_database_connection()
         .await
         .expect("Failed to connect to database");
+    run_migrations(&pg_pool)
+        .await
+        .expect("Failed to run database migrations");
     let db = PgPoolProvider::new(pg_pool);
-    let s3_client = create_s3_client().await;
-    let s3_client_wrapper = S3ClientProvider::new(s3_client);
+    let s3_client_wrapper = match file_storage_directory() {
+        Some(directory) => S3ClientProvider::file_system(directory),
+        None => S3ClientProvider::new(create_s3_client().await, BucketConfig::from_env()),
+    };
     let redis_client = create_redis_pool();
-    let redis_client_wrapper = RedisClientProvider::new(redis_client);
+    let redis_subscriber = RedisSubscriber::new(redis_client.clone());
+    let redis_client_wrapper =
+        RedisClientProvider::new(create_redis_connection(redis_client).await);
+    let geo_ip_reader = GeoIpReaderProvider::new(create_geoip_reader());
+    let (click_queue, click_receiver) = create_click_queue(CLICK_QUEUE_CAPACITY);
+    let click_event_hub = create_click_event_hub(CLICK_EVENT_HUB_CAPACITY);
+    let qr_image_cache = create_qr_image_cache(QR_IMAGE_CACHE_CAPACITY);
 
     let container = container! {
         redis_client_wrapper => redis_client_wrapper; singleton,
         s3_client_wrapper => s3_client_wrapper; singleton,
         db => db; singleton,
+        geo_ip_reader => geo_ip_reader; singleton,
+        click_queue => click_queue; singleton,
+        click_event_hub => click_event_hub; singleton,
+        qr_image_cache => qr_image_cache; singleton,
         url_service => UrlServiceProvider; scoped,
         url_repository => UrlRepositoryProvider; scoped,
+        schedule_service => ScheduleServiceProvider; scoped,
+        schedule_repository => ScheduleRepositoryProvider; scoped,
+        history_service => HistoryServiceProvider; scoped,
+        history_repository => HistoryRepositoryProvider; scoped,
+        click_service => ClickServiceProvider; scoped,
+        click_repository => ClickRepositoryProvider; scoped,
+        stats_service => StatsServiceProvider; scoped,
+        click_count_service => ClickCountServiceProvider; scoped,
+        click_count_repository => ClickCountRepositoryProvider; scoped,
+        click_event_service => ClickEventServiceProvider; scoped,
+        export_service => ExportServiceProvider; scoped,
+        click_dump_service => ClickDumpServiceProvider; scoped,
+        click_dump_repository => ClickDumpRepositoryProvider; scoped,
+        retention_service => RetentionServiceProvider; scoped,
+        top_links_service => TopLinksServiceProvider; scoped,
+        conversion_service => ConversionServiceProvider; scoped,
+        conversion_repository => ConversionRepositoryProvider; scoped,
+        qr_service => QrServiceProvider; scoped,
+        qr_options_repository => QrOptionsRepositoryProvider; scoped,
+        qr_backfill_service => QrBackfillServiceProvider; scoped,
+        qr_backfill_repository => QrBackfillRepositoryProvider; scoped,
+        qr_reconcile_service => QrReconcileServiceProvider; scoped,
+        qr_reconcile_repository => QrReconcileRepositoryProvider; scoped,
+        qr_upload_service => QrUploadServiceProvider; scoped,
+        qr_upload_repository => QrUploadRepositoryProvider; scoped,
     };
 
+    let schedule_service = container
+        .scoped()
+        .resolve::<dyn ScheduleServiceTrait>("schedule_service")
+        .expect("Failed to resolve schedule service");
+    let schedule_worker_interval = env::var("SCHEDULE_WORKER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SCHEDULE_WORKER_INTERVAL_SECS);
+    actix_web::rt::spawn(run_schedule_worker(
+        schedule_service,
+        Duration::from_secs(schedule_worker_interval),
+    ));
+
+    let click_repository = container
+        .scoped()
+        .resolve::<dyn ClickRepositoryTrait>("click_repository")
+        .expect("Failed to resolve click repository");
+    actix_web::rt::spawn(run_click_worker(
+        click_receiver,
+        click_repository,
+        CLICK_BATCH_SIZE,
+        Duration::from_millis(CLICK_FLUSH_INTERVAL_MILLIS),
+    ));
+
+    let click_count_service = container
+        .scoped()
+        .resolve::<dyn ClickCountServiceTrait>("click_count_service")
+        .expect("Failed to resolve click count service");
+    actix_web::rt::spawn(run_click_count_worker(
+        click_count_service,
+        Duration::from_secs(CLICK_COUNT_FLUSH_INTERVAL_SECS),
+    ));
+
+    let click_event_hub = container
+        .scoped()
+        .resolve::<dyn ClickEventHubTrait>("click_event_hub")
+        .expect("Failed to resolve click event hub");
+    actix_web::rt::spawn(run_click_event_worker(
+        redis_subscriber,
+        click_event_hub,
+        Duration::from_secs(CLICK_EVENT_RETRY_INTERVAL_SECS),
+    ));
+
+    let click_dump_service = container
+        .scoped()
+        .resolve::<dyn ClickDumpServiceTrait>("click_dump_service")
+        .expect("Failed to resolve click dump service");
+    actix_web::rt::spawn(run_click_dump_worker(
+        click_dump_service,
+        Duration::from_secs(CLICK_DUMP_INTERVAL_SECS),
+    ));
+
+    let retention_service = container
+        .scoped()
+        .resolve::<dyn RetentionServiceTrait>("retention_service")
+        .expect("Failed to resolve retention service");
+    actix_web::rt::spawn(run_retention_worker(
+        retention_service,
+        Duration::from_secs(RETENTION_INTERVAL_SECS),
+    ));
+
+    let qr_backfill_service = container
+        .scoped()
+        .resolve::<dyn QrBackfillServiceTrait>("qr_backfill_service")
+        .expect("Failed to resolve qr backfill service");
+    actix_web::rt::spawn(run_qr_backfill_worker(
+        qr_backfill_service,
+        Duration::from_secs(QR_BACKFILL_INTERVAL_SECS),
+    ));
+
+    let qr_reconcile_service = container
+        .scoped()
+        .resolve::<dyn QrReconcileServiceTrait>("qr_reconcile_service")
+        .expect("Failed to resolve qr reconcile service");
+    actix_web::rt::spawn(run_qr_reconcile_worker(
+        qr_reconcile_service,
+        Duration::from_secs(QR_RECONCILE_INTERVAL_SECS),
+    ));
+
+    let qr_upload_service = container
+        .scoped()
+        .resolve::<dyn QrUploadServiceTrait>("qr_upload_service")
+        .expect("Failed to resolve qr upload service");
+    actix_web::rt::spawn(run_qr_upload_worker(
+        qr_upload_service,
+        Duration::from_secs(QR_UPLOAD_RETRY_INTERVAL_SECS),
+    ));
+
     let governor_conf = GovernorConfigBuilder::default()
         .seconds_per_request(SECONDS_PER_REQUEST)
         .burst_size(MAX_REQUEST_PER_SEC_ALLOWED)
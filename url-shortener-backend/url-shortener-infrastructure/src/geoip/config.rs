@@ -0,0 +1,19 @@
+use log::{info, warn};
+use maxminddb::Reader;
+use std::env;
+
+/// Geo lookups are optional, without `GEOIP_DATABASE_PATH` clicks are stored without a location.
+pub fn create_geoip_reader() -> Option<Reader<Vec<u8>>> {
+    let path = env::var("GEOIP_DATABASE_PATH").ok()?;
+
+    match Reader::open_readfile(&path) {
+        Ok(reader) => {
+            info!("Loaded GeoIP database: {}", path);
+            Some(reader)
+        }
+        Err(e) => {
+            warn!("Failed to load GeoIP database {}: {}", path, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use coi::{Inject, Provide};
+use log::warn;
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use mockall::automock;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const NAME_LANGUAGE: &str = "en";
+
+/// The coarse location of an address, the address itself is never part of it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GeoLocation {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+#[automock]
+pub trait GeoIpReaderTrait: Inject {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation>;
+}
+
+#[derive(Inject)]
+pub struct GeoIpReader(Option<Arc<Reader<Vec<u8>>>>);
+
+impl GeoIpReaderTrait for GeoIpReader {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let reader = self.0.as_ref()?;
+
+        let city = match reader.lookup::<geoip2::City>(ip) {
+            Ok(city) => city,
+            Err(MaxMindDBError::AddressNotFoundError(_)) => return None,
+            Err(e) => {
+                warn!("Failed to look up location: {}", e);
+                return None;
+            }
+        };
+
+        Some(GeoLocation {
+            country: city
+                .country
+                .and_then(|country| country.iso_code)
+                .map(str::to_string),
+            region: city
+                .subdivisions
+                .and_then(|subdivisions| subdivisions.into_iter().next())
+                .and_then(|subdivision| subdivision.iso_code)
+                .map(str::to_string),
+            city: city.city.and_then(|city| english_name(city.names)),
+        })
+    }
+}
+
+fn english_name(names: Option<BTreeMap<&str, &str>>) -> Option<String> {
+    names?.get(NAME_LANGUAGE).map(|name| name.to_string())
+}
+
+#[derive(Provide)]
+#[coi(provides dyn GeoIpReaderTrait with GeoIpReader(self.0.clone()))]
+pub struct GeoIpReaderProvider(Option<Arc<Reader<Vec<u8>>>>);
+
+impl GeoIpReaderProvider {
+    pub fn new(reader: Option<Reader<Vec<u8>>>) -> Self {
+        Self(reader.map(Arc::new))
+    }
+}
+
+// for mocking purposes
+impl Inject for MockGeoIpReaderTrait {}
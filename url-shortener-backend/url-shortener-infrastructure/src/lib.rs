@@ -1,2 +1,3 @@
-pub mod s3;
+pub mod geoip;
 pub mod redis;
+pub mod s3;
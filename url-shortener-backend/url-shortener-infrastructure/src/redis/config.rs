@@ -1,8 +1,15 @@
-use std::env;
+use redis::aio::ConnectionManager;
 use redis::Client;
+use std::env;
 
-pub fn create_redis_pool() ->Client {
+pub fn create_redis_pool() -> Client {
     let redis_conn_string = env::var("REDIS_URL").expect("REDIS_URL must be set");
-    let client = Client::open(redis_conn_string).unwrap();
-    client
-}
\ No newline at end of file
+    Client::open(redis_conn_string).unwrap()
+}
+
+/// One multiplexed connection shared by every clone, it reconnects on its own after it drops.
+pub async fn create_redis_connection(client: Client) -> ConnectionManager {
+    ConnectionManager::new(client)
+        .await
+        .expect("Failed to connect to redis")
+}
@@ -1,3 +1,4 @@
 pub mod redis_client;
 pub mod config;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod pubsub;
\ No newline at end of file
@@ -0,0 +1,47 @@
+use crate::redis::error::CacheError;
+use error_stack::{Report, ResultExt};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use log::warn;
+use redis::Client;
+
+pub struct RedisSubscriber(Client);
+
+impl RedisSubscriber {
+    pub fn new(client: Client) -> Self {
+        Self(client)
+    }
+
+    /// Opens a dedicated connection subscribed to the channel, the stream ends when the connection drops.
+    pub async fn subscribe(
+        &self,
+        channel: &str,
+    ) -> Result<BoxStream<'static, String>, Report<CacheError>> {
+        let mut pubsub = self
+            .0
+            .get_async_pubsub()
+            .await
+            .attach_printable_lazy(|| format!("Failed to set connection: {}", channel))
+            .change_context(CacheError)?;
+
+        pubsub
+            .subscribe(channel)
+            .await
+            .attach_printable_lazy(|| format!("Failed to subscribe: {}", channel))
+            .change_context(CacheError)?;
+
+        let messages = pubsub.into_on_message().filter_map(|msg| async move {
+            msg.get_payload::<String>()
+                .inspect_err(|e| {
+                    warn!(
+                        "Skipping malformed message on {}: {}",
+                        msg.get_channel_name(),
+                        e
+                    )
+                })
+                .ok()
+        });
+
+        Ok(messages.boxed())
+    }
+}
@@ -1,55 +1,436 @@
+use crate::redis::error::CacheError;
 use async_trait::async_trait;
 use coi::{Inject, Provide};
 use error_stack::{Report, ResultExt};
 use mockall::automock;
-use redis::{AsyncCommands, Client};
-use crate::redis::error::CacheError;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, Script};
+use std::collections::HashMap;
 
 #[async_trait]
 #[automock]
 pub trait RedisClientWrapperTrait: Inject {
     async fn get_cache(&self, key: &str) -> Result<String, Report<CacheError>>;
     async fn set_cache(&self, key: &str, value: &str) -> Result<(), Report<CacheError>>;
+    async fn set_cache_with_expiry(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: i64,
+    ) -> Result<(), Report<CacheError>>;
+    async fn delete_cache(&self, key: &str) -> Result<(), Report<CacheError>>;
+    async fn add_to_hyperloglog(
+        &self,
+        key: &str,
+        element: &str,
+        ttl_seconds: i64,
+    ) -> Result<(), Report<CacheError>>;
+    async fn count_hyperloglog(&self, keys: Vec<String>) -> Result<u64, Report<CacheError>>;
+    async fn count_each_hyperloglog(
+        &self,
+        keys: Vec<String>,
+    ) -> Result<Vec<u64>, Report<CacheError>>;
+    async fn increment_counters(
+        &self,
+        keys: Vec<String>,
+        pending_key: &str,
+        pending_member: &str,
+    ) -> Result<(), Report<CacheError>>;
+    async fn claim_counters(
+        &self,
+        pending_key: &str,
+        counter_prefix: &str,
+        claim_key: &str,
+        limit: usize,
+    ) -> Result<HashMap<String, i64>, Report<CacheError>>;
+    async fn get_counters(&self, key: &str) -> Result<HashMap<String, i64>, Report<CacheError>>;
+    /// The counter, 0 while the key does not exist.
+    async fn get_counter(&self, key: &str) -> Result<i64, Report<CacheError>>;
+    /// Deletes the claim and releases its flush id, the counters are only decremented by their
+    /// amounts while the claim is still held, so a retried release never decrements twice.
+    async fn release_claim(
+        &self,
+        claim_key: &str,
+        claimed_key: &str,
+        flush_id: &str,
+        decrements: Vec<(String, i64)>,
+    ) -> Result<(), Report<CacheError>>;
+    async fn get_set_members(&self, key: &str) -> Result<Vec<String>, Report<CacheError>>;
+    async fn add_to_set(&self, key: &str, member: &str) -> Result<(), Report<CacheError>>;
+    async fn increment_with_expiry(
+        &self,
+        key: &str,
+        ttl_seconds: i64,
+    ) -> Result<i64, Report<CacheError>>;
+    /// Stores the value unless the key already holds one, and returns whichever value the key holds.
+    async fn get_or_set_with_expiry(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: i64,
+    ) -> Result<String, Report<CacheError>>;
+    async fn publish(&self, channel: &str, message: &str) -> Result<(), Report<CacheError>>;
+    async fn increment_sorted_sets(
+        &self,
+        keys: Vec<(String, i64)>,
+        member: &str,
+    ) -> Result<(), Report<CacheError>>;
+    async fn top_of_sorted_sets(
+        &self,
+        keys: Vec<(String, f64)>,
+        destination: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, f64)>, Report<CacheError>>;
 }
 
-#[derive(Inject)]
-pub struct RedisClientWrapper(Client);
+/// Moves up to `ARGV[1]` pending counters into the claim hash in one step, so a
+/// counter is either still pending or claimed and can never be flushed twice.
+const CLAIM_COUNTERS_SCRIPT: &str = r#"
+local members = redis.call('SPOP', KEYS[1], ARGV[1])
+for _, member in ipairs(members) do
+    local value = redis.call('GETDEL', ARGV[2] .. member)
+    if value then
+        redis.call('HINCRBY', KEYS[2], member, value)
+    end
+end
+return redis.call('HGETALL', KEYS[2])
+"#;
 
+/// Takes `ARGV[1]` out of the claimed set, decrementing each `ARGV` key and amount pair after
+/// it only when the claim hash was still there to delete.
+const RELEASE_CLAIM_SCRIPT: &str = r#"
+if redis.call('DEL', KEYS[1]) == 1 then
+    for i = 2, #ARGV, 2 do
+        redis.call('DECRBY', ARGV[i], ARGV[i + 1])
+    end
+end
+redis.call('SREM', KEYS[2], ARGV[1])
+return 0
+"#;
+
+#[derive(Inject)]
+pub struct RedisClientWrapper(ConnectionManager);
 
 #[async_trait]
 impl RedisClientWrapperTrait for RedisClientWrapper {
     async fn get_cache(&self, key: &str) -> Result<String, Report<CacheError>> {
-        let mut con = self.0.get_multiplexed_tokio_connection().await
-            .attach_printable_lazy(|| format!("Failed to set connection: {}", key))
-            .change_context(CacheError)?;
-        
-        con.get(key).await
+        let mut con = self.0.clone();
+
+        con.get(key)
+            .await
             .attach_printable_lazy(|| format!("Failed to get cache: {}", key))
             .change_context(CacheError)
     }
-    
+
     async fn set_cache(&self, key: &str, value: &str) -> Result<(), Report<CacheError>> {
-        
-        let mut con = self.0.get_multiplexed_tokio_connection().await
-            .attach_printable_lazy(|| format!("Failed to set connection: {}", key))
+        let mut con = self.0.clone();
+
+        con.set::<_, _, ()>(key, value)
+            .await
+            .attach_printable_lazy(|| format!("Failed to set cache: {} - {}", key, value))
             .change_context(CacheError)?;
-        
-        con.set(key, value).await
+
+        Ok(())
+    }
+
+    async fn set_cache_with_expiry(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: i64,
+    ) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.set_ex::<_, _, ()>(key, value, ttl_seconds as u64)
+            .await
             .attach_printable_lazy(|| format!("Failed to set cache: {} - {}", key, value))
             .change_context(CacheError)?;
-        
+
+        Ok(())
+    }
+
+    async fn delete_cache(&self, key: &str) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.del::<_, ()>(key)
+            .await
+            .attach_printable_lazy(|| format!("Failed to delete cache: {}", key))
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn add_to_hyperloglog(
+        &self,
+        key: &str,
+        element: &str,
+        ttl_seconds: i64,
+    ) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        redis::pipe()
+            .pfadd(key, element)
+            .ignore()
+            .expire(key, ttl_seconds)
+            .ignore()
+            .query_async::<()>(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to add to hyperloglog: {}", key))
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn count_hyperloglog(&self, keys: Vec<String>) -> Result<u64, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.pfcount(&keys)
+            .await
+            .attach_printable_lazy(|| format!("Failed to count hyperloglog: {:?}", keys))
+            .change_context(CacheError)
+    }
+
+    async fn count_each_hyperloglog(
+        &self,
+        keys: Vec<String>,
+    ) -> Result<Vec<u64>, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.pfcount(key);
+        }
+
+        pipe.query_async(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to count hyperloglogs: {:?}", keys))
+            .change_context(CacheError)
+    }
+
+    async fn increment_counters(
+        &self,
+        keys: Vec<String>,
+        pending_key: &str,
+        pending_member: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for key in &keys {
+            pipe.incr(key, 1).ignore();
+        }
+        pipe.sadd(pending_key, pending_member).ignore();
+
+        pipe.query_async::<()>(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to increment counters: {:?}", keys))
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn claim_counters(
+        &self,
+        pending_key: &str,
+        counter_prefix: &str,
+        claim_key: &str,
+        limit: usize,
+    ) -> Result<HashMap<String, i64>, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        Script::new(CLAIM_COUNTERS_SCRIPT)
+            .key(pending_key)
+            .key(claim_key)
+            .arg(limit)
+            .arg(counter_prefix)
+            .invoke_async(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to claim counters: {}", claim_key))
+            .change_context(CacheError)
+    }
+
+    async fn get_counters(&self, key: &str) -> Result<HashMap<String, i64>, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.hgetall(key)
+            .await
+            .attach_printable_lazy(|| format!("Failed to get counters: {}", key))
+            .change_context(CacheError)
+    }
+
+    async fn get_counter(&self, key: &str) -> Result<i64, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let counter: Option<i64> = con
+            .get(key)
+            .await
+            .attach_printable_lazy(|| format!("Failed to get counter: {}", key))
+            .change_context(CacheError)?;
+
+        Ok(counter.unwrap_or_default())
+    }
+
+    async fn release_claim(
+        &self,
+        claim_key: &str,
+        claimed_key: &str,
+        flush_id: &str,
+        decrements: Vec<(String, i64)>,
+    ) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let script = Script::new(RELEASE_CLAIM_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        invocation.key(claim_key).key(claimed_key).arg(flush_id);
+        for (key, amount) in &decrements {
+            invocation.arg(key).arg(amount);
+        }
+
+        invocation
+            .invoke_async::<()>(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to release claim: {}", claim_key))
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn get_set_members(&self, key: &str) -> Result<Vec<String>, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.smembers(key)
+            .await
+            .attach_printable_lazy(|| format!("Failed to get set members: {}", key))
+            .change_context(CacheError)
+    }
+
+    async fn add_to_set(&self, key: &str, member: &str) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.sadd::<_, _, ()>(key, member)
+            .await
+            .attach_printable_lazy(|| format!("Failed to add to set: {} - {}", key, member))
+            .change_context(CacheError)?;
+
         Ok(())
     }
+
+    async fn increment_with_expiry(
+        &self,
+        key: &str,
+        ttl_seconds: i64,
+    ) -> Result<i64, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let (count,): (i64,) = redis::pipe()
+            .atomic()
+            .incr(key, 1)
+            .expire(key, ttl_seconds)
+            .ignore()
+            .query_async(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to increment: {}", key))
+            .change_context(CacheError)?;
+
+        Ok(count)
+    }
+
+    async fn get_or_set_with_expiry(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: i64,
+    ) -> Result<String, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let (stored,): (String,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .ignore()
+            .get(key)
+            .query_async(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to get or set: {}", key))
+            .change_context(CacheError)?;
+
+        Ok(stored)
+    }
+
+    async fn publish(&self, channel: &str, message: &str) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        con.publish::<_, _, ()>(channel, message)
+            .await
+            .attach_printable_lazy(|| format!("Failed to publish: {}", channel))
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn increment_sorted_sets(
+        &self,
+        keys: Vec<(String, i64)>,
+        member: &str,
+    ) -> Result<(), Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        let mut pipe = redis::pipe();
+        for (key, ttl_seconds) in &keys {
+            pipe.zincr(key, member, 1)
+                .ignore()
+                .expire(key, *ttl_seconds)
+                .ignore();
+        }
+
+        pipe.query_async::<()>(&mut con)
+            .await
+            .attach_printable_lazy(|| {
+                format!("Failed to increment sorted sets: {:?} - {}", keys, member)
+            })
+            .change_context(CacheError)?;
+
+        Ok(())
+    }
+
+    async fn top_of_sorted_sets(
+        &self,
+        keys: Vec<(String, f64)>,
+        destination: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, f64)>, Report<CacheError>> {
+        let mut con = self.0.clone();
+
+        // the union is only a scratch key, it is read in the same transaction and removed right after
+        let (top,): (Vec<(String, f64)>,) = redis::pipe()
+            .atomic()
+            .zunionstore_weights(destination, &keys)
+            .ignore()
+            .zrevrange_withscores(destination, 0, limit as isize - 1)
+            .del(destination)
+            .ignore()
+            .query_async(&mut con)
+            .await
+            .attach_printable_lazy(|| format!("Failed to rank sorted sets: {:?}", keys))
+            .change_context(CacheError)?;
+
+        Ok(top)
+    }
 }
 
 #[derive(Provide)]
 #[coi(provides dyn RedisClientWrapperTrait with RedisClientWrapper(self.0.clone()))]
-pub struct RedisClientProvider(Client);
+pub struct RedisClientProvider(ConnectionManager);
 
 impl RedisClientProvider {
-    pub fn new(client: Client) -> Self {
-        Self(client)
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self(connection)
     }
 }
 
-impl Inject for MockRedisClientWrapperTrait {}
\ No newline at end of file
+impl Inject for MockRedisClientWrapperTrait {}
@@ -3,6 +3,12 @@ use aws_config::Region;
 use aws_sdk_s3::config::{BehaviorVersion, Credentials};
 use aws_sdk_s3::{Client, Config};
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_STORAGE_DIRECTORY: &str = "storage";
+/// Images are overwritten in place when a code is regenerated, so caches only keep them for an hour.
+const DEFAULT_IMAGE_CACHE_CONTROL: &str = "public, max-age=3600";
 
 pub struct S3Config {
     pub access_key: String,
@@ -22,6 +28,46 @@ impl S3Config {
     }
 }
 
+/// Where objects are kept in the bucket and how clients reach them, read once at startup.
+pub struct BucketConfig {
+    pub name: String,
+    /// Put in front of every image key, so several deployments or environments can share a bucket.
+    pub key_prefix: String,
+    pub image_cache_control: String,
+    /// The CDN in front of the bucket, images are linked through it when set.
+    pub public_url: Option<String>,
+    /// Without a CDN images are linked with presigned URLs valid this long, for private buckets.
+    pub presign_expiry: Option<Duration>,
+}
+
+impl BucketConfig {
+    pub fn from_env() -> Self {
+        Self {
+            name: env::var("S3_BUCKET_NAME").expect("S3_BUCKET_NAME must be set"),
+            key_prefix: env::var("S3_KEY_PREFIX")
+                .map(|prefix| prefix.trim_matches('/').to_string())
+                .unwrap_or_default(),
+            image_cache_control: env::var("S3_IMAGE_CACHE_CONTROL")
+                .unwrap_or_else(|_| DEFAULT_IMAGE_CACHE_CONTROL.to_string()),
+            public_url: env::var("CLOUD_FRONT_URL").ok(),
+            presign_expiry: env::var("S3_PRESIGN_EXPIRY_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        }
+    }
+
+    /// The image's key in the bucket, an empty name gives the prefix every image key starts with.
+    pub fn key(&self, name: &str) -> String {
+        if self.key_prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.key_prefix, name)
+        }
+    }
+}
+
 pub async fn create_s3_client() -> Client {
     let config = S3Config::from_env();
 
@@ -40,3 +86,38 @@ pub async fn create_s3_client() -> Client {
 
     Client::from_conf(config)
 }
+
+/// With `STORAGE_BACKEND=filesystem` objects are kept in `STORAGE_DIRECTORY` instead of S3,
+/// so local development and tests need no AWS credentials.
+pub fn file_storage_directory() -> Option<PathBuf> {
+    if env::var("STORAGE_BACKEND").ok()? != "filesystem" {
+        return None;
+    }
+
+    Some(PathBuf::from(
+        env::var("STORAGE_DIRECTORY").unwrap_or_else(|_| DEFAULT_STORAGE_DIRECTORY.to_string()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::s3::config::BucketConfig;
+
+    fn bucket(key_prefix: &str) -> BucketConfig {
+        BucketConfig {
+            name: "bucket".to_string(),
+            key_prefix: key_prefix.to_string(),
+            image_cache_control: "public, max-age=3600".to_string(),
+            public_url: None,
+            presign_expiry: None,
+        }
+    }
+
+    #[test]
+    fn bucket_config_puts_the_prefix_in_front_of_keys() {
+        // Act & Assert
+        assert_eq!(bucket("").key("1234556.png"), "1234556.png");
+        assert_eq!(bucket("staging").key("1234556.png"), "staging/1234556.png");
+        assert_eq!(bucket("staging").key(""), "staging/");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::s3::error::S3Error;
+use crate::s3::s3_client::S3ClientWrapperTrait;
+use async_trait::async_trait;
+use coi::Inject;
+use error_stack::{Report, ResultExt};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use tempfile::Builder;
+use tokio::{fs, task};
+
+/// Marks a file that is still being written.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Keeps objects in a local directory under the same keys they would have in the bucket,
+/// so the stack runs without AWS credentials.
+#[derive(Inject)]
+pub struct FileStorage {
+    directory: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Keys are relative paths inside the directory, anything that could step out of it is refused.
+    fn path(&self, key: &str) -> Result<PathBuf, Report<S3Error>> {
+        let relative = Path::new(key);
+        let is_inside = relative.components().next().is_some()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !is_inside {
+            return Err(Report::new(S3Error).attach_printable(format!("Invalid key: {}", key)));
+        }
+
+        Ok(self.directory.join(relative))
+    }
+
+    /// Writes a temporary file of its own next to the target and renames it, so readers never see
+    /// a partially written file and concurrent writes of one key never share one.
+    async fn write(&self, body: Vec<u8>, key: &str) -> Result<(), Report<S3Error>> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .attach_printable_lazy(|| format!("Failed to create directory: {:?}", parent))
+                .change_context(S3Error)?;
+        }
+
+        let target = path.clone();
+        task::spawn_blocking(move || write_and_persist(&target, &body))
+            .await
+            .change_context(S3Error)?
+            .attach_printable_lazy(|| format!("Failed to write file: {:?}", path))
+            .change_context(S3Error)
+    }
+}
+
+fn write_and_persist(path: &Path, body: &[u8]) -> io::Result<()> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut partial = Builder::new()
+        .suffix(PARTIAL_SUFFIX)
+        .tempfile_in(directory)?;
+    partial.write_all(body)?;
+    partial.persist(path)?;
+    Ok(())
+}
+
+#[async_trait]
+impl S3ClientWrapperTrait for FileStorage {
+    async fn upload_image(
+        &self,
+        image: Vec<u8>,
+        file_name: &str,
+        _content_type: &str,
+    ) -> Result<(), Report<S3Error>> {
+        self.write(image, file_name)
+            .await
+            .attach_printable_lazy(|| format!("Failed to upload image: {}", file_name))
+    }
+
+    async fn upload_object(
+        &self,
+        body: Vec<u8>,
+        key: &str,
+        _content_type: &str,
+    ) -> Result<(), Report<S3Error>> {
+        self.write(body, key)
+            .await
+            .attach_printable_lazy(|| format!("Failed to upload object: {}", key))
+    }
+
+    async fn get_image(&self, file_name: &str) -> Result<Option<Vec<u8>>, Report<S3Error>> {
+        match fs::read(self.path(file_name)?).await {
+            Ok(image) => Ok(Some(image)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Report::new(e)
+                .attach_printable(format!("Failed to get image: {}", file_name))
+                .change_context(S3Error)),
+        }
+    }
+
+    /// The directory is not reachable from outside, the backend serves the images itself.
+    async fn image_url(&self, _file_name: &str) -> Result<Option<String>, Report<S3Error>> {
+        Ok(None)
+    }
+
+    async fn list_images(&self) -> Result<Vec<String>, Report<S3Error>> {
+        let mut entries = match fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            // nothing was uploaded yet
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(Report::new(e)
+                    .attach_printable(format!("Failed to list images in: {:?}", self.directory))
+                    .change_context(S3Error))
+            }
+        };
+
+        let mut file_names = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .attach_printable_lazy(|| format!("Failed to list images in: {:?}", self.directory))
+            .change_context(S3Error)?
+        {
+            let is_file = entry
+                .file_type()
+                .await
+                .is_ok_and(|file_type| file_type.is_file());
+            let Ok(file_name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if is_file && !file_name.ends_with(PARTIAL_SUFFIX) {
+                file_names.push(file_name);
+            }
+        }
+
+        Ok(file_names)
+    }
+
+    async fn delete_image(&self, file_name: &str) -> Result<(), Report<S3Error>> {
+        match fs::remove_file(self.path(file_name)?).await {
+            // like S3, deleting what is already gone succeeds
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(Report::new(e)
+                .attach_printable(format!("Failed to delete image: {}", file_name))
+                .change_context(S3Error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::s3::file_storage::FileStorage;
+    use crate::s3::s3_client::S3ClientWrapperTrait;
+    use tempfile::TempDir;
+
+    /// The directory is removed once the returned guard is dropped.
+    fn storage() -> (TempDir, FileStorage) {
+        let directory = TempDir::new().unwrap();
+        let storage = FileStorage::new(directory.path().to_path_buf());
+        (directory, storage)
+    }
+
+    #[tokio::test]
+    async fn file_storage_reads_back_uploaded_images() {
+        // Arrange
+        let (_directory, storage) = storage();
+
+        // Act
+        storage
+            .upload_image(b"png".to_vec(), "1234556.png", "image/png")
+            .await
+            .unwrap();
+        storage
+            .upload_object(
+                b"parquet".to_vec(),
+                "clicks/2025-03-01.parquet",
+                "application/vnd.apache.parquet",
+            )
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            storage.get_image("1234556.png").await.unwrap(),
+            Some(b"png".to_vec())
+        );
+        assert_eq!(storage.get_image("abc.png").await.unwrap(), None);
+        assert_eq!(storage.list_images().await.unwrap(), vec!["1234556.png"]);
+        assert_eq!(
+            storage
+                .get_image("clicks/2025-03-01.parquet")
+                .await
+                .unwrap(),
+            Some(b"parquet".to_vec())
+        );
+        storage.delete_image("1234556.png").await.unwrap();
+        storage.delete_image("1234556.png").await.unwrap();
+        assert!(storage.list_images().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_storage_refuses_keys_outside_its_directory() {
+        // Arrange
+        let (_directory, storage) = storage();
+
+        // Act & Assert
+        assert!(storage
+            .upload_image(b"png".to_vec(), "../1234556.png", "image/png")
+            .await
+            .is_err());
+        assert!(storage.get_image("/etc/passwd").await.is_err());
+        assert!(storage.get_image("").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn file_storage_concurrent_writes_of_one_key_both_succeed() {
+        // Arrange
+        let (_directory, storage) = storage();
+
+        // Act
+        let (first, second) = tokio::join!(
+            storage.upload_image(b"first".to_vec(), "1234556.png", "image/png"),
+            storage.upload_image(b"second".to_vec(), "1234556.png", "image/png"),
+        );
+
+        // Assert
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        let image = storage.get_image("1234556.png").await.unwrap().unwrap();
+        assert!(image == b"first" || image == b"second");
+        assert_eq!(storage.list_images().await.unwrap(), vec!["1234556.png"]);
+    }
+}
@@ -1,31 +1,67 @@
+use crate::s3::config::BucketConfig;
 use crate::s3::error::S3Error;
+use crate::s3::file_storage::FileStorage;
 use async_trait::async_trait;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::Client;
-use coi::{Inject, Provide};
+use coi::{Container, Inject, Provide};
 use error_stack::{Report, ResultExt};
 use mockall::automock;
-use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[async_trait]
 #[automock]
 pub trait S3ClientWrapperTrait: Inject {
-    async fn upload_image(&self, image: Vec<u8>, file_name: &str) -> Result<(), Report<S3Error>>;
+    async fn upload_image(
+        &self,
+        image: Vec<u8>,
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<(), Report<S3Error>>;
+    /// Stores the object under the key as given, the key prefix only applies to images.
+    async fn upload_object(
+        &self,
+        body: Vec<u8>,
+        key: &str,
+        content_type: &str,
+    ) -> Result<(), Report<S3Error>>;
+    /// The stored image, `None` when nothing was uploaded under the name.
+    async fn get_image(&self, file_name: &str) -> Result<Option<Vec<u8>>, Report<S3Error>>;
+    /// Where clients fetch the image from directly, `None` when only the backend can serve it.
+    async fn image_url(&self, file_name: &str) -> Result<Option<String>, Report<S3Error>>;
+    /// The names of the stored images, objects in nested folders such as click dumps are left out.
+    async fn list_images(&self) -> Result<Vec<String>, Report<S3Error>>;
+    async fn delete_image(&self, file_name: &str) -> Result<(), Report<S3Error>>;
 }
 #[derive(Inject)]
-pub struct S3ClientWrapper(Client);
+pub struct S3ClientWrapper {
+    client: Client,
+    bucket: BucketConfig,
+}
+
+impl S3ClientWrapper {
+    pub fn new(client: Client, bucket: BucketConfig) -> Self {
+        Self { client, bucket }
+    }
+}
 
 #[async_trait]
 impl S3ClientWrapperTrait for S3ClientWrapper {
-    async fn upload_image(&self, image: Vec<u8>, file_name: &str) -> Result<(), Report<S3Error>> {
-        let bucket_name = env::var("S3_BUCKET_NAME").expect("S3_BUCKET_NAME must be set");
-        let body = ByteStream::from(image);
-
-        self.0
+    async fn upload_image(
+        &self,
+        image: Vec<u8>,
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<(), Report<S3Error>> {
+        self.client
             .put_object()
-            .bucket(bucket_name)
-            .key(file_name)
-            .body(body)
+            .bucket(&self.bucket.name)
+            .key(self.bucket.key(file_name))
+            .content_type(content_type)
+            .cache_control(&self.bucket.image_cache_control)
+            .body(ByteStream::from(image))
             .send()
             .await
             .attach_printable_lazy(|| format!("Failed to upload image: {}", file_name))
@@ -33,15 +69,146 @@ impl S3ClientWrapperTrait for S3ClientWrapper {
 
         Ok(())
     }
+
+    async fn upload_object(
+        &self,
+        body: Vec<u8>,
+        key: &str,
+        content_type: &str,
+    ) -> Result<(), Report<S3Error>> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket.name)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .attach_printable_lazy(|| format!("Failed to upload object: {}", key))
+            .change_context(S3Error)?;
+
+        Ok(())
+    }
+
+    async fn get_image(&self, file_name: &str) -> Result<Option<Vec<u8>>, Report<S3Error>> {
+        let output = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket.name)
+            .key(self.bucket.key(file_name))
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => {
+                return Err(Report::new(e)
+                    .attach_printable(format!("Failed to get image: {}", file_name))
+                    .change_context(S3Error))
+            }
+        };
+
+        let body = output
+            .body
+            .collect()
+            .await
+            .attach_printable_lazy(|| format!("Failed to read image: {}", file_name))
+            .change_context(S3Error)?;
+
+        Ok(Some(body.into_bytes().to_vec()))
+    }
+
+    async fn image_url(&self, file_name: &str) -> Result<Option<String>, Report<S3Error>> {
+        let key = self.bucket.key(file_name);
+        if let Some(public_url) = &self.bucket.public_url {
+            return Ok(Some(format!("{}/{}", public_url, key)));
+        }
+        let Some(expiry) = self.bucket.presign_expiry else {
+            return Ok(None);
+        };
+
+        let presigning_config = PresigningConfig::expires_in(expiry)
+            .attach_printable_lazy(|| format!("Invalid presigned url expiry: {:?}", expiry))
+            .change_context(S3Error)?;
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket.name)
+            .key(key)
+            .presigned(presigning_config)
+            .await
+            .attach_printable_lazy(|| format!("Failed to presign image url: {}", file_name))
+            .change_context(S3Error)?;
+
+        Ok(Some(request.uri().to_string()))
+    }
+
+    async fn list_images(&self) -> Result<Vec<String>, Report<S3Error>> {
+        let prefix = self.bucket.key("");
+        let mut file_names = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            // the delimiter groups nested keys into common prefixes, only top level objects are listed
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket.name)
+                .prefix(&prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .attach_printable_lazy(|| format!("Failed to list images under: {:?}", prefix))
+                .change_context(S3Error)?;
+
+            file_names.extend(
+                output
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key()?.strip_prefix(&prefix))
+                    .map(str::to_string),
+            );
+            match output.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+
+        Ok(file_names)
+    }
+
+    async fn delete_image(&self, file_name: &str) -> Result<(), Report<S3Error>> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket.name)
+            .key(self.bucket.key(file_name))
+            .send()
+            .await
+            .attach_printable_lazy(|| format!("Failed to delete image: {}", file_name))
+            .change_context(S3Error)?;
+
+        Ok(())
+    }
 }
 
-#[derive(Provide)]
-#[coi(provides dyn S3ClientWrapperTrait with S3ClientWrapper(self.0.clone()))]
-pub struct S3ClientProvider(Client);
+/// Provides either the S3 client or, for offline development, the filesystem storage.
+pub struct S3ClientProvider(Arc<dyn S3ClientWrapperTrait>);
 
 impl S3ClientProvider {
-    pub fn new(client: Client) -> Self {
-        Self(client)
+    pub fn new(client: Client, bucket: BucketConfig) -> Self {
+        Self(Arc::new(S3ClientWrapper::new(client, bucket)))
+    }
+
+    pub fn file_system(directory: PathBuf) -> Self {
+        Self(Arc::new(FileStorage::new(directory)))
+    }
+}
+
+impl Provide for S3ClientProvider {
+    type Output = dyn S3ClientWrapperTrait;
+
+    fn provide(&self, _: &Container) -> coi::Result<Arc<Self::Output>> {
+        Ok(self.0.clone())
     }
 }
 
@@ -0,0 +1,27 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::conversion_models::ConversionRequest;
+use url_shortener_application::services::conversion_service::ConversionServiceTrait;
+
+#[post("/conversions")]
+#[inject]
+pub async fn create_conversion(
+    req: HttpRequest,
+    request: web::Json<ConversionRequest>,
+    #[inject] conversion_service: Arc<dyn ConversionServiceTrait>,
+) -> HttpResponse {
+    let result = conversion_service
+        .record_conversion(bearer_token(&req), request.into_inner())
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
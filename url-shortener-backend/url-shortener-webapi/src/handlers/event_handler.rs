@@ -0,0 +1,94 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use futures::future;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use std::convert::Infallible;
+use std::time::Duration;
+use url_shortener_application::models::click_event_models::{ClickEventModel, ClickEventQuery};
+use url_shortener_application::models::errors::ApiError;
+use url_shortener_application::services::click_event_service::ClickEventServiceTrait;
+
+const KEEP_ALIVE_INTERVAL_SECS: u64 = 15;
+
+#[get("/{short_url}/events")]
+#[inject]
+pub async fn get_url_events(
+    short_url: web::Path<String>,
+    query: web::Query<ClickEventQuery>,
+    #[inject] click_event_service: Arc<dyn ClickEventServiceTrait>,
+) -> HttpResponse {
+    let result = click_event_service
+        .subscribe(
+            None,
+            Some(short_url.into_inner()),
+            query.include_bots.unwrap_or_default(),
+        )
+        .await;
+
+    event_stream_response(result)
+}
+
+/// Streams every link's clicks to an admin, the deployment is treated as a single workspace.
+#[get("/events")]
+#[inject]
+pub async fn get_all_events(
+    req: HttpRequest,
+    query: web::Query<ClickEventQuery>,
+    #[inject] click_event_service: Arc<dyn ClickEventServiceTrait>,
+) -> HttpResponse {
+    let result = click_event_service
+        .subscribe(
+            bearer_token(&req),
+            None,
+            query.include_bots.unwrap_or_default(),
+        )
+        .await;
+
+    event_stream_response(result)
+}
+
+fn event_stream_response(
+    result: Result<BoxStream<'static, ClickEventModel>, ApiError>,
+) -> HttpResponse {
+    match result {
+        Ok(events) => {
+            // the end of the subscription is marked with None so the response can end with it
+            let events = events
+                .filter_map(|event| async move {
+                    serde_json::to_string(&event)
+                        .ok()
+                        .map(|data| Some(Bytes::from(format!("event: click\ndata: {}\n\n", data))))
+                })
+                .chain(stream::once(future::ready(None)));
+            // comments keep proxies from closing the connection while no one clicks
+            let keep_alive = stream::unfold(
+                actix_web::rt::time::interval(Duration::from_secs(KEEP_ALIVE_INTERVAL_SECS)),
+                |mut interval| async move {
+                    interval.tick().await;
+                    Some((Some(Bytes::from_static(b": keep-alive\n\n")), interval))
+                },
+            );
+
+            HttpResponse::Ok()
+                .content_type("text/event-stream")
+                .insert_header((header::CACHE_CONTROL, "no-cache"))
+                .insert_header(("X-Accel-Buffering", "no"))
+                .streaming(
+                    stream::select(events, keep_alive)
+                        .take_while(|chunk| future::ready(chunk.is_some()))
+                        .filter_map(future::ready)
+                        .map(Ok::<_, Infallible>),
+                )
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
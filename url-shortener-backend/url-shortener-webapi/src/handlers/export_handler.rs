@@ -0,0 +1,70 @@
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{error, get, web, HttpResponse};
+use coi_actix_web::inject;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use url_shortener_application::models::errors::ApiError;
+use url_shortener_application::models::export_models::{ExportFormat, ExportQuery};
+use url_shortener_application::services::export_service::ExportServiceTrait;
+
+#[get("/{short_url}/export")]
+#[inject]
+pub async fn export_clicks(
+    short_url: web::Path<String>,
+    query: web::Query<ExportQuery>,
+    #[inject] export_service: Arc<dyn ExportServiceTrait>,
+) -> HttpResponse {
+    let format = query.format.unwrap_or_default();
+    let result = export_service
+        .export_clicks(short_url.as_str(), query.into_inner())
+        .await;
+
+    export_response(result, short_url.as_str(), format)
+}
+
+#[get("/campaigns/{campaign}/export")]
+#[inject]
+pub async fn export_campaign_clicks(
+    campaign: web::Path<String>,
+    query: web::Query<ExportQuery>,
+    #[inject] export_service: Arc<dyn ExportServiceTrait>,
+) -> HttpResponse {
+    let format = query.format.unwrap_or_default();
+    let result = export_service
+        .export_campaign_clicks(campaign.as_str(), query.into_inner())
+        .await;
+
+    export_response(result, campaign.as_str(), format)
+}
+
+fn export_response(
+    result: Result<BoxStream<'static, Result<String, ApiError>>, ApiError>,
+    name: &str,
+    format: ExportFormat,
+) -> HttpResponse {
+    match result {
+        Ok(chunks) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}-clicks.{}\"",
+                    name,
+                    format.extension()
+                ),
+            ))
+            // a failure mid-export aborts the response so a truncated file is never mistaken for a complete one
+            .streaming(chunks.map(|chunk| {
+                chunk
+                    .map(Bytes::from)
+                    .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+            })),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::response_model::HistoryResponseModel;
+use url_shortener_application::services::history_service::HistoryServiceTrait;
+
+#[get("/{short_url}/history")]
+#[inject]
+pub async fn get_history(
+    short_url: web::Path<String>,
+    #[inject] history_service: Arc<dyn HistoryServiceTrait>,
+) -> HttpResponse {
+    let result = history_service.get_history(short_url.as_str()).await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(ApiResponseModel::<Vec<HistoryResponseModel>>::success(
+            Some(res),
+        )),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[post("/{short_url}/history/{id}/rollback")]
+#[inject]
+pub async fn rollback_history(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    #[inject] history_service: Arc<dyn HistoryServiceTrait>,
+) -> HttpResponse {
+    let (short_url, id) = path.into_inner();
+    let result = history_service
+        .rollback(&short_url, id, bearer_token(&req))
+        .await;
+
+    match result {
+        Ok(res) => {
+            HttpResponse::Ok().json(ApiResponseModel::<HistoryResponseModel>::success(Some(res)))
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
@@ -1 +1,10 @@
+pub mod conversion_handler;
+pub mod event_handler;
+pub mod export_handler;
+pub mod history_handler;
+pub mod qr_backfill_handler;
+pub mod qr_handler;
+pub mod schedule_handler;
+pub mod stats_handler;
+pub mod top_handler;
 pub mod url_handler;
@@ -0,0 +1,99 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::qr_models::{QrBackfillRequest, QrReconcileRequest};
+use url_shortener_application::models::response_model::{
+    QrBackfillResponseModel, QrReconcileResponseModel,
+};
+use url_shortener_application::services::qr_backfill_service::QrBackfillServiceTrait;
+use url_shortener_application::services::qr_reconcile_service::QrReconcileServiceTrait;
+
+#[post("/qr/backfill")]
+#[inject]
+pub async fn start_qr_backfill(
+    req: HttpRequest,
+    request: web::Json<QrBackfillRequest>,
+    #[inject] qr_backfill_service: Arc<dyn QrBackfillServiceTrait>,
+) -> HttpResponse {
+    let result = qr_backfill_service
+        .start_backfill(bearer_token(&req), request.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Accepted().json(
+            ApiResponseModel::<QrBackfillResponseModel>::success(Some(res)),
+        ),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[get("/qr/backfill/{id}")]
+#[inject]
+pub async fn get_qr_backfill(
+    req: HttpRequest,
+    id: web::Path<i64>,
+    #[inject] qr_backfill_service: Arc<dyn QrBackfillServiceTrait>,
+) -> HttpResponse {
+    let result = qr_backfill_service
+        .get_backfill(bearer_token(&req), id.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(ApiResponseModel::<QrBackfillResponseModel>::success(
+            Some(res),
+        )),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[post("/qr/reconcile")]
+#[inject]
+pub async fn reconcile_qr(
+    req: HttpRequest,
+    request: web::Json<QrReconcileRequest>,
+    #[inject] qr_reconcile_service: Arc<dyn QrReconcileServiceTrait>,
+) -> HttpResponse {
+    let result = qr_reconcile_service
+        .start_reconcile(bearer_token(&req), request.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Accepted().json(
+            ApiResponseModel::<QrReconcileResponseModel>::success(Some(res)),
+        ),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[get("/qr/reconcile/{id}")]
+#[inject]
+pub async fn get_qr_reconcile(
+    req: HttpRequest,
+    id: web::Path<i64>,
+    #[inject] qr_reconcile_service: Arc<dyn QrReconcileServiceTrait>,
+) -> HttpResponse {
+    let result = qr_reconcile_service
+        .get_reconcile(bearer_token(&req), id.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(ApiResponseModel::<QrReconcileResponseModel>::success(
+            Some(res),
+        )),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
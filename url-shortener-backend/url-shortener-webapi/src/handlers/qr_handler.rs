@@ -0,0 +1,306 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{delete, error, get, post, put, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use futures::StreamExt;
+use url_shortener_application::models::errors::ApiError;
+use url_shortener_application::models::qr_models::{
+    QrExportRequest, QrImageModel, QrImageQuery, QrOptions,
+};
+use url_shortener_application::models::response_model::CreateResponseModel;
+use url_shortener_application::services::qr_service::QrServiceTrait;
+
+/// Clients revalidate after an hour, the ETag makes that a cheap 304 unless the code changed.
+const QR_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// If-None-Match compares weakly, so a `W/` prefix added by a proxy still matches.
+fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+}
+
+fn image_response(req: &HttpRequest, result: Result<QrImageModel, ApiError>) -> HttpResponse {
+    match result {
+        Ok(image) if etag_matches(req, &image.etag) => HttpResponse::NotModified()
+            .insert_header((header::ETAG, image.etag))
+            .insert_header((header::CACHE_CONTROL, QR_CACHE_CONTROL))
+            .finish(),
+        Ok(image) => HttpResponse::Ok()
+            .content_type(image.content_type)
+            .insert_header((header::ETAG, image.etag))
+            .insert_header((header::CACHE_CONTROL, QR_CACHE_CONTROL))
+            .body(image.body),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[get("/{short_url}/qr")]
+#[inject]
+pub async fn get_qr(
+    req: HttpRequest,
+    short_url: web::Path<String>,
+    query: web::Query<QrImageQuery>,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let result = qr_service
+        .get_qr_image(short_url.as_str(), query.into_inner())
+        .await;
+
+    image_response(&req, result)
+}
+
+/// Pre-rendered images kept by the filesystem storage, which has no CDN to serve them.
+#[get("/qr/files/{file_name}")]
+#[inject]
+pub async fn get_stored_qr(
+    req: HttpRequest,
+    file_name: web::Path<String>,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let result = qr_service.get_stored_image(file_name.as_str()).await;
+
+    image_response(&req, result)
+}
+
+#[post("/{short_url}/qr")]
+#[inject]
+pub async fn regenerate_qr(
+    req: HttpRequest,
+    short_url: web::Path<String>,
+    request: web::Json<QrOptions>,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let result = qr_service
+        .regenerate_qr(bearer_token(&req), short_url.as_str(), request.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => {
+            HttpResponse::Ok().json(ApiResponseModel::<CreateResponseModel>::success(Some(res)))
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[put("/{short_url}/qr/logo")]
+#[inject]
+pub async fn upload_qr_logo(
+    req: HttpRequest,
+    short_url: web::Path<String>,
+    body: web::Bytes,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let result = qr_service
+        .upload_logo(bearer_token(&req), short_url.as_str(), body.to_vec())
+        .await;
+
+    match result {
+        Ok(res) => {
+            HttpResponse::Ok().json(ApiResponseModel::<CreateResponseModel>::success(Some(res)))
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[delete("/{short_url}/qr/logo")]
+#[inject]
+pub async fn delete_qr_logo(
+    req: HttpRequest,
+    short_url: web::Path<String>,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let result = qr_service
+        .remove_logo(bearer_token(&req), short_url.as_str())
+        .await;
+
+    match result {
+        Ok(res) => {
+            HttpResponse::Ok().json(ApiResponseModel::<CreateResponseModel>::success(Some(res)))
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[post("/qr/export")]
+#[inject]
+pub async fn export_qr(
+    request: web::Json<QrExportRequest>,
+    #[inject] qr_service: Arc<dyn QrServiceTrait>,
+) -> HttpResponse {
+    let format = request.format.unwrap_or_default();
+    let result = qr_service.export_qr(request.into_inner()).await;
+
+    match result {
+        Ok(chunks) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"qr-codes.{}\"", format.extension()),
+            ))
+            // a failure mid-export aborts the response so a truncated file is never mistaken for a complete one
+            .streaming(chunks.map(|chunk| {
+                chunk
+                    .map(Bytes::from)
+                    .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+            })),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::handlers::qr_handler::{delete_qr_logo, upload_qr_logo};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use coi::{container, Container};
+    use coi_actix_web::AppExt;
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_application::qr::cache::{MockQrImageCacheTrait, QrImageCacheTrait};
+    use url_shortener_application::services::qr_service::QrServiceProvider;
+    use url_shortener_database::repositories::qr_options_repository::{
+        MockQrOptionsRepositoryTrait, QrOptionsRepositoryTrait,
+    };
+    use url_shortener_database::repositories::qr_upload_repository::{
+        MockQrUploadRepositoryTrait, QrUploadRepositoryTrait,
+    };
+    use url_shortener_database::repositories::url_repository::{
+        MockUrlRepositoryTrait, UrlRepositoryTrait,
+    };
+    use url_shortener_infrastructure::s3::s3_client::{
+        MockS3ClientWrapperTrait, S3ClientWrapperTrait,
+    };
+
+    const TEST_TOKEN: &str = "admin-secret";
+
+    /// The repositories and storage expect no calls, a request that gets past the token check fails the test.
+    fn container() -> Container {
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        container! {
+            qr_service => QrServiceProvider; scoped,
+            url_repository => |_: &Container| -> coi::Result<Arc<dyn UrlRepositoryTrait>> {
+                Ok(Arc::new(MockUrlRepositoryTrait::new()))
+            }; scoped,
+            qr_options_repository => |_: &Container| -> coi::Result<Arc<dyn QrOptionsRepositoryTrait>> {
+                Ok(Arc::new(MockQrOptionsRepositoryTrait::new()))
+            }; scoped,
+            qr_upload_repository => |_: &Container| -> coi::Result<Arc<dyn QrUploadRepositoryTrait>> {
+                Ok(Arc::new(MockQrUploadRepositoryTrait::new()))
+            }; scoped,
+            s3_client_wrapper => |_: &Container| -> coi::Result<Arc<dyn S3ClientWrapperTrait>> {
+                Ok(Arc::new(MockS3ClientWrapperTrait::new()))
+            }; scoped,
+            qr_image_cache => |_: &Container| -> coi::Result<Arc<dyn QrImageCacheTrait>> {
+                Ok(Arc::new(MockQrImageCacheTrait::new()))
+            }; scoped,
+        }
+    }
+
+    #[actix_web::test]
+    async fn upload_qr_logo_without_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(upload_qr_logo),
+        )
+        .await;
+        let req = test::TestRequest::put()
+            .uri("/1234556/qr/logo")
+            .set_payload("logo")
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn upload_qr_logo_with_wrong_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(upload_qr_logo),
+        )
+        .await;
+        let req = test::TestRequest::put()
+            .uri("/1234556/qr/logo")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .set_payload("logo")
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn delete_qr_logo_without_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(delete_qr_logo),
+        )
+        .await;
+        let req = test::TestRequest::delete()
+            .uri("/1234556/qr/logo")
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn delete_qr_logo_with_wrong_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(delete_qr_logo),
+        )
+        .await;
+        let req = test::TestRequest::delete()
+            .uri("/1234556/qr/logo")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::implementations::auth::bearer_token;
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::response_model::ScheduleResponseModel;
+use url_shortener_application::models::url_models::CreateScheduleRequest;
+use url_shortener_application::services::schedule_service::ScheduleServiceTrait;
+
+#[post("/{short_url}/schedules")]
+#[inject]
+pub async fn create_schedule(
+    req: HttpRequest,
+    short_url: web::Path<String>,
+    request: web::Json<CreateScheduleRequest>,
+    #[inject] schedule_service: Arc<dyn ScheduleServiceTrait>,
+) -> HttpResponse {
+    let result = schedule_service
+        .create_schedule(bearer_token(&req), short_url.as_str(), request.0)
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Created().json(
+            ApiResponseModel::<ScheduleResponseModel>::success(Some(res)),
+        ),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[get("/{short_url}/schedules")]
+#[inject]
+pub async fn get_schedules(
+    short_url: web::Path<String>,
+    #[inject] schedule_service: Arc<dyn ScheduleServiceTrait>,
+) -> HttpResponse {
+    let result = schedule_service
+        .get_pending_schedules(short_url.as_str())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(
+            ApiResponseModel::<Vec<ScheduleResponseModel>>::success(Some(res)),
+        ),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[delete("/{short_url}/schedules/{id}")]
+#[inject]
+pub async fn delete_schedule(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    #[inject] schedule_service: Arc<dyn ScheduleServiceTrait>,
+) -> HttpResponse {
+    let (short_url, id) = path.into_inner();
+    let result = schedule_service
+        .cancel_schedule(bearer_token(&req), &short_url, id)
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::handlers::schedule_handler::{create_schedule, delete_schedule};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use coi::{container, Container};
+    use coi_actix_web::AppExt;
+    use std::env;
+    use std::sync::Arc;
+    use url_shortener_application::services::schedule_service::ScheduleServiceProvider;
+    use url_shortener_database::repositories::schedule_repository::{
+        MockScheduleRepositoryTrait, ScheduleRepositoryTrait,
+    };
+    use url_shortener_database::repositories::url_repository::{
+        MockUrlRepositoryTrait, UrlRepositoryTrait,
+    };
+    use url_shortener_infrastructure::redis::redis_client::{
+        MockRedisClientWrapperTrait, RedisClientWrapperTrait,
+    };
+
+    const TEST_TOKEN: &str = "admin-secret";
+    const TEST_SCHEDULE: &str =
+        r#"{"url":"https://www.google.com","scheduledAt":"2100-01-01T00:00:00Z"}"#;
+
+    /// The repositories expect no calls, a request that gets past the token check fails the test.
+    fn container() -> Container {
+        env::set_var("ADMIN_TOKEN", TEST_TOKEN);
+        container! {
+            schedule_service => ScheduleServiceProvider; scoped,
+            schedule_repository => |_: &Container| -> coi::Result<Arc<dyn ScheduleRepositoryTrait>> {
+                Ok(Arc::new(MockScheduleRepositoryTrait::new()))
+            }; scoped,
+            url_repository => |_: &Container| -> coi::Result<Arc<dyn UrlRepositoryTrait>> {
+                Ok(Arc::new(MockUrlRepositoryTrait::new()))
+            }; scoped,
+            redis_client_wrapper => |_: &Container| -> coi::Result<Arc<dyn RedisClientWrapperTrait>> {
+                Ok(Arc::new(MockRedisClientWrapperTrait::new()))
+            }; scoped,
+        }
+    }
+
+    #[actix_web::test]
+    async fn create_schedule_without_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(create_schedule),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/1234556/schedules")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(TEST_SCHEDULE)
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn create_schedule_with_wrong_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(create_schedule),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/1234556/schedules")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(TEST_SCHEDULE)
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn delete_schedule_without_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(delete_schedule),
+        )
+        .await;
+        let req = test::TestRequest::delete()
+            .uri("/1234556/schedules/1")
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn delete_schedule_with_wrong_token_returns_unauthorized() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .register_container(container())
+                .service(delete_schedule),
+        )
+        .await;
+        let req = test::TestRequest::delete()
+            .uri("/1234556/schedules/1")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{get, web, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::stats_models::{StatsQuery, StatsResponseModel};
+use url_shortener_application::services::stats_service::StatsServiceTrait;
+
+#[get("/{short_url}/stats")]
+#[inject]
+pub async fn get_stats(
+    short_url: web::Path<String>,
+    query: web::Query<StatsQuery>,
+    #[inject] stats_service: Arc<dyn StatsServiceTrait>,
+) -> HttpResponse {
+    let result = stats_service
+        .get_stats(short_url.as_str(), query.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => {
+            HttpResponse::Ok().json(ApiResponseModel::<StatsResponseModel>::success(Some(res)))
+        }
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::implementations::errors::FormatErrorTrait;
+use crate::models::api_response_model::ApiResponseModel;
+use actix_web::{get, web, HttpResponse};
+use coi_actix_web::inject;
+use url_shortener_application::models::top_models::{
+    TopLinksQuery, TopLinksResponseModel, TrendingLinkModel, TrendingLinksQuery,
+};
+use url_shortener_application::services::top_links_service::TopLinksServiceTrait;
+
+#[get("/top")]
+#[inject]
+pub async fn get_top_links(
+    query: web::Query<TopLinksQuery>,
+    #[inject] top_links_service: Arc<dyn TopLinksServiceTrait>,
+) -> HttpResponse {
+    let result = top_links_service.get_top_links(query.into_inner()).await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(ApiResponseModel::<TopLinksResponseModel>::success(
+            Some(res),
+        )),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
+
+#[get("/trending")]
+#[inject]
+pub async fn get_trending_links(
+    query: web::Query<TrendingLinksQuery>,
+    #[inject] top_links_service: Arc<dyn TopLinksServiceTrait>,
+) -> HttpResponse {
+    let result = top_links_service
+        .get_trending_links(query.into_inner())
+        .await;
+
+    match result {
+        Ok(res) => HttpResponse::Ok().json(ApiResponseModel::<Vec<TrendingLinkModel>>::success(
+            Some(res),
+        )),
+        Err(e) => {
+            let (status, e) = e.get_message_status();
+            HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
+        }
+    }
+}
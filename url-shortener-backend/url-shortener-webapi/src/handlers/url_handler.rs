@@ -1,9 +1,14 @@
+use crate::implementations::client_ip::client_ip;
 use crate::implementations::errors::FormatErrorTrait;
 use crate::models::api_response_model::ApiResponseModel;
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::http::header;
+use actix_web::http::Method;
+use actix_web::{post, route, web, HttpRequest, HttpResponse};
 use coi_actix_web::inject;
+use url_shortener_application::models::click_models::{ClickRequest, ClickSource};
 use url_shortener_application::models::response_model::CreateResponseModel;
 use url_shortener_application::models::url_models::CreateUrlRequest;
+use url_shortener_application::services::click_service::ClickServiceTrait;
 use url_shortener_application::services::url_service::UrlServiceTrait;
 
 #[post("")]
@@ -24,20 +29,51 @@ pub async fn create_url(
     }
 }
 
-#[get("/{short_url}")]
+#[route("/{short_url}", method = "GET", method = "HEAD")]
 #[inject]
 pub async fn get_url(
+    req: HttpRequest,
     short_url: web::Path<String>,
     #[inject] url_service: Arc<dyn UrlServiceTrait>,
+    #[inject] click_service: Arc<dyn ClickServiceTrait>,
 ) -> HttpResponse {
-    let result = url_service.get_long_url(short_url.as_str()).await;
+    let mut request = click_request(&req);
+    let result = url_service
+        .get_long_url(short_url.as_str(), request.do_not_track)
+        .await;
     match result {
-        Ok(res) => HttpResponse::Found()
-            .append_header(("Location", res))
-            .finish(),
+        Ok(res) => {
+            request.click_id = res.click_id.clone();
+            // the click is counted after the response, the redirect never waits for redis
+            click_service.record_click(short_url.as_str(), &res.url, request);
+            HttpResponse::Found()
+                .append_header(("Location", res.location()))
+                .finish()
+        }
         Err(e) => {
             let (status, e) = e.get_message_status();
             HttpResponse::build(status).json(ApiResponseModel::<String>::failure(Some(e)))
         }
     }
 }
+
+fn click_request(req: &HttpRequest) -> ClickRequest {
+    let header_value = |name: header::HeaderName| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+    };
+
+    ClickRequest {
+        referrer: header_value(header::REFERER),
+        user_agent: header_value(header::USER_AGENT),
+        ip: client_ip(req),
+        accept_language: header_value(header::ACCEPT_LANGUAGE),
+        is_head_request: req.method() == Method::HEAD,
+        do_not_track: header_value(header::DNT).as_deref() == Some("1")
+            || header_value(header::HeaderName::from_static("sec-gpc")).as_deref() == Some("1"),
+        source: ClickSource::from_query(req.query_string()),
+        click_id: None,
+    }
+}
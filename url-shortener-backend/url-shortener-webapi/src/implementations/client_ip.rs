@@ -0,0 +1,86 @@
+use actix_web::HttpRequest;
+use std::env;
+use std::net::IpAddr;
+
+/// The address of the visitor: the connecting peer, unless the peer is one of the proxies listed
+/// in `TRUSTED_PROXIES`, then the last address in `X-Forwarded-For` no trusted proxy added.
+pub(crate) fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    let trusted = trusted_proxies();
+    if !trusted.contains(&peer) {
+        return Some(peer.to_string());
+    }
+
+    // every proxy appends the address it saw, anything left of the first untrusted one may be forged
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let client = forwarded
+        .into_iter()
+        .rev()
+        .find(|ip| {
+            ip.parse::<IpAddr>()
+                .map_or(true, |ip| !trusted.contains(&ip))
+        })
+        .map(str::to_owned);
+
+    client.or_else(|| Some(peer.to_string()))
+}
+
+fn trusted_proxies() -> Vec<IpAddr> {
+    env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client_ip;
+    use actix_web::test::TestRequest;
+    use std::env;
+
+    const TEST_PROXY: &str = "10.0.0.1";
+
+    fn request(peer: &str, forwarded_for: Option<&str>) -> TestRequest {
+        env::set_var("TRUSTED_PROXIES", format!("{}, 10.0.0.2", TEST_PROXY));
+        let request = TestRequest::default().peer_addr(format!("{}:5555", peer).parse().unwrap());
+        match forwarded_for {
+            Some(value) => request.insert_header(("x-forwarded-for", value)),
+            None => request,
+        }
+    }
+
+    #[test]
+    fn client_ip_ignores_forwarded_for_from_untrusted_peer() {
+        // Arrange
+        let req = request("203.0.113.7", Some("198.51.100.1")).to_http_request();
+
+        // Act & Assert
+        assert_eq!(client_ip(&req).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn client_ip_behind_trusted_proxies_skips_forged_entries() {
+        // Arrange
+        let req =
+            request(TEST_PROXY, Some("198.51.100.1, 203.0.113.7, 10.0.0.2")).to_http_request();
+
+        // Act & Assert
+        assert_eq!(client_ip(&req).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn client_ip_behind_trusted_proxy_without_header_uses_peer() {
+        // Arrange
+        let req = request(TEST_PROXY, None).to_http_request();
+
+        // Act & Assert
+        assert_eq!(client_ip(&req).as_deref(), Some(TEST_PROXY));
+    }
+}
@@ -10,6 +10,7 @@ impl FormatErrorTrait for ApiError {
         match *self {
             ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message.to_owned()),
             ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message.to_owned()),
+            ApiError::Unauthorized(message) => (StatusCode::UNAUTHORIZED, message.to_owned()),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Something went wrong".to_owned(),
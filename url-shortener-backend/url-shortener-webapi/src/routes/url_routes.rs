@@ -1,8 +1,47 @@
+use crate::handlers::conversion_handler::create_conversion;
+use crate::handlers::event_handler::{get_all_events, get_url_events};
+use crate::handlers::export_handler::{export_campaign_clicks, export_clicks};
+use crate::handlers::history_handler::{get_history, rollback_history};
+use crate::handlers::qr_backfill_handler::{
+    get_qr_backfill, get_qr_reconcile, reconcile_qr, start_qr_backfill,
+};
+use crate::handlers::qr_handler::{
+    delete_qr_logo, export_qr, get_qr, get_stored_qr, regenerate_qr, upload_qr_logo,
+};
+use crate::handlers::schedule_handler::{create_schedule, delete_schedule, get_schedules};
+use crate::handlers::stats_handler::get_stats;
+use crate::handlers::top_handler::{get_top_links, get_trending_links};
 use crate::handlers::url_handler::{create_url, get_url};
 use actix_web::web;
 
 pub(crate) fn register_url_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api/url").service(create_url));
+    cfg.service(
+        web::scope("/api/url")
+            .service(create_url)
+            .service(create_conversion)
+            .service(get_top_links)
+            .service(get_trending_links)
+            .service(create_schedule)
+            .service(get_schedules)
+            .service(delete_schedule)
+            .service(get_history)
+            .service(rollback_history)
+            .service(get_stats)
+            .service(start_qr_backfill)
+            .service(get_qr_backfill)
+            .service(reconcile_qr)
+            .service(get_qr_reconcile)
+            .service(export_qr)
+            .service(get_stored_qr)
+            .service(get_qr)
+            .service(regenerate_qr)
+            .service(upload_qr_logo)
+            .service(delete_qr_logo)
+            .service(get_all_events)
+            .service(get_url_events)
+            .service(export_campaign_clicks)
+            .service(export_clicks),
+    );
 
     cfg.service(get_url);
 }